@@ -27,19 +27,60 @@ api_http.interceptors.request.use(config => {
     return config;
 });
 
+// In-flight refresh request, shared by all requests that fail while the access
+// token is expired so that the single-use refresh token is only spent once.
+let pendingRefresh = null;
+
+async function refreshSession(refreshToken) {
+    if (!pendingRefresh) {
+        pendingRefresh = api_http.post('auth/refresh', { refresh_token: refreshToken })
+            .then(({ data }) => {
+                const s = settings.load();
+                s.auth = { ...s.auth, token: data.token, refreshToken: data.refresh_token };
+                settings.save(s);
+            })
+            .finally(() => {
+                pendingRefresh = null;
+            });
+    }
+
+    return pendingRefresh;
+}
+
 api_http.interceptors.response.use(
     r => {
         updateUiSettings(r);
         return r;
     },
-    r => {
+    async r => {
         if (r.response) {
             updateUiSettings(r.response);
         } else if (r.headers) {
             updateUiSettings(r);
         }
 
-        if ((r.status || r.response?.status) === 401) {
+        // Failed refreshes are handled by the request that triggered them.
+        if ((r.status || r.response?.status) === 401 && r.config?.url !== 'auth/refresh') {
+            const config = r.config;
+            const refreshToken = settings.load().auth?.refreshToken;
+
+            if (refreshToken && config && !config.ctRetried) {
+                try {
+                    await refreshSession(refreshToken);
+                } catch (e) {
+                    // Another tab may have already used the refresh token; if
+                    // so, it has stored the new one and we can carry on.
+                    if (settings.load().auth?.refreshToken === refreshToken) {
+                        const s = settings.load();
+                        s.auth = {};
+                        settings.save(s);
+                        throw r;
+                    }
+                }
+
+                return api_http.request({ ...config, ctRetried: true });
+            }
+
             const s = settings.load();
             s.auth = {};
             settings.save(s);
@@ -61,8 +102,16 @@ export async function getDashboardTrackers() {
     return api_http.get('/dashboard/tracker');
 }
 
-export async function getTrackerChecksHistory(id) {
-    return api_http.get(`tracker/${id}/checks_history`);
+export async function getUserGames(params) {
+    return api_http.get('user/self/games', { params });
+}
+
+export async function getUserHints(since) {
+    return api_http.get('user/self/hints', { params: { since } });
+}
+
+export async function getTrackerChecksHistory(id, forecast = false) {
+    return api_http.get(`tracker/${id}/checks_history`, { params: { forecast } });
 }
 
 export async function updateGame(tracker_id, game, priorOwner) {
@@ -76,6 +125,34 @@ export async function updateGame(tracker_id, game, priorOwner) {
     });
 }
 
+export async function lockGame(tracker_id, game_id, locked) {
+    return api_http.request({
+        method: 'put',
+        url: `tracker/${tracker_id}/game/${game_id}/lock`,
+        data: { locked },
+    });
+}
+
+export async function joinGame(tracker_id, game_id, discord_ping) {
+    return api_http.put(`tracker/${tracker_id}/game/${game_id}/claimant`, { discord_ping });
+}
+
+export async function removeCoClaimant(tracker_id, game_id, ct_user_id) {
+    return api_http.delete(`tracker/${tracker_id}/game/${game_id}/claimant/${ct_user_id}`);
+}
+
+export async function createClaimRequest(tracker_id, game_id, message, co_claim = false) {
+    return api_http.post(`tracker/${tracker_id}/game/${game_id}/claim_request`, { message, co_claim });
+}
+
+export async function resolveClaimRequest(tracker_id, request_id, action) {
+    return api_http.post(`tracker/${tracker_id}/claim_request/${request_id}/${action}`);
+}
+
+export async function withdrawClaimRequest(tracker_id, request_id) {
+    return api_http.delete(`tracker/${tracker_id}/claim_request/${request_id}`);
+}
+
 export async function updateHint(tracker_id, hint) {
     return api_http.request({
         method: 'put',
@@ -92,6 +169,30 @@ export async function updateTracker(tracker) {
     });
 }
 
+export async function offerOwnershipTransfer(tracker_id, to_ct_user_id) {
+    return api_http.request({
+        method: 'put',
+        url: `tracker/${tracker_id}/ownership_transfer`,
+        data: { to_ct_user_id },
+    });
+}
+
+export async function cancelOwnershipTransfer(tracker_id) {
+    return api_http.delete(`tracker/${tracker_id}/ownership_transfer`);
+}
+
+export async function acceptOwnershipTransfer(tracker_id) {
+    return api_http.post(`tracker/${tracker_id}/ownership_transfer/accept`);
+}
+
+export async function setNotificationMute(tracker_id, muted) {
+    return api_http.request({
+        method: 'put',
+        url: `tracker/${tracker_id}/notification_mute`,
+        data: { muted },
+    });
+}
+
 export async function setDashboardOverrideStatus(tracker_id, visibility) {
     return api_http.request({
         method: 'put',
@@ -104,14 +205,18 @@ export async function ping() {
     return api_http.get('ping');
 }
 
-export async function authBegin() {
-    return api_http.get('auth/begin');
+function authPrefix(provider) {
+    return provider === 'oidc' ? 'auth/oidc' : 'auth';
 }
 
-export async function authComplete(data) {
+export async function authBegin(provider) {
+    return api_http.get(`${authPrefix(provider)}/begin`);
+}
+
+export async function authComplete(provider, data) {
     return api_http.request({
         method: 'post',
-        url: 'auth/complete',
+        url: `${authPrefix(provider)}/complete`,
         data,
     });
 }
@@ -124,16 +229,16 @@ export async function createJsError(data) {
     });
 }
 
-export async function getApiKey() {
-    return api_http.get('user/self/api_key');
+export async function getApiKeys() {
+    return api_http.get('user/self/api_keys');
 }
 
-export async function newApiKey() {
-    return api_http.post('user/self/api_key');
+export async function createApiKey(data) {
+    return api_http.post('user/self/api_keys', data);
 }
 
-export async function deleteApiKey() {
-    return api_http.delete('user/self/api_key');
+export async function deleteApiKey(id) {
+    return api_http.delete(`user/self/api_keys/${id}`);
 }
 
 export async function getUserServerSettings() {
@@ -147,3 +252,67 @@ export async function updateUserServerSettings(data) {
         data,
     });
 }
+
+export async function getGamePreferences() {
+    return api_http.get('user/self/game_preferences');
+}
+
+export async function putGamePreference(game, data) {
+    return api_http.request({
+        method: 'put',
+        url: `user/self/game_preferences/${encodeURIComponent(game)}`,
+        data,
+    });
+}
+
+export async function deleteGamePreference(game) {
+    return api_http.delete(`user/self/game_preferences/${encodeURIComponent(game)}`);
+}
+
+export async function getIdentities() {
+    return api_http.get('user/self/identities');
+}
+
+export async function beginLinkIdentity(provider) {
+    return api_http.get('user/self/identities/link', { params: { provider } });
+}
+
+export async function linkIdentity(data) {
+    return api_http.request({
+        method: 'post',
+        url: 'user/self/identities',
+        data,
+    });
+}
+
+export async function unlinkIdentity(id) {
+    return api_http.delete(`user/self/identities/${id}`);
+}
+
+export async function getNotifications(before) {
+    return api_http.get('user/self/notifications', { params: { before } });
+}
+
+export async function getUnreadNotificationCount() {
+    return api_http.get('user/self/notifications/unread_count');
+}
+
+export async function markNotificationsRead(ids) {
+    return api_http.post('user/self/notifications/read', { ids });
+}
+
+export async function getSessions() {
+    return api_http.get('user/self/sessions');
+}
+
+export async function revokeSession(id) {
+    return api_http.delete(`user/self/sessions/${id}`);
+}
+
+export async function revokeCurrentSession() {
+    return api_http.delete('user/self/sessions/current');
+}
+
+export async function revokeAllSessions() {
+    return api_http.delete('user/self/sessions');
+}
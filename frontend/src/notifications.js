@@ -0,0 +1,23 @@
+import { ref, watch } from 'vue';
+import { getUnreadNotificationCount } from '@/api';
+import { currentUser } from '@/settings';
+
+export const unreadNotificationCount = ref(0);
+
+async function refreshUnreadNotificationCount() {
+    if (currentUser.value?.id === undefined) {
+        unreadNotificationCount.value = 0;
+        return;
+    }
+
+    try {
+        unreadNotificationCount.value = (await getUnreadNotificationCount()).data.unread;
+    } catch (e) {
+        console.error(`Failed to get unread notification count: ${e}`);
+    }
+}
+
+watch(currentUser, refreshUnreadNotificationCount);
+
+refreshUnreadNotificationCount();
+setInterval(refreshUnreadNotificationCount, 60000);
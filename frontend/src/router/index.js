@@ -3,6 +3,7 @@ import DashboardView from '@/views/DashboardView.vue';
 import TrackerViewProxy from '@/views/TrackerViewProxy.vue';
 import SettingsView from '@/views/SettingsView.vue';
 import HelpView from '@/views/HelpView.vue';
+import NotificationsView from '@/views/NotificationsView.vue';
 import AuthComplete from '@/views/AuthComplete.vue';
 
 const router = createRouter({
@@ -24,6 +25,11 @@ const router = createRouter({
       component: TrackerViewProxy,
       props: true,
     },
+    {
+      path: '/notifications',
+      name: 'notifications',
+      component: NotificationsView,
+    },
     {
       path: '/help',
       name: 'help',
@@ -35,6 +41,12 @@ const router = createRouter({
       name: 'authcomplete',
       component: AuthComplete,
     },
+    {
+      path: '/auth/oidc/complete',
+      name: 'oidcauthcomplete',
+      component: AuthComplete,
+      props: { provider: 'oidc' },
+    },
   ]
 });
 
@@ -19,10 +19,16 @@ const SETTINGS_SCHEMA = Joi.object().keys({
 
     auth: Joi.object().keys({
         token: Joi.string(),
+        refreshToken: Joi.string(),
         userId: Joi.number(),
         discordUsername: Joi.string(),
         discordSigninContinuationToken: Joi.string(),
+        provider: Joi.string(),
         returnTo: Joi.string(),
+        link: Joi.object().keys({
+            provider: Joi.string(),
+            continuationToken: Joi.string(),
+        }),
     })
         .default({})
         .and('token', 'userId', 'discordUsername'),
@@ -92,6 +92,7 @@ export function getClaimingUserForGame(game) {
             id: game.claimed_by_ct_user_id,
             discordUsername: game.effective_discord_username,
             isAway: game.user_is_away,
+            awayUntil: game.user_away_until,
         };
     }
 
@@ -99,6 +100,7 @@ export function getClaimingUserForGame(game) {
         return {
             discordUsername: game.effective_discord_username,
             isAway: game.user_is_away,
+            awayUntil: game.user_away_until,
         };
     }
 
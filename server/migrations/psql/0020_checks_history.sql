@@ -0,0 +1,32 @@
+CREATE TABLE checks_history (
+    id SERIAL NOT NULL PRIMARY KEY,
+    ap_tracker_id INTEGER NOT NULL REFERENCES ap_tracker (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    ap_game_id INTEGER NOT NULL REFERENCES ap_game_store (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    changed_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    previous_checks_done INTEGER NOT NULL,
+    checks_done INTEGER NOT NULL
+);
+
+CREATE INDEX idx_checks_history_tracker_lookup ON checks_history (ap_tracker_id, changed_at);
+
+CREATE INDEX idx_checks_history_game_lookup ON checks_history (ap_game_id, changed_at);
+
+-- Backfill from the check count changes recorded by the system in the audit
+-- log.
+INSERT INTO checks_history (ap_tracker_id, ap_game_id, changed_at, previous_checks_done, checks_done)
+SELECT
+    g.tracker_id,
+    a.entity_id,
+    a.changed_at,
+    (a.diff::jsonb -> 'checks_done' ->> 'old')::integer,
+    (a.diff::jsonb -> 'checks_done' ->> 'new')::integer
+
+FROM audit a
+INNER JOIN ap_game_store g
+    ON g.id = a.entity_id
+
+WHERE a.entity = 'ap_game'
+AND a.actor_ct_user_id IS NULL
+AND (a.diff::jsonb -> 'checks_done') IS NOT NULL
+
+ORDER BY a.changed_at, a.id;
@@ -0,0 +1,108 @@
+CREATE TYPE inactivity_color AS ENUM (
+    'green',
+    'yellow',
+    'red'
+);
+
+CREATE CAST (text AS inactivity_color) WITH INOUT AS ASSIGNMENT;
+
+-- Determines the inactivity color of a slot from its completion status, the
+-- last time it was checked or had activity, and the inactivity thresholds of
+-- its tracker.  This matches the coloring used by the frontend.
+CREATE FUNCTION slot_inactivity_color(
+    completion_status completion_status,
+    last_checked timestamp with time zone,
+    last_activity timestamp with time zone,
+    yellow_hours integer,
+    red_hours integer
+)
+RETURNS inactivity_color
+    LANGUAGE sql STABLE
+    AS $$
+    SELECT (
+        CASE
+            WHEN completion_status IN ('done', 'released') THEN 'green'
+            WHEN GREATEST(last_checked, last_activity) IS NULL THEN 'red'
+            WHEN GREATEST(last_checked, last_activity) <= now() - make_interval(hours => red_hours) THEN 'red'
+            WHEN GREATEST(last_checked, last_activity) <= now() - make_interval(hours => yellow_hours) THEN 'yellow'
+            ELSE 'green'
+        END
+    )::inactivity_color
+$$;
+
+CREATE FUNCTION get_tracker_slot_stats(tid integer)
+RETURNS TABLE(
+    completion_status completion_status,
+    progression_status progression_status,
+    availability_status availability_status,
+    is_claimed boolean,
+    inactivity_color inactivity_color,
+    slots bigint
+)
+    LANGUAGE sql STABLE
+    AS $$
+    SELECT
+        g.completion_status,
+        g.progression_status,
+        g.availability_status,
+        g.claimed_by_ct_user_id IS NOT NULL OR g.discord_username IS NOT NULL,
+        slot_inactivity_color(
+            g.completion_status,
+            g.last_checked,
+            g.last_activity,
+            t.inactivity_threshold_yellow_hours,
+            t.inactivity_threshold_red_hours
+        ),
+        COUNT(*)
+
+    FROM ap_game_store g
+    INNER JOIN ap_tracker t
+        ON t.id = g.tracker_id
+
+    WHERE g.tracker_id = tid
+    GROUP BY 1, 2, 3, 4, 5
+$$;
+
+CREATE FUNCTION get_tracker_hint_stats(tid integer)
+RETURNS TABLE(
+    classification hint_classification,
+    found boolean,
+    hints bigint
+)
+    LANGUAGE sql STABLE
+    AS $$
+    SELECT
+        h.classification,
+        h.found,
+        COUNT(*)
+
+    FROM ap_hint h
+    INNER JOIN ap_game_store g
+        ON g.id = h.finder_game_id
+
+    WHERE g.tracker_id = tid
+    GROUP BY 1, 2
+$$;
+
+CREATE FUNCTION get_tracker_game_stats(tid integer)
+RETURNS TABLE(
+    game text,
+    slots bigint,
+    finished_slots bigint,
+    checks_done bigint,
+    checks_total bigint
+)
+    LANGUAGE sql STABLE
+    AS $$
+    SELECT
+        g.game,
+        COUNT(*),
+        COUNT(*) FILTER (WHERE g.completion_status IN ('done', 'released')),
+        SUM(g.checks_done),
+        SUM(g.checks_total)
+
+    FROM ap_game_store g
+
+    WHERE g.tracker_id = tid
+    GROUP BY 1
+$$;
@@ -0,0 +1,30 @@
+ALTER TABLE ap_hint
+    ADD COLUMN created_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN found_at TIMESTAMP WITH TIME ZONE;
+
+-- Backfill the time hints were found from the found state changes recorded by
+-- the system in the audit log.
+UPDATE ap_hint h
+SET found_at = a.changed_at
+FROM (
+    SELECT entity_id, MAX(changed_at) AS changed_at
+    FROM audit
+    WHERE entity = 'ap_hint'
+    AND actor_ct_user_id IS NULL
+    AND (diff::jsonb -> 'found' ->> 'new')::boolean
+    GROUP BY entity_id
+) a
+WHERE a.entity_id = h.id
+AND h.found;
+
+-- Hint creation was never audited, so the best we can do is the time of the
+-- earliest audit of any kind for the hint, which is an upper bound.
+UPDATE ap_hint h
+SET created_at = a.changed_at
+FROM (
+    SELECT entity_id, MIN(changed_at) AS changed_at
+    FROM audit
+    WHERE entity = 'ap_hint'
+    GROUP BY entity_id
+) a
+WHERE a.entity_id = h.id;
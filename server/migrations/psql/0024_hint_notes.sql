@@ -0,0 +1,3 @@
+ALTER TABLE ap_hint
+    ADD COLUMN notes TEXT NOT NULL DEFAULT '',
+    ADD COLUMN acknowledged_by_ct_user_id INTEGER NULL REFERENCES ct_user (id) ON DELETE SET NULL ON UPDATE CASCADE;
@@ -0,0 +1,18 @@
+CREATE TYPE tracker_role AS ENUM (
+    'owner',
+    'organizer',
+    'viewer'
+);
+
+CREATE CAST (text AS tracker_role) WITH INOUT AS ASSIGNMENT;
+
+-- The owner of a tracker is recorded in ap_tracker.owner_ct_user_id, so only
+-- the other roles are stored here.
+CREATE TABLE ap_tracker_member (
+    ct_user_id INTEGER NOT NULL REFERENCES ct_user (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    ap_tracker_id INTEGER NOT NULL REFERENCES ap_tracker (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    role tracker_role NOT NULL CHECK (role <> 'owner'),
+    PRIMARY KEY (ct_user_id, ap_tracker_id)
+);
+
+CREATE INDEX idx_ap_tracker_member_ap_tracker_id ON ap_tracker_member (ap_tracker_id);
@@ -0,0 +1,10 @@
+-- Pending offers to transfer ownership of a tracker.  Each tracker has at most
+-- one pending offer; making a new offer replaces the previous one.
+CREATE TABLE ap_tracker_ownership_transfer (
+    id SERIAL NOT NULL PRIMARY KEY,
+    ap_tracker_id INTEGER NOT NULL UNIQUE REFERENCES ap_tracker (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    from_ct_user_id INTEGER NOT NULL REFERENCES ct_user (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    to_ct_user_id INTEGER NOT NULL REFERENCES ct_user (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
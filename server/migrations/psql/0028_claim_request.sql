@@ -0,0 +1,25 @@
+CREATE TYPE claim_request_status AS ENUM (
+    'pending',
+    'approved',
+    'denied',
+    'withdrawn'
+);
+
+CREATE CAST (text AS claim_request_status) WITH INOUT AS ASSIGNMENT;
+
+-- Requests by users to take over the claim on a slot.
+CREATE TABLE ap_game_claim_request (
+    id SERIAL NOT NULL PRIMARY KEY,
+    ap_game_id INTEGER NOT NULL REFERENCES ap_game_store (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    requested_by_ct_user_id INTEGER NOT NULL REFERENCES ct_user (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    message TEXT NOT NULL,
+    status claim_request_status NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    resolved_at TIMESTAMP WITH TIME ZONE NULL,
+    resolved_by_ct_user_id INTEGER NULL REFERENCES ct_user (id) ON DELETE SET NULL ON UPDATE CASCADE
+);
+
+-- A user may only have one pending request per slot.
+CREATE UNIQUE INDEX idx_ap_game_claim_request_pending
+    ON ap_game_claim_request (ap_game_id, requested_by_ct_user_id)
+    WHERE status = 'pending';
@@ -0,0 +1,231 @@
+-- Co-claimants of a slot, in addition to the claimant recorded in
+-- ap_game_store.claimed_by_ct_user_id or ap_game_store.discord_username.
+CREATE TABLE ap_game_claimant (
+    ap_game_id INTEGER NOT NULL REFERENCES ap_game_store (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    ct_user_id INTEGER NOT NULL REFERENCES ct_user (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    discord_ping ping_preference NOT NULL DEFAULT 'never',
+    claimed_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (ap_game_id, ct_user_id)
+);
+
+CREATE INDEX idx_ap_game_claimant_ct_user_id ON ap_game_claimant (ct_user_id);
+
+-- The claimant's username, or the username of the earliest co-claimant if the
+-- slot has no claimant.
+CREATE FUNCTION ap_game_effective_discord_username(gid integer, claimed_by integer, username text)
+RETURNS text
+    LANGUAGE sql STABLE
+    AS $$
+    SELECT COALESCE(
+        (SELECT u.discord_username FROM ct_user u WHERE u.id = claimed_by),
+        username,
+        (
+            SELECT u.discord_username
+            FROM ap_game_claimant c
+            INNER JOIN ct_user u
+                ON u.id = c.ct_user_id
+            WHERE c.ap_game_id = gid
+            ORDER BY c.claimed_at, c.ct_user_id
+            LIMIT 1
+        )
+    )
+$$;
+
+-- A slot's claimants are away only if every claimant is an away user.
+-- Unauthenticated claimants are never away.
+CREATE FUNCTION ap_game_user_is_away(gid integer, claimed_by integer, username text)
+RETURNS boolean
+    LANGUAGE sql STABLE
+    AS $$
+    SELECT (claimed_by IS NOT NULL OR username IS NULL) AND COALESCE(bool_and(u.is_away), FALSE)
+    FROM ct_user u
+    WHERE u.id = claimed_by
+        OR u.id IN (SELECT c.ct_user_id FROM ap_game_claimant c WHERE c.ap_game_id = gid)
+$$;
+
+CREATE OR REPLACE VIEW ap_game WITH (security_barrier='false', security_invoker='true') AS
+ SELECT g.id,
+    g.tracker_id,
+    g.name,
+    g.game,
+    g.checks_done,
+    g.checks_total,
+    g.last_activity,
+    g.discord_username,
+    g.last_checked,
+    g."position",
+    g.tracker_status,
+    g.notes,
+    g.discord_ping,
+    g.claimed_by_ct_user_id,
+    g.availability_status,
+    g.completion_status,
+    g.progression_status,
+    public.ap_game_effective_discord_username(g.id, g.claimed_by_ct_user_id, g.discord_username) AS effective_discord_username,
+    public.ap_game_user_is_away(g.id, g.claimed_by_ct_user_id, g.discord_username) AS user_is_away,
+    g.locked
+   FROM public.ap_game_store g;
+
+CREATE OR REPLACE RULE ap_game_delete_store AS
+    ON DELETE TO public.ap_game DO INSTEAD  DELETE FROM public.ap_game_store
+  WHERE (ap_game_store.id = old.id)
+  RETURNING ap_game_store.id,
+    ap_game_store.tracker_id,
+    ap_game_store.name,
+    ap_game_store.game,
+    ap_game_store.checks_done,
+    ap_game_store.checks_total,
+    ap_game_store.last_activity,
+    ap_game_store.discord_username,
+    ap_game_store.last_checked,
+    ap_game_store."position",
+    ap_game_store.tracker_status,
+    ap_game_store.notes,
+    ap_game_store.discord_ping,
+    ap_game_store.claimed_by_ct_user_id,
+    ap_game_store.availability_status,
+    ap_game_store.completion_status,
+    ap_game_store.progression_status,
+    public.ap_game_effective_discord_username(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS effective_discord_username,
+    public.ap_game_user_is_away(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS user_is_away,
+    ap_game_store.locked;
+
+CREATE OR REPLACE RULE ap_game_insert_store AS
+    ON INSERT TO public.ap_game DO INSTEAD  INSERT INTO public.ap_game_store (id, tracker_id, name, game, checks_done, checks_total, last_activity, discord_username, last_checked, "position", tracker_status, notes, discord_ping, claimed_by_ct_user_id, availability_status, completion_status, progression_status, locked)
+  VALUES (new.id, new.tracker_id, new.name, new.game, new.checks_done, new.checks_total, new.last_activity, new.discord_username, new.last_checked, new."position", new.tracker_status, new.notes, new.discord_ping, new.claimed_by_ct_user_id, new.availability_status, new.completion_status, new.progression_status, new.locked)
+  RETURNING ap_game_store.id,
+    ap_game_store.tracker_id,
+    ap_game_store.name,
+    ap_game_store.game,
+    ap_game_store.checks_done,
+    ap_game_store.checks_total,
+    ap_game_store.last_activity,
+    ap_game_store.discord_username,
+    ap_game_store.last_checked,
+    ap_game_store."position",
+    ap_game_store.tracker_status,
+    ap_game_store.notes,
+    ap_game_store.discord_ping,
+    ap_game_store.claimed_by_ct_user_id,
+    ap_game_store.availability_status,
+    ap_game_store.completion_status,
+    ap_game_store.progression_status,
+    public.ap_game_effective_discord_username(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS effective_discord_username,
+    public.ap_game_user_is_away(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS user_is_away,
+    ap_game_store.locked;
+
+CREATE OR REPLACE RULE ap_game_update_store AS
+    ON UPDATE TO public.ap_game DO INSTEAD  UPDATE public.ap_game_store SET id = new.id, tracker_id = new.tracker_id, name = new.name, game = new.game, checks_done = new.checks_done, checks_total = new.checks_total, last_activity = new.last_activity, discord_username = new.discord_username, last_checked = new.last_checked, "position" = new."position", tracker_status = new.tracker_status, notes = new.notes, discord_ping = new.discord_ping, claimed_by_ct_user_id = new.claimed_by_ct_user_id, availability_status = new.availability_status, completion_status = new.completion_status, progression_status = new.progression_status, locked = new.locked
+  WHERE (ap_game_store.id = old.id)
+  RETURNING ap_game_store.id,
+    ap_game_store.tracker_id,
+    ap_game_store.name,
+    ap_game_store.game,
+    ap_game_store.checks_done,
+    ap_game_store.checks_total,
+    ap_game_store.last_activity,
+    ap_game_store.discord_username,
+    ap_game_store.last_checked,
+    ap_game_store."position",
+    ap_game_store.tracker_status,
+    ap_game_store.notes,
+    ap_game_store.discord_ping,
+    ap_game_store.claimed_by_ct_user_id,
+    ap_game_store.availability_status,
+    ap_game_store.completion_status,
+    ap_game_store.progression_status,
+    public.ap_game_effective_discord_username(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS effective_discord_username,
+    public.ap_game_user_is_away(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS user_is_away,
+    ap_game_store.locked;
+
+DROP FUNCTION get_dashboard_trackers;
+
+CREATE FUNCTION get_dashboard_trackers(uid integer)
+RETURNS TABLE(
+    id integer,
+    tracker_id uuid,
+    title text,
+    owner_ct_user_id integer,
+    owner_discord_username text,
+    last_activity timestamp with time zone,
+    dashboard_override_visibility boolean,
+    room_link text,
+    last_port integer,
+    next_port_check_at timestamp with time zone,
+    upstream_url text
+)
+    LANGUAGE sql STABLE
+    AS $$
+    WITH ut (id) AS (
+        SELECT id
+        FROM ap_tracker t
+        WHERE owner_ct_user_id = uid
+
+        UNION
+
+        SELECT tracker_id
+        FROM ap_game_store
+        WHERE claimed_by_ct_user_id = uid
+
+        UNION
+
+        SELECT g.tracker_id
+        FROM ap_game_claimant c
+        INNER JOIN ap_game_store g
+            ON g.id = c.ap_game_id
+        WHERE c.ct_user_id = uid
+
+        UNION
+
+        SELECT ap_tracker_id
+        FROM ap_tracker_dashboard_override
+        WHERE ct_user_id = uid AND visibility
+    )
+
+    SELECT
+        t.id,
+        t.tracker_id,
+        t.title,
+        t.owner_ct_user_id,
+        u.discord_username AS owner_discord_username,
+        gs.last_activity,
+        tdo.visibility AS dashboard_override_visibility,
+        t.room_link,
+        t.last_port,
+        t.next_port_check_at,
+        t.upstream_url
+
+    FROM ap_tracker t
+    LEFT OUTER JOIN ct_user u
+        ON u.id = t.owner_ct_user_id
+
+    INNER JOIN (
+        SELECT
+            tracker_id,
+            MAX(last_activity) AS last_activity,
+            MIN(
+                CASE completion_status
+                    WHEN 'done' THEN 1
+                    WHEN 'goal' THEN 1
+                    WHEN 'released' THEN 1
+                    ELSE 0
+                END
+            ) = 1 AS all_done
+
+        FROM ap_game_store
+        WHERE tracker_id IN (SELECT id FROM ut)
+        GROUP BY tracker_id
+    ) gs
+        ON gs.tracker_id = t.id
+
+    LEFT OUTER JOIN ap_tracker_dashboard_override tdo
+        ON tdo.ct_user_id = uid AND tdo.ap_tracker_id = t.id
+
+    WHERE (
+        (
+            t.id IN (SELECT id FROM ut)
+            AND NOT gs.all_done
+        ) OR tdo.visibility IS NOT DISTINCT FROM TRUE
+    )
+    AND tdo.visibility IS DISTINCT FROM FALSE
+$$;
@@ -0,0 +1,55 @@
+ALTER TABLE ap_tracker ADD COLUMN claim_expiry_days INTEGER NULL CHECK (claim_expiry_days > 0);
+ALTER TABLE ap_tracker ADD COLUMN claim_expiry_notify BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- Finds claimed slots in trackers with a claim expiry policy that have had no
+-- activity or check-in for longer than the tracker's claim expiry period.
+--
+-- Slots that have never had activity or been checked are skipped, since there
+-- is no way to tell how long they have been claimed.  Finished slots and slots
+-- claimed by away users are also skipped, as are trackers that have not been
+-- updated since updated_after, whose activity information may be out of date.
+CREATE FUNCTION get_expired_claims(
+    as_of timestamp with time zone,
+    updated_after timestamp with time zone
+)
+RETURNS TABLE(
+    tracker_id uuid,
+    tracker_title text,
+    ap_game_id integer,
+    game_name text,
+    claim_expiry_days integer,
+    claim_expiry_notify boolean,
+    inactive_since timestamp with time zone,
+    claimant_ct_user_id integer,
+    claimant_discord_username text,
+    claimant_discord_user_id bigint
+)
+    LANGUAGE sql STABLE
+    AS $$
+    SELECT
+        t.tracker_id,
+        t.title,
+        g.id,
+        g.name,
+        t.claim_expiry_days,
+        t.claim_expiry_notify,
+        GREATEST(g.last_checked, g.last_activity),
+        g.claimed_by_ct_user_id,
+        COALESCE(cu.discord_username, g.discord_username),
+        cu.discord_user_id
+
+    FROM ap_game g
+    INNER JOIN ap_tracker t
+        ON t.id = g.tracker_id
+    LEFT JOIN ct_user cu
+        ON cu.id = g.claimed_by_ct_user_id
+
+    WHERE t.claim_expiry_days IS NOT NULL
+    AND (g.claimed_by_ct_user_id IS NOT NULL OR g.discord_username IS NOT NULL)
+    AND NOT g.user_is_away
+    AND g.completion_status NOT IN ('done', 'released')
+    AND GREATEST(g.last_checked, g.last_activity) < as_of - make_interval(days => t.claim_expiry_days)
+    AND t.updated_at >= updated_after
+
+    ORDER BY t.id, g.position
+$$;
@@ -0,0 +1,144 @@
+ALTER TABLE ct_user ADD COLUMN away_from TIMESTAMP WITH TIME ZONE NULL;
+ALTER TABLE ct_user ADD COLUMN away_until TIMESTAMP WITH TIME ZONE NULL;
+ALTER TABLE ct_user ADD COLUMN away_message TEXT NOT NULL DEFAULT '';
+ALTER TABLE ct_user ADD CONSTRAINT ct_user_away_window_check CHECK (away_from < away_until);
+
+CREATE INDEX idx_ct_user_away_from ON ct_user (away_from) WHERE away_from IS NOT NULL;
+CREATE INDEX idx_ct_user_away_until ON ct_user (away_until) WHERE away_until IS NOT NULL;
+
+-- When a slot's claimants are away, the earliest time one of them is expected
+-- back.  NULL if they are not away or none of them set a return time.
+CREATE FUNCTION ap_game_user_away_until(gid integer, claimed_by integer, username text)
+RETURNS timestamp with time zone
+    LANGUAGE sql STABLE
+    AS $$
+    SELECT MIN(u.away_until)
+    FROM ct_user u
+    WHERE ap_game_user_is_away(gid, claimed_by, username)
+    AND (
+        u.id = claimed_by
+        OR u.id IN (SELECT c.ct_user_id FROM ap_game_claimant c WHERE c.ap_game_id = gid)
+    )
+$$;
+
+-- When a slot's claimants are away, the away message of the claimant, or of
+-- the earliest co-claimant with a message.  NULL if they are not away or none
+-- of them left a message.
+CREATE FUNCTION ap_game_user_away_message(gid integer, claimed_by integer, username text)
+RETURNS text
+    LANGUAGE sql STABLE
+    AS $$
+    SELECT u.away_message
+    FROM ct_user u
+    LEFT JOIN ap_game_claimant c
+        ON c.ap_game_id = gid
+        AND c.ct_user_id = u.id
+    WHERE ap_game_user_is_away(gid, claimed_by, username)
+    AND (u.id = claimed_by OR c.ct_user_id IS NOT NULL)
+    AND u.away_message <> ''
+    ORDER BY u.id = claimed_by DESC, c.claimed_at
+    LIMIT 1
+$$;
+
+CREATE OR REPLACE VIEW ap_game WITH (security_barrier='false', security_invoker='true') AS
+ SELECT g.id,
+    g.tracker_id,
+    g.name,
+    g.game,
+    g.checks_done,
+    g.checks_total,
+    g.last_activity,
+    g.discord_username,
+    g.last_checked,
+    g."position",
+    g.tracker_status,
+    g.notes,
+    g.discord_ping,
+    g.claimed_by_ct_user_id,
+    g.availability_status,
+    g.completion_status,
+    g.progression_status,
+    public.ap_game_effective_discord_username(g.id, g.claimed_by_ct_user_id, g.discord_username) AS effective_discord_username,
+    public.ap_game_user_is_away(g.id, g.claimed_by_ct_user_id, g.discord_username) AS user_is_away,
+    g.locked,
+    public.ap_game_user_away_until(g.id, g.claimed_by_ct_user_id, g.discord_username) AS user_away_until,
+    public.ap_game_user_away_message(g.id, g.claimed_by_ct_user_id, g.discord_username) AS user_away_message
+   FROM public.ap_game_store g;
+
+CREATE OR REPLACE RULE ap_game_delete_store AS
+    ON DELETE TO public.ap_game DO INSTEAD  DELETE FROM public.ap_game_store
+  WHERE (ap_game_store.id = old.id)
+  RETURNING ap_game_store.id,
+    ap_game_store.tracker_id,
+    ap_game_store.name,
+    ap_game_store.game,
+    ap_game_store.checks_done,
+    ap_game_store.checks_total,
+    ap_game_store.last_activity,
+    ap_game_store.discord_username,
+    ap_game_store.last_checked,
+    ap_game_store."position",
+    ap_game_store.tracker_status,
+    ap_game_store.notes,
+    ap_game_store.discord_ping,
+    ap_game_store.claimed_by_ct_user_id,
+    ap_game_store.availability_status,
+    ap_game_store.completion_status,
+    ap_game_store.progression_status,
+    public.ap_game_effective_discord_username(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS effective_discord_username,
+    public.ap_game_user_is_away(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS user_is_away,
+    ap_game_store.locked,
+    public.ap_game_user_away_until(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS user_away_until,
+    public.ap_game_user_away_message(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS user_away_message;
+
+CREATE OR REPLACE RULE ap_game_insert_store AS
+    ON INSERT TO public.ap_game DO INSTEAD  INSERT INTO public.ap_game_store (id, tracker_id, name, game, checks_done, checks_total, last_activity, discord_username, last_checked, "position", tracker_status, notes, discord_ping, claimed_by_ct_user_id, availability_status, completion_status, progression_status, locked)
+  VALUES (new.id, new.tracker_id, new.name, new.game, new.checks_done, new.checks_total, new.last_activity, new.discord_username, new.last_checked, new."position", new.tracker_status, new.notes, new.discord_ping, new.claimed_by_ct_user_id, new.availability_status, new.completion_status, new.progression_status, new.locked)
+  RETURNING ap_game_store.id,
+    ap_game_store.tracker_id,
+    ap_game_store.name,
+    ap_game_store.game,
+    ap_game_store.checks_done,
+    ap_game_store.checks_total,
+    ap_game_store.last_activity,
+    ap_game_store.discord_username,
+    ap_game_store.last_checked,
+    ap_game_store."position",
+    ap_game_store.tracker_status,
+    ap_game_store.notes,
+    ap_game_store.discord_ping,
+    ap_game_store.claimed_by_ct_user_id,
+    ap_game_store.availability_status,
+    ap_game_store.completion_status,
+    ap_game_store.progression_status,
+    public.ap_game_effective_discord_username(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS effective_discord_username,
+    public.ap_game_user_is_away(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS user_is_away,
+    ap_game_store.locked,
+    public.ap_game_user_away_until(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS user_away_until,
+    public.ap_game_user_away_message(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS user_away_message;
+
+CREATE OR REPLACE RULE ap_game_update_store AS
+    ON UPDATE TO public.ap_game DO INSTEAD  UPDATE public.ap_game_store SET id = new.id, tracker_id = new.tracker_id, name = new.name, game = new.game, checks_done = new.checks_done, checks_total = new.checks_total, last_activity = new.last_activity, discord_username = new.discord_username, last_checked = new.last_checked, "position" = new."position", tracker_status = new.tracker_status, notes = new.notes, discord_ping = new.discord_ping, claimed_by_ct_user_id = new.claimed_by_ct_user_id, availability_status = new.availability_status, completion_status = new.completion_status, progression_status = new.progression_status, locked = new.locked
+  WHERE (ap_game_store.id = old.id)
+  RETURNING ap_game_store.id,
+    ap_game_store.tracker_id,
+    ap_game_store.name,
+    ap_game_store.game,
+    ap_game_store.checks_done,
+    ap_game_store.checks_total,
+    ap_game_store.last_activity,
+    ap_game_store.discord_username,
+    ap_game_store.last_checked,
+    ap_game_store."position",
+    ap_game_store.tracker_status,
+    ap_game_store.notes,
+    ap_game_store.discord_ping,
+    ap_game_store.claimed_by_ct_user_id,
+    ap_game_store.availability_status,
+    ap_game_store.completion_status,
+    ap_game_store.progression_status,
+    public.ap_game_effective_discord_username(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS effective_discord_username,
+    public.ap_game_user_is_away(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS user_is_away,
+    ap_game_store.locked,
+    public.ap_game_user_away_until(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS user_away_until,
+    public.ap_game_user_away_message(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) AS user_away_message;
@@ -0,0 +1,13 @@
+ALTER TABLE ct_user ADD COLUMN default_ping_preference ping_preference NULL;
+ALTER TABLE ct_user ADD COLUMN default_availability_status availability_status NULL;
+
+-- Per-game overrides of a user's default claim preferences.  game matches
+-- ap_game_store.game.
+CREATE TABLE ct_user_game_preference (
+    ct_user_id INTEGER NOT NULL REFERENCES ct_user (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    game TEXT NOT NULL,
+    discord_ping ping_preference NULL,
+    availability_status availability_status NULL,
+    PRIMARY KEY (ct_user_id, game),
+    CHECK (discord_ping IS NOT NULL OR availability_status IS NOT NULL)
+);
@@ -0,0 +1,30 @@
+-- Counts the unfound hints that each slot claimed or co-claimed by a user has
+-- yet to find, grouped by classification.
+CREATE FUNCTION get_user_game_hint_stats(uid integer)
+RETURNS TABLE(
+    ap_game_id integer,
+    classification hint_classification,
+    hints bigint
+)
+    LANGUAGE sql STABLE
+    AS $$
+    SELECT
+        h.finder_game_id,
+        h.classification,
+        COUNT(*)
+
+    FROM ap_hint h
+    INNER JOIN ap_game_store g
+        ON g.id = h.finder_game_id
+
+    WHERE NOT h.found
+        AND (
+            g.claimed_by_ct_user_id = uid
+            OR EXISTS (
+                SELECT 1
+                FROM ap_game_claimant c
+                WHERE c.ap_game_id = g.id AND c.ct_user_id = uid
+            )
+        )
+    GROUP BY 1, 2
+$$;
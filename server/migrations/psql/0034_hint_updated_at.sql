@@ -0,0 +1,20 @@
+ALTER TABLE ap_hint
+    ADD COLUMN updated_at TIMESTAMP WITH TIME ZONE;
+
+-- Backfill from the latest audit of any kind for the hint, falling back to the
+-- time the hint was created or found.
+UPDATE ap_hint h
+SET updated_at = GREATEST(h.created_at, h.found_at, a.changed_at)
+FROM (
+    SELECT entity_id, MAX(changed_at) AS changed_at
+    FROM audit
+    WHERE entity = 'ap_hint'
+    GROUP BY entity_id
+) a
+WHERE a.entity_id = h.id;
+
+UPDATE ap_hint
+SET updated_at = GREATEST(created_at, found_at)
+WHERE updated_at IS NULL;
+
+CREATE INDEX ap_hint_updated_at ON ap_hint (updated_at);
@@ -0,0 +1,26 @@
+-- In-app notifications about events that concern a user.
+CREATE TABLE ct_user_notification (
+    id SERIAL NOT NULL PRIMARY KEY,
+    ct_user_id INTEGER NOT NULL REFERENCES ct_user (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    ap_tracker_id INTEGER NULL REFERENCES ap_tracker (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    read_at TIMESTAMP WITH TIME ZONE NULL,
+    -- The event as JSON, in the same form sent to webhook notifiers.
+    event TEXT NOT NULL,
+    -- Human-readable description of the event.
+    message TEXT NOT NULL
+);
+
+CREATE INDEX idx_ct_user_notification_ct_user_id
+    ON ct_user_notification (ct_user_id, id);
+
+CREATE INDEX idx_ct_user_notification_unread
+    ON ct_user_notification (ct_user_id)
+    WHERE read_at IS NULL;
+
+-- Trackers that a user does not want in-app notifications about.
+CREATE TABLE ap_tracker_notification_mute (
+    ct_user_id INTEGER NOT NULL REFERENCES ct_user (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    ap_tracker_id INTEGER NOT NULL REFERENCES ap_tracker (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    PRIMARY KEY (ct_user_id, ap_tracker_id)
+);
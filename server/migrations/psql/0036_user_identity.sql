@@ -0,0 +1,24 @@
+-- Users who sign in through a provider other than Discord have no Discord
+-- account or tokens.
+ALTER TABLE ct_user
+    ALTER COLUMN discord_access_token DROP NOT NULL,
+    ALTER COLUMN discord_access_token_expires_at DROP NOT NULL,
+    ALTER COLUMN discord_refresh_token DROP NOT NULL,
+    ALTER COLUMN discord_user_id DROP NOT NULL;
+
+-- Identities at external login providers, each linked to a user.
+CREATE TABLE ct_user_identity (
+    id SERIAL NOT NULL PRIMARY KEY,
+    ct_user_id INTEGER NOT NULL REFERENCES ct_user (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    -- The configured identifier of the login provider.
+    provider TEXT NOT NULL,
+    -- The provider's stable identifier for the account (the OIDC "sub" claim).
+    subject TEXT NOT NULL,
+    -- The account's display name at the provider, as of the last sign-in.
+    username TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    UNIQUE (provider, subject)
+);
+
+CREATE INDEX idx_ct_user_identity_ct_user_id
+    ON ct_user_identity (ct_user_id);
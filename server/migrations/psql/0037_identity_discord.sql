@@ -0,0 +1,160 @@
+-- Existing Discord accounts become identities.  ct_user stored Discord's
+-- unsigned 64-bit user IDs reinterpreted as signed bigints; identities store
+-- the true ID as text.
+INSERT INTO ct_user_identity (ct_user_id, provider, subject, username, created_at)
+SELECT
+    id,
+    'discord',
+    CASE
+        WHEN discord_user_id < 0 THEN (discord_user_id::numeric + 18446744073709551616)::text
+        ELSE discord_user_id::text
+    END,
+    discord_username,
+    now()
+FROM ct_user
+WHERE discord_user_id IS NOT NULL;
+
+ALTER TABLE ct_user DROP COLUMN discord_user_id;
+
+-- The Discord user ID used to mention a user, in the signed bigint form
+-- previously stored in ct_user.  If several Discord accounts are linked, the
+-- one linked first is used.
+CREATE FUNCTION ct_user_discord_user_id(uid integer)
+RETURNS bigint
+    LANGUAGE sql STABLE
+    AS $$
+    SELECT
+        CASE
+            WHEN i.subject::numeric > 9223372036854775807
+                THEN (i.subject::numeric - 18446744073709551616)::bigint
+            ELSE i.subject::bigint
+        END
+
+    FROM ct_user_identity i
+
+    WHERE i.ct_user_id = uid
+    AND i.provider = 'discord'
+
+    ORDER BY i.id
+    LIMIT 1
+$$;
+
+CREATE OR REPLACE FUNCTION get_pending_inactivity_alerts(
+    alerted_before timestamp with time zone,
+    updated_after timestamp with time zone
+)
+RETURNS TABLE(
+    tracker_id uuid,
+    tracker_title text,
+    ap_game_id integer,
+    game_name text,
+    inactivity_color inactivity_color,
+    threshold_hours integer,
+    inactive_since timestamp with time zone,
+    claimant_ct_user_id integer,
+    claimant_discord_username text,
+    claimant_discord_user_id bigint,
+    owner_ct_user_id integer,
+    owner_discord_username text,
+    owner_discord_user_id bigint
+)
+    LANGUAGE sql STABLE
+    AS $$
+    SELECT
+        t.tracker_id,
+        t.title,
+        g.id,
+        g.name,
+        c.color,
+        CASE c.color
+            WHEN 'red' THEN t.inactivity_threshold_red_hours
+            ELSE t.inactivity_threshold_yellow_hours
+        END,
+        GREATEST(g.last_checked, g.last_activity),
+        g.claimed_by_ct_user_id,
+        g.effective_discord_username,
+        ct_user_discord_user_id(cu.id),
+        o.id,
+        o.discord_username,
+        ct_user_discord_user_id(o.id)
+
+    FROM ap_game g
+    INNER JOIN ap_tracker t
+        ON t.id = g.tracker_id
+    CROSS JOIN LATERAL slot_inactivity_color(
+        g.completion_status,
+        g.last_checked,
+        g.last_activity,
+        t.inactivity_threshold_yellow_hours,
+        t.inactivity_threshold_red_hours
+    ) c(color)
+    LEFT JOIN ct_user cu
+        ON cu.id = g.claimed_by_ct_user_id
+    LEFT JOIN ct_user o
+        ON o.id = t.owner_ct_user_id
+        AND NOT o.is_away
+    LEFT JOIN inactivity_alert a
+        ON a.ap_game_id = g.id
+        AND a.inactivity_color = c.color
+
+    WHERE g.effective_discord_username IS NOT NULL
+    AND NOT g.user_is_away
+    AND g.completion_status NOT IN ('done', 'released')
+    AND c.color <> 'green'
+    AND t.updated_at >= updated_after
+    AND (
+        a.alerted_at IS NULL
+        OR (
+            a.alerted_at < alerted_before
+            AND a.alerted_at < GREATEST(g.last_checked, g.last_activity)
+        )
+    )
+
+    ORDER BY t.id, g.position
+$$;
+
+CREATE OR REPLACE FUNCTION get_expired_claims(
+    as_of timestamp with time zone,
+    updated_after timestamp with time zone
+)
+RETURNS TABLE(
+    tracker_id uuid,
+    tracker_title text,
+    ap_game_id integer,
+    game_name text,
+    claim_expiry_days integer,
+    claim_expiry_notify boolean,
+    inactive_since timestamp with time zone,
+    claimant_ct_user_id integer,
+    claimant_discord_username text,
+    claimant_discord_user_id bigint
+)
+    LANGUAGE sql STABLE
+    AS $$
+    SELECT
+        t.tracker_id,
+        t.title,
+        g.id,
+        g.name,
+        t.claim_expiry_days,
+        t.claim_expiry_notify,
+        GREATEST(g.last_checked, g.last_activity),
+        g.claimed_by_ct_user_id,
+        COALESCE(cu.discord_username, g.discord_username),
+        ct_user_discord_user_id(cu.id)
+
+    FROM ap_game g
+    INNER JOIN ap_tracker t
+        ON t.id = g.tracker_id
+    LEFT JOIN ct_user cu
+        ON cu.id = g.claimed_by_ct_user_id
+
+    WHERE t.claim_expiry_days IS NOT NULL
+    AND (g.claimed_by_ct_user_id IS NOT NULL OR g.discord_username IS NOT NULL)
+    AND NOT g.user_is_away
+    AND g.completion_status NOT IN ('done', 'released')
+    AND GREATEST(g.last_checked, g.last_activity) < as_of - make_interval(days => t.claim_expiry_days)
+    AND t.updated_at >= updated_after
+
+    ORDER BY t.id, g.position
+$$;
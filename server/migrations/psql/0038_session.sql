@@ -0,0 +1,22 @@
+-- Server-side sessions.  A session is identified in its short-lived access
+-- tokens by the jti claim, and is renewed using a refresh token.
+CREATE TABLE ct_user_session (
+    id SERIAL NOT NULL PRIMARY KEY,
+    ct_user_id INTEGER NOT NULL REFERENCES ct_user (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    jti UUID NOT NULL UNIQUE,
+    -- Hash of the current refresh token.  The token itself is never stored.
+    refresh_token_hash TEXT NOT NULL UNIQUE,
+    -- When the user signed in.
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    -- When the session was last refreshed.
+    last_used_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    -- When the refresh token stops being accepted if it is not used.
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE NULL,
+    -- The client that last used the session.
+    user_agent TEXT NULL,
+    ipaddr INET NULL
+);
+
+CREATE INDEX idx_ct_user_session_ct_user_id
+    ON ct_user_session (ct_user_id);
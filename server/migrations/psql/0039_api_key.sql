@@ -0,0 +1,59 @@
+-- Named API keys.  A user may have any number of keys, each limited to the
+-- changes allowed by its scopes.  A key with no update scopes is read-only.
+CREATE TABLE api_key (
+    id SERIAL NOT NULL PRIMARY KEY,
+    ct_user_id INTEGER NOT NULL REFERENCES ct_user (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    name TEXT NOT NULL,
+    -- Hash of the key's secret.  The secret itself is never stored.
+    secret_hash TEXT NOT NULL UNIQUE,
+    -- Allows claiming and updating slots.
+    can_update_games BOOLEAN NOT NULL,
+    -- Allows updating hints.
+    can_update_hints BOOLEAN NOT NULL,
+    -- Allows changing tracker settings, members and ownership.
+    can_update_trackers BOOLEAN NOT NULL,
+    -- If false, the key can only make changes to the trackers listed in
+    -- api_key_tracker.
+    all_trackers BOOLEAN NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NULL,
+    last_used_at TIMESTAMP WITH TIME ZONE NULL
+);
+
+CREATE INDEX idx_api_key_ct_user_id
+    ON api_key (ct_user_id);
+
+CREATE TABLE api_key_tracker (
+    api_key_id INTEGER NOT NULL REFERENCES api_key (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    ap_tracker_id INTEGER NOT NULL REFERENCES ap_tracker (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    PRIMARY KEY (api_key_id, ap_tracker_id)
+);
+
+-- Existing keys keep working with full access.  They are sent as hyphenated
+-- UUIDs, which is the text form hashed here.
+INSERT INTO api_key (
+    ct_user_id,
+    name,
+    secret_hash,
+    can_update_games,
+    can_update_hints,
+    can_update_trackers,
+    all_trackers,
+    created_at
+)
+SELECT
+    id,
+    'API key',
+    encode(sha256(convert_to(api_key::text, 'UTF8')), 'base64'),
+    TRUE,
+    TRUE,
+    TRUE,
+    TRUE,
+    now()
+FROM ct_user
+WHERE api_key IS NOT NULL;
+
+ALTER TABLE ct_user DROP COLUMN api_key;
+
+ALTER TABLE audit ADD COLUMN actor_api_key_id INTEGER NULL
+    REFERENCES api_key (id) ON DELETE SET NULL ON UPDATE CASCADE;
@@ -0,0 +1,76 @@
+-- Slots that have never had any activity have no inactivity period to alert
+-- about; skip them instead of treating them as inactive since forever.
+CREATE OR REPLACE FUNCTION get_pending_inactivity_alerts(
+    alerted_before timestamp with time zone,
+    updated_after timestamp with time zone
+)
+RETURNS TABLE(
+    tracker_id uuid,
+    tracker_title text,
+    ap_game_id integer,
+    game_name text,
+    inactivity_color inactivity_color,
+    threshold_hours integer,
+    inactive_since timestamp with time zone,
+    claimant_ct_user_id integer,
+    claimant_discord_username text,
+    claimant_discord_user_id bigint,
+    owner_ct_user_id integer,
+    owner_discord_username text,
+    owner_discord_user_id bigint
+)
+    LANGUAGE sql STABLE
+    AS $$
+    SELECT
+        t.tracker_id,
+        t.title,
+        g.id,
+        g.name,
+        c.color,
+        CASE c.color
+            WHEN 'red' THEN t.inactivity_threshold_red_hours
+            ELSE t.inactivity_threshold_yellow_hours
+        END,
+        GREATEST(g.last_checked, g.last_activity),
+        g.claimed_by_ct_user_id,
+        g.effective_discord_username,
+        ct_user_discord_user_id(cu.id),
+        o.id,
+        o.discord_username,
+        ct_user_discord_user_id(o.id)
+
+    FROM ap_game g
+    INNER JOIN ap_tracker t
+        ON t.id = g.tracker_id
+    CROSS JOIN LATERAL slot_inactivity_color(
+        g.completion_status,
+        g.last_checked,
+        g.last_activity,
+        t.inactivity_threshold_yellow_hours,
+        t.inactivity_threshold_red_hours
+    ) c(color)
+    LEFT JOIN ct_user cu
+        ON cu.id = g.claimed_by_ct_user_id
+    LEFT JOIN ct_user o
+        ON o.id = t.owner_ct_user_id
+        AND NOT o.is_away
+    LEFT JOIN inactivity_alert a
+        ON a.ap_game_id = g.id
+        AND a.inactivity_color = c.color
+
+    WHERE g.effective_discord_username IS NOT NULL
+    AND NOT g.user_is_away
+    AND g.completion_status NOT IN ('done', 'released')
+    AND c.color <> 'green'
+    AND GREATEST(g.last_checked, g.last_activity) IS NOT NULL
+    AND t.updated_at >= updated_after
+    AND (
+        a.alerted_at IS NULL
+        OR (
+            a.alerted_at < alerted_before
+            AND a.alerted_at < GREATEST(g.last_checked, g.last_activity)
+        )
+    )
+
+    ORDER BY t.id, g.position
+$$;
@@ -0,0 +1,3 @@
+-- Requests to join a slot as a co-claimant, rather than to take over its
+-- claim.
+ALTER TABLE ap_game_claim_request ADD COLUMN co_claim BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,14 @@
+-- Names of users who only signed in through providers other than Discord are
+-- qualified with the provider, so that they can't pass for Discord usernames.
+UPDATE ct_user u
+SET discord_username = i.username || '@' || i.provider
+FROM ct_user_identity i
+WHERE i.ct_user_id = u.id
+AND i.provider <> 'discord'
+AND u.discord_username = i.username
+AND NOT EXISTS (
+    SELECT 1
+    FROM ct_user_identity d
+    WHERE d.ct_user_id = u.id
+    AND d.provider = 'discord'
+);
@@ -0,0 +1,12 @@
+-- Allows changing the user's settings, preferences and notifications.  Keys
+-- with full access keep it.
+ALTER TABLE api_key ADD COLUMN can_update_account BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE api_key
+SET can_update_account = TRUE
+WHERE can_update_games
+AND can_update_hints
+AND can_update_trackers
+AND all_trackers;
+
+ALTER TABLE api_key ALTER COLUMN can_update_account DROP DEFAULT;
@@ -0,0 +1,177 @@
+-- Computes the claimant information of all slots with a single pass over their
+-- claimants, instead of one correlated subquery per column.  The function is
+-- inlined into queries that join it laterally.
+CREATE FUNCTION ap_game_claim_info(gid integer, claimed_by integer, username text)
+RETURNS TABLE(
+    effective_discord_username text,
+    user_is_away boolean,
+    user_away_until timestamp with time zone,
+    user_away_message text
+)
+    LANGUAGE sql STABLE
+    AS $$
+    WITH claimant AS (
+        SELECT
+            u.discord_username,
+            u.is_away,
+            u.away_until,
+            u.away_message,
+            FALSE AS is_co_claimant,
+            NULL::timestamp with time zone AS claimed_at,
+            u.id
+        FROM ct_user u
+        WHERE u.id = claimed_by
+
+        UNION ALL
+
+        SELECT
+            u.discord_username,
+            u.is_away,
+            u.away_until,
+            u.away_message,
+            TRUE,
+            c.claimed_at,
+            u.id
+        FROM ap_game_claimant c
+        INNER JOIN ct_user u
+            ON u.id = c.ct_user_id
+        WHERE c.ap_game_id = gid
+    ),
+    summary AS (
+        SELECT
+            -- The claimant's username, or the username of the earliest
+            -- co-claimant if the slot has no claimant.
+            COALESCE(
+                MIN(discord_username) FILTER (WHERE NOT is_co_claimant),
+                username,
+                (array_agg(discord_username ORDER BY claimed_at, id) FILTER (WHERE is_co_claimant))[1]
+            ) AS effective_discord_username,
+            -- A slot's claimants are away only if every claimant is an away
+            -- user.  Unauthenticated claimants are never away.
+            (claimed_by IS NOT NULL OR username IS NULL) AND COALESCE(bool_and(is_away), FALSE) AS user_is_away,
+            MIN(away_until) AS away_until,
+            (array_agg(away_message ORDER BY is_co_claimant, claimed_at, id) FILTER (WHERE away_message <> ''))[1] AS away_message
+        FROM claimant
+    )
+    SELECT
+        effective_discord_username,
+        user_is_away,
+        -- When the claimants are away, the earliest time one of them is
+        -- expected back.
+        CASE WHEN user_is_away THEN away_until END,
+        -- When the claimants are away, the away message of the claimant, or of
+        -- the earliest co-claimant with a message.
+        CASE WHEN user_is_away THEN away_message END
+    FROM summary
+$$;
+
+CREATE OR REPLACE VIEW ap_game WITH (security_barrier='false', security_invoker='true') AS
+ SELECT g.id,
+    g.tracker_id,
+    g.name,
+    g.game,
+    g.checks_done,
+    g.checks_total,
+    g.last_activity,
+    g.discord_username,
+    g.last_checked,
+    g."position",
+    g.tracker_status,
+    g.notes,
+    g.discord_ping,
+    g.claimed_by_ct_user_id,
+    g.availability_status,
+    g.completion_status,
+    g.progression_status,
+    ci.effective_discord_username,
+    ci.user_is_away,
+    g.locked,
+    ci.user_away_until,
+    ci.user_away_message
+   FROM public.ap_game_store g
+   LEFT JOIN LATERAL public.ap_game_claim_info(g.id, g.claimed_by_ct_user_id, g.discord_username) ci ON TRUE;
+
+-- Writes through the view only return the written rows, so the rules look up
+-- each column separately.
+CREATE OR REPLACE RULE ap_game_delete_store AS
+    ON DELETE TO public.ap_game DO INSTEAD  DELETE FROM public.ap_game_store
+  WHERE (ap_game_store.id = old.id)
+  RETURNING ap_game_store.id,
+    ap_game_store.tracker_id,
+    ap_game_store.name,
+    ap_game_store.game,
+    ap_game_store.checks_done,
+    ap_game_store.checks_total,
+    ap_game_store.last_activity,
+    ap_game_store.discord_username,
+    ap_game_store.last_checked,
+    ap_game_store."position",
+    ap_game_store.tracker_status,
+    ap_game_store.notes,
+    ap_game_store.discord_ping,
+    ap_game_store.claimed_by_ct_user_id,
+    ap_game_store.availability_status,
+    ap_game_store.completion_status,
+    ap_game_store.progression_status,
+    (SELECT ci.effective_discord_username FROM public.ap_game_claim_info(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) ci) AS effective_discord_username,
+    (SELECT ci.user_is_away FROM public.ap_game_claim_info(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) ci) AS user_is_away,
+    ap_game_store.locked,
+    (SELECT ci.user_away_until FROM public.ap_game_claim_info(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) ci) AS user_away_until,
+    (SELECT ci.user_away_message FROM public.ap_game_claim_info(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) ci) AS user_away_message;
+
+CREATE OR REPLACE RULE ap_game_insert_store AS
+    ON INSERT TO public.ap_game DO INSTEAD  INSERT INTO public.ap_game_store (id, tracker_id, name, game, checks_done, checks_total, last_activity, discord_username, last_checked, "position", tracker_status, notes, discord_ping, claimed_by_ct_user_id, availability_status, completion_status, progression_status, locked)
+  VALUES (new.id, new.tracker_id, new.name, new.game, new.checks_done, new.checks_total, new.last_activity, new.discord_username, new.last_checked, new."position", new.tracker_status, new.notes, new.discord_ping, new.claimed_by_ct_user_id, new.availability_status, new.completion_status, new.progression_status, new.locked)
+  RETURNING ap_game_store.id,
+    ap_game_store.tracker_id,
+    ap_game_store.name,
+    ap_game_store.game,
+    ap_game_store.checks_done,
+    ap_game_store.checks_total,
+    ap_game_store.last_activity,
+    ap_game_store.discord_username,
+    ap_game_store.last_checked,
+    ap_game_store."position",
+    ap_game_store.tracker_status,
+    ap_game_store.notes,
+    ap_game_store.discord_ping,
+    ap_game_store.claimed_by_ct_user_id,
+    ap_game_store.availability_status,
+    ap_game_store.completion_status,
+    ap_game_store.progression_status,
+    (SELECT ci.effective_discord_username FROM public.ap_game_claim_info(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) ci) AS effective_discord_username,
+    (SELECT ci.user_is_away FROM public.ap_game_claim_info(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) ci) AS user_is_away,
+    ap_game_store.locked,
+    (SELECT ci.user_away_until FROM public.ap_game_claim_info(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) ci) AS user_away_until,
+    (SELECT ci.user_away_message FROM public.ap_game_claim_info(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) ci) AS user_away_message;
+
+CREATE OR REPLACE RULE ap_game_update_store AS
+    ON UPDATE TO public.ap_game DO INSTEAD  UPDATE public.ap_game_store SET id = new.id, tracker_id = new.tracker_id, name = new.name, game = new.game, checks_done = new.checks_done, checks_total = new.checks_total, last_activity = new.last_activity, discord_username = new.discord_username, last_checked = new.last_checked, "position" = new."position", tracker_status = new.tracker_status, notes = new.notes, discord_ping = new.discord_ping, claimed_by_ct_user_id = new.claimed_by_ct_user_id, availability_status = new.availability_status, completion_status = new.completion_status, progression_status = new.progression_status, locked = new.locked
+  WHERE (ap_game_store.id = old.id)
+  RETURNING ap_game_store.id,
+    ap_game_store.tracker_id,
+    ap_game_store.name,
+    ap_game_store.game,
+    ap_game_store.checks_done,
+    ap_game_store.checks_total,
+    ap_game_store.last_activity,
+    ap_game_store.discord_username,
+    ap_game_store.last_checked,
+    ap_game_store."position",
+    ap_game_store.tracker_status,
+    ap_game_store.notes,
+    ap_game_store.discord_ping,
+    ap_game_store.claimed_by_ct_user_id,
+    ap_game_store.availability_status,
+    ap_game_store.completion_status,
+    ap_game_store.progression_status,
+    (SELECT ci.effective_discord_username FROM public.ap_game_claim_info(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) ci) AS effective_discord_username,
+    (SELECT ci.user_is_away FROM public.ap_game_claim_info(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) ci) AS user_is_away,
+    ap_game_store.locked,
+    (SELECT ci.user_away_until FROM public.ap_game_claim_info(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) ci) AS user_away_until,
+    (SELECT ci.user_away_message FROM public.ap_game_claim_info(ap_game_store.id, ap_game_store.claimed_by_ct_user_id, ap_game_store.discord_username) ci) AS user_away_message;
+
+DROP FUNCTION ap_game_user_away_message;
+DROP FUNCTION ap_game_user_away_until;
+DROP FUNCTION ap_game_user_is_away;
+DROP FUNCTION ap_game_effective_discord_username;
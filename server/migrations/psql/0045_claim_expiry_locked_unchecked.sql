@@ -0,0 +1,84 @@
+-- When the slot was claimed.  This is NULL for slots without a claimant, and
+-- set to the time this migration ran for slots that were already claimed.
+ALTER TABLE ap_game_store ADD COLUMN claimed_at TIMESTAMP WITH TIME ZONE NULL;
+
+UPDATE ap_game_store
+SET claimed_at = now()
+WHERE claimed_by_ct_user_id IS NOT NULL OR discord_username IS NOT NULL;
+
+CREATE FUNCTION ap_game_store_set_claimed_at()
+RETURNS trigger
+    LANGUAGE plpgsql
+    AS $$
+BEGIN
+    IF TG_OP = 'INSERT'
+        OR NEW.claimed_by_ct_user_id IS DISTINCT FROM OLD.claimed_by_ct_user_id
+        OR (
+            NEW.claimed_by_ct_user_id IS NULL
+            AND NEW.discord_username IS DISTINCT FROM OLD.discord_username
+        )
+    THEN
+        NEW.claimed_at := CASE
+            WHEN NEW.claimed_by_ct_user_id IS NOT NULL OR NEW.discord_username IS NOT NULL
+            THEN now()
+        END;
+    END IF;
+
+    RETURN NEW;
+END
+$$;
+
+CREATE TRIGGER ap_game_store_claimed_at
+    BEFORE INSERT OR UPDATE ON ap_game_store
+    FOR EACH ROW EXECUTE FUNCTION ap_game_store_set_claimed_at();
+
+-- Locked slots are skipped.  Slots that have never had activity or been
+-- checked expire based on when they were claimed.
+CREATE OR REPLACE FUNCTION get_expired_claims(
+    as_of timestamp with time zone,
+    updated_after timestamp with time zone
+)
+RETURNS TABLE(
+    tracker_id uuid,
+    tracker_title text,
+    ap_game_id integer,
+    game_name text,
+    claim_expiry_days integer,
+    claim_expiry_notify boolean,
+    inactive_since timestamp with time zone,
+    claimant_ct_user_id integer,
+    claimant_discord_username text,
+    claimant_discord_user_id bigint
+)
+    LANGUAGE sql STABLE
+    AS $$
+    SELECT
+        t.tracker_id,
+        t.title,
+        g.id,
+        g.name,
+        t.claim_expiry_days,
+        t.claim_expiry_notify,
+        COALESCE(GREATEST(g.last_checked, g.last_activity), gs.claimed_at),
+        g.claimed_by_ct_user_id,
+        COALESCE(cu.discord_username, g.discord_username),
+        ct_user_discord_user_id(cu.id)
+
+    FROM ap_game g
+    INNER JOIN ap_game_store gs
+        ON gs.id = g.id
+    INNER JOIN ap_tracker t
+        ON t.id = g.tracker_id
+    LEFT JOIN ct_user cu
+        ON cu.id = g.claimed_by_ct_user_id
+
+    WHERE t.claim_expiry_days IS NOT NULL
+    AND (g.claimed_by_ct_user_id IS NOT NULL OR g.discord_username IS NOT NULL)
+    AND NOT g.user_is_away
+    AND NOT g.locked
+    AND g.completion_status NOT IN ('done', 'released')
+    AND COALESCE(GREATEST(g.last_checked, g.last_activity), gs.claimed_at) < as_of - make_interval(days => t.claim_expiry_days)
+    AND t.updated_at >= updated_after
+
+    ORDER BY t.id, g.position
+$$;
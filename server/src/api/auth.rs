@@ -1,30 +1,183 @@
 //! Authentication endpoints.
 
-use std::{future::ready, sync::Arc};
+use std::{future::ready, net::IpAddr, sync::Arc};
 
 use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
-use chrono::Utc;
+use axum_client_ip::ClientIp;
+use axum_extra::{TypedHeader, headers::UserAgent};
+use chrono::{DateTime, Utc};
 use futures::TryStreamExt;
 use oauth2::TokenResponse;
+use uuid::Uuid;
 
 use crate::{
+    auth::{continuation::AuthState, discord, secret},
     db::{
         DataAccess, DataAccessProvider, Transactable, Transaction, create_audit_for,
-        model::{CtUserIden, CtUserInsertion},
+        model::{
+            CtUserIden, CtUserIdentity, CtUserIdentityIden, CtUserIdentityInsertion,
+            CtUserInsertion, CtUserSessionIden, CtUserSessionInsertion,
+        },
     },
     logging::UnexpectedResultExt,
     send_hack::{send_future, send_stream},
     state::AppState,
 };
 
+/// A login provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthProvider {
+    /// Discord.
+    Discord,
+    /// The configured OpenID Connect provider.
+    Oidc,
+}
+
+/// Discord OAuth2 tokens of an account that completed authentication.
+pub struct DiscordTokens {
+    pub access_token: String,
+    pub expires_at: DateTime<Utc>,
+    pub refresh_token: String,
+}
+
+/// An account at a login provider that completed authentication.
+pub struct ProviderAccount {
+    /// The identifier of the provider, as recorded in identities.
+    pub provider_id: String,
+    /// The provider's stable identifier for the account.
+    pub subject: String,
+    /// The account's display name at the provider.
+    pub username: String,
+    /// The account's OAuth2 tokens, if it is a Discord account.
+    pub discord_tokens: Option<DiscordTokens>,
+}
+
+impl ProviderAccount {
+    /// The name to show for users who signed in with this account.
+    ///
+    /// Names are shown in the same places as Discord usernames, so names from
+    /// other providers are qualified with the provider's identifier.  This
+    /// keeps them from passing for Discord usernames, which can't contain `@`.
+    pub fn display_name(&self) -> String {
+        match self.provider_id == discord::PROVIDER_ID {
+            true => self.username.clone(),
+            false => format!("{}@{}", self.username, self.provider_id),
+        }
+    }
+}
+
+/// Begins an authentication attempt with a provider.
+///
+/// Fails with [`StatusCode::NOT_FOUND`] if the provider is not configured.
+pub async fn begin_provider_auth<D>(
+    state: &AppState<D>,
+    provider: AuthProvider,
+) -> Result<AuthState, StatusCode> {
+    match provider {
+        AuthProvider::Discord => state.auth_client.begin().unexpected(),
+        AuthProvider::Oidc => state
+            .oidc_auth_client
+            .as_ref()
+            .ok_or(StatusCode::NOT_FOUND)?
+            .begin()
+            .await
+            .unexpected(),
+    }
+}
+
+/// Completes an authentication attempt with a provider, returning the account
+/// that signed in.
+///
+/// Fails with [`StatusCode::NOT_FOUND`] if the provider is not configured.
+pub async fn complete_provider_auth<D>(
+    state: &AppState<D>,
+    provider: AuthProvider,
+    request: CompleteAuthRequest,
+) -> Result<ProviderAccount, StatusCode> {
+    #[derive(Debug, thiserror::Error)]
+    #[error("missing refresh token")]
+    struct MissingRefreshTokenError;
+
+    match provider {
+        AuthProvider::Discord => {
+            let token = state
+                .auth_client
+                .complete(request.code, &request.state, &request.continuation_token)
+                .await
+                .unexpected()?;
+
+            let expires_at = Utc::now()
+                + token
+                    .expires_in()
+                    .and_then(|d| chrono::Duration::from_std(d).ok())
+                    .unwrap_or_else(|| chrono::Duration::days(1));
+
+            let user_info = serenity::all::User::from(
+                serenity::http::Http::new(&format!("Bearer {}", token.access_token().secret()))
+                    .get_current_user()
+                    .await
+                    .unexpected()?,
+            );
+
+            Ok(ProviderAccount {
+                provider_id: discord::PROVIDER_ID.to_owned(),
+                subject: user_info.id.get().to_string(),
+                username: user_info.name,
+                discord_tokens: Some(DiscordTokens {
+                    access_token: token.access_token().secret().to_owned(),
+                    expires_at,
+                    refresh_token: token
+                        .refresh_token()
+                        .ok_or(MissingRefreshTokenError)
+                        .unexpected()?
+                        .secret()
+                        .to_owned(),
+                }),
+            })
+        }
+        AuthProvider::Oidc => {
+            let client = state
+                .oidc_auth_client
+                .as_ref()
+                .ok_or(StatusCode::NOT_FOUND)?;
+
+            let user_info = client
+                .complete(request.code, &request.state, &request.continuation_token)
+                .await
+                .unexpected()?;
+
+            let username = user_info.display_name().to_owned();
+
+            Ok(ProviderAccount {
+                provider_id: client.provider_id().to_owned(),
+                subject: user_info.sub,
+                username,
+                discord_tokens: None,
+            })
+        }
+    }
+}
+
 /// `GET /auth/begin`: Begin Discord authentication.
 pub async fn begin_discord_auth<D>(
     State(state): State<Arc<AppState<D>>>,
 ) -> Result<impl IntoResponse, StatusCode> {
-    state.auth_client.begin().unexpected().map(Json)
+    begin_provider_auth(&state, AuthProvider::Discord)
+        .await
+        .map(Json)
 }
 
-/// Request body for [`complete_discord_auth`].
+/// `GET /auth/oidc/begin`: Begin OpenID Connect authentication.
+pub async fn begin_oidc_auth<D>(
+    State(state): State<Arc<AppState<D>>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    begin_provider_auth(&state, AuthProvider::Oidc)
+        .await
+        .map(Json)
+}
+
+/// Request body for [`complete_discord_auth`] and [`complete_oidc_auth`].
 #[derive(serde::Deserialize)]
 pub struct CompleteAuthRequest {
     pub code: String,
@@ -32,54 +185,82 @@ pub struct CompleteAuthRequest {
     pub continuation_token: String,
 }
 
+/// Response body for [`complete_discord_auth`] and [`complete_oidc_auth`].
+#[derive(serde::Serialize)]
+pub struct CompleteAuthResponse {
+    /// Short-lived access token.
+    pub token: String,
+    /// Refresh token for the new session, used with [`refresh_session`].
+    pub refresh_token: String,
+    pub user_id: i32,
+    pub discord_username: String,
+}
+
 /// `POST /auth/complete`: Complete Discord authentication.
 pub async fn complete_discord_auth<D>(
     State(state): State<Arc<AppState<D>>>,
+    ClientIp(ip): ClientIp,
+    user_agent: Option<TypedHeader<UserAgent>>,
     Json(request): Json<CompleteAuthRequest>,
 ) -> Result<impl IntoResponse, StatusCode>
 where
     D: DataAccessProvider + Send + Sync + 'static,
 {
-    #[derive(Debug, thiserror::Error)]
-    #[error("missing refresh token")]
-    struct MissingRefreshTokenError;
+    let account = complete_provider_auth(&state, AuthProvider::Discord, request).await?;
 
-    #[derive(Debug, thiserror::Error)]
-    #[error("failed to insert Discord user {0} but user doesn't exist")]
-    struct MissingUserError(u64);
-
-    #[derive(serde::Serialize)]
-    struct Response {
-        token: String,
-        user_id: i32,
-        discord_username: String,
-    }
+    sign_in(&state, account, ip, user_agent_string(user_agent))
+        .await
+        .map(Json)
+}
 
-    let token = state
-        .auth_client
-        .complete(request.code, &request.state, &request.continuation_token)
+/// `POST /auth/oidc/complete`: Complete OpenID Connect authentication.
+pub async fn complete_oidc_auth<D>(
+    State(state): State<Arc<AppState<D>>>,
+    ClientIp(ip): ClientIp,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    Json(request): Json<CompleteAuthRequest>,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let account = complete_provider_auth(&state, AuthProvider::Oidc, request).await?;
+
+    sign_in(&state, account, ip, user_agent_string(user_agent))
         .await
-        .unexpected()?;
+        .map(Json)
+}
 
-    let expires_at = Utc::now()
-        + token
-            .expires_in()
-            .and_then(|d| chrono::Duration::from_std(d).ok())
-            .unwrap_or_else(|| chrono::Duration::days(1));
+/// Request body for [`refresh_session`].
+#[derive(serde::Deserialize)]
+pub struct RefreshSessionRequest {
+    pub refresh_token: String,
+}
 
-    let user_info = serenity::all::User::from(
-        serenity::http::Http::new(&format!("Bearer {}", token.access_token().secret()))
-            .get_current_user()
-            .await
-            .unexpected()?,
-    );
+/// Response body for [`refresh_session`].
+#[derive(serde::Serialize)]
+pub struct RefreshSessionResponse {
+    /// Short-lived access token.
+    pub token: String,
+    /// Refresh token replacing the one in the request.
+    pub refresh_token: String,
+}
 
-    // This may overflow, which is fine.  PostgreSQL doesn't support unsigned
-    // types; the alternative is NUMERIC or TEXT.
-    //
-    // The domains of i64 and u64 are the same size, and the cast is reversible,
-    // so we can cast back to u64 later to retrieve the true user ID.
-    let discord_user_id = user_info.id.get() as i64;
+/// `POST /auth/refresh`: Exchange a session's refresh token for a new access
+/// token and refresh token.
+///
+/// Each refresh token can be used only once.  Fails with
+/// [`StatusCode::UNAUTHORIZED`] if the session was revoked or has expired.
+pub async fn refresh_session<D>(
+    State(state): State<Arc<AppState<D>>>,
+    ClientIp(ip): ClientIp,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    Json(request): Json<RefreshSessionRequest>,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let now = Utc::now();
+    let old_hash = secret::hash(&request.refresh_token);
 
     let mut db = state
         .data_provider
@@ -87,34 +268,93 @@ where
         .await
         .unexpected()?;
 
-    let mut tx = db.begin().await.unexpected()?;
+    let mut session = db
+        .get_ct_user_session_by_refresh_token_hash(&old_hash)
+        .await
+        .unexpected()?
+        .filter(|s| s.revoked_at.is_none() && s.expires_at > now)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
 
-    // Try to insert the user.  If the user already exists, we'll fetch it
-    // below.
-    let r = {
-        let users = send_stream(
-            tx.create_ct_users([CtUserInsertion {
-                discord_access_token: token.access_token().secret().to_owned(),
-                discord_access_token_expires_at: expires_at,
-                discord_refresh_token: token
-                    .refresh_token()
-                    .ok_or(MissingRefreshTokenError)
-                    .unexpected()?
-                    .secret()
-                    .to_owned(),
-                discord_user_id,
-                discord_username: user_info.name.clone(),
-                api_key: None,
-                is_away: false,
-            }]),
-        );
+    let (refresh_token, hash) = secret::generate();
 
-        tokio::pin!(users);
+    session.refresh_token_hash = hash;
+    session.last_used_at = now;
+    session.expires_at = now + state.token_processor.session_validity();
+    session.ipaddr = Some(ip.into());
+    session.user_agent = user_agent_string(user_agent);
+
+    let session = db
+        .refresh_ct_user_session(
+            session,
+            &old_hash,
+            &[
+                CtUserSessionIden::RefreshTokenHash,
+                CtUserSessionIden::LastUsedAt,
+                CtUserSessionIden::ExpiresAt,
+                CtUserSessionIden::Ipaddr,
+                CtUserSessionIden::UserAgent,
+            ],
+        )
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    Ok(Json(RefreshSessionResponse {
+        token: state.token_processor.encode(&session).unexpected()?,
+        refresh_token,
+    }))
+}
+
+/// Converts an optional `User-Agent` header to the form stored in sessions.
+fn user_agent_string(user_agent: Option<TypedHeader<UserAgent>>) -> Option<String> {
+    user_agent.map(|TypedHeader(ua)| ua.as_str().to_owned())
+}
+
+/// Signs in as the user an account is linked to, creating a new user for the
+/// account if it isn't linked to one yet.
+///
+/// Signing in with a Discord account refreshes the user's Discord tokens and
+/// display name.  Users without any linked Discord account take their display
+/// name from the account they signed in with instead.
+async fn sign_in<D>(
+    state: &AppState<D>,
+    account: ProviderAccount,
+    ip: IpAddr,
+    user_agent: Option<String>,
+) -> Result<CompleteAuthResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    #[derive(Debug, thiserror::Error)]
+    #[error("failed to insert identity but identity doesn't exist")]
+    struct MissingIdentityError;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("identity {0} refers to a user that doesn't exist")]
+    struct MissingUserError(i32);
+
+    let now = Utc::now();
 
-        users.try_next().await
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let r = match tx
+        .get_ct_user_identity(&account.provider_id, &account.subject)
+        .await
+        .unexpected()?
+    {
+        Some(identity) => Ok(identity),
+        // Try to create a user for the account.  If another request created
+        // one concurrently, we'll fetch its identity below.
+        None => create_identity_user(&mut tx, &account, now).await,
     };
 
-    let ct_user = match r {
+    let mut identity = match r {
         Err(e)
             if e.as_database_error()
                 .is_some_and(|dbe| dbe.is_unique_violation()) =>
@@ -123,63 +363,147 @@ where
             send_future(tx.rollback()).await.unexpected()?;
             tx = db.begin().await.unexpected()?;
 
-            Ok(None)
-        }
-        v => v,
-    }
-    .unexpected()?;
-
-    let ct_user = match ct_user {
-        Some(u) => u,
-        None => {
-            let mut u = tx
-                .get_ct_user_by_discord_user_id(discord_user_id)
+            tx.get_ct_user_identity(&account.provider_id, &account.subject)
                 .await
                 .unexpected()?
-                .ok_or(MissingUserError(user_info.id.get()))
-                .unexpected()?;
+                .ok_or(MissingIdentityError)
+                .unexpected()?
+        }
+        v => v.unexpected()?,
+    };
 
-            let old_u = u.clone();
+    if identity.username != account.username {
+        identity.username = account.username.clone();
 
-            // The user already existed.  Update their token and username.
-            u.discord_access_token = token.access_token().secret().to_owned();
-            u.discord_access_token_expires_at = expires_at;
-            u.discord_refresh_token = token
-                .refresh_token()
-                .ok_or(MissingRefreshTokenError)
-                .unexpected()?
-                .secret()
-                .to_owned();
-            u.discord_username = user_info.name;
-
-            let audit = create_audit_for(None, None, Utc::now(), &old_u, &u);
-
-            tx.update_ct_user(
-                u.clone(),
-                &[
-                    CtUserIden::DiscordAccessToken,
-                    CtUserIden::DiscordAccessTokenExpiresAt,
-                    CtUserIden::DiscordRefreshToken,
-                    CtUserIden::DiscordUsername,
-                ],
-            )
+        tx.update_ct_user_identity(identity.clone(), &[CtUserIdentityIden::Username])
             .await
             .unexpected()?;
+    }
 
-            send_stream(tx.create_audits(audit))
-                .try_for_each(|_| ready(Ok(())))
+    let mut u = tx
+        .get_ct_user_by_id(identity.ct_user_id)
+        .await
+        .unexpected()?
+        .ok_or(MissingUserError(identity.id))
+        .unexpected()?;
+
+    let old_u = u.clone();
+
+    let display_name = account.display_name();
+
+    match account.discord_tokens {
+        Some(tokens) => {
+            u.discord_access_token = Some(tokens.access_token);
+            u.discord_access_token_expires_at = Some(tokens.expires_at);
+            u.discord_refresh_token = Some(tokens.refresh_token);
+            u.discord_username = display_name;
+        }
+        None => {
+            let identities: Vec<_> = send_stream(tx.get_ct_user_identities_by_user_id(u.id))
+                .try_collect()
                 .await
                 .unexpected()?;
 
-            u
+            if !identities
+                .iter()
+                .any(|i| i.provider == discord::PROVIDER_ID)
+            {
+                u.discord_username = display_name;
+            }
         }
+    }
+
+    let audit = create_audit_for(None, None, now, &old_u, &u);
+
+    tx.update_ct_user(
+        u.clone(),
+        &[
+            CtUserIden::DiscordAccessToken,
+            CtUserIden::DiscordAccessTokenExpiresAt,
+            CtUserIden::DiscordRefreshToken,
+            CtUserIden::DiscordUsername,
+        ],
+    )
+    .await
+    .unexpected()?;
+
+    send_stream(tx.create_audits(audit))
+        .try_for_each(|_| ready(Ok(())))
+        .await
+        .unexpected()?;
+
+    let (refresh_token, refresh_token_hash) = secret::generate();
+
+    let session = {
+        let sessions = send_stream(tx.create_ct_user_sessions([CtUserSessionInsertion {
+            ct_user_id: u.id,
+            jti: Uuid::new_v4(),
+            refresh_token_hash,
+            created_at: now,
+            last_used_at: now,
+            expires_at: now + state.token_processor.session_validity(),
+            revoked_at: None,
+            user_agent,
+            ipaddr: Some(ip.into()),
+        }]));
+
+        tokio::pin!(sessions);
+
+        sessions
+            .try_next()
+            .await
+            .unexpected()?
+            .ok_or("Session insertion returned no rows")
+            .unexpected()?
     };
 
     send_future(tx.commit()).await.unexpected()?;
 
-    Ok(Json(Response {
-        token: state.token_processor.encode(ct_user.id).unexpected()?,
-        user_id: ct_user.id,
-        discord_username: ct_user.discord_username,
-    }))
+    Ok(CompleteAuthResponse {
+        token: state.token_processor.encode(&session).unexpected()?,
+        refresh_token,
+        user_id: u.id,
+        discord_username: u.discord_username,
+    })
+}
+
+/// Creates a new user for an account at a login provider, along with the
+/// identity linking the account to the user.
+async fn create_identity_user(
+    db: &mut (impl DataAccess + Send),
+    account: &ProviderAccount,
+    now: DateTime<Utc>,
+) -> sqlx::Result<CtUserIdentity> {
+    let tokens = account.discord_tokens.as_ref();
+
+    let user = {
+        let users = send_stream(db.create_ct_users([CtUserInsertion {
+            discord_access_token: tokens.map(|t| t.access_token.clone()),
+            discord_access_token_expires_at: tokens.map(|t| t.expires_at),
+            discord_refresh_token: tokens.map(|t| t.refresh_token.clone()),
+            discord_username: account.display_name(),
+            is_away: false,
+            away_from: None,
+            away_until: None,
+            away_message: "".to_owned(),
+            default_ping_preference: None,
+            default_availability_status: None,
+        }]));
+
+        tokio::pin!(users);
+
+        users.try_next().await?.ok_or(sqlx::Error::RowNotFound)?
+    };
+
+    let identities = send_stream(db.create_ct_user_identities([CtUserIdentityInsertion {
+        ct_user_id: user.id,
+        provider: account.provider_id.clone(),
+        subject: account.subject.clone(),
+        username: account.username.clone(),
+        created_at: now,
+    }]));
+
+    tokio::pin!(identities);
+
+    identities.try_next().await?.ok_or(sqlx::Error::RowNotFound)
 }
@@ -1,6 +1,6 @@
 //! API endpoints and related facilities.
 
-use std::{future::ready, sync::Arc};
+use std::{borrow::Cow, future::ready, sync::Arc};
 
 use axum::{
     Json,
@@ -21,6 +21,7 @@ use crate::{
 
 pub mod auth;
 pub mod dashboard;
+pub mod policy;
 pub mod tracker;
 pub mod user;
 
@@ -34,6 +35,9 @@ where
     axum::Router::new()
         .route("/auth/begin", get(auth::begin_discord_auth))
         .route("/auth/complete", post(auth::complete_discord_auth))
+        .route("/auth/oidc/begin", get(auth::begin_oidc_auth))
+        .route("/auth/oidc/complete", post(auth::complete_oidc_auth))
+        .route("/auth/refresh", post(auth::refresh_session))
         .route("/dashboard/tracker", get(dashboard::get_dashboard_trackers))
         .route("/tracker", post(tracker::create_tracker))
         .route("/tracker/{tracker_id}", get(tracker::get_tracker))
@@ -42,10 +46,70 @@ where
             "/tracker/{tracker_id}/game/{game_id}",
             put(tracker::update_game),
         )
+        .route(
+            "/tracker/{tracker_id}/game/{game_id}/unclaim",
+            post(tracker::force_unclaim_game),
+        )
+        .route(
+            "/tracker/{tracker_id}/game/{game_id}/reassign",
+            post(tracker::reassign_game),
+        )
+        .route(
+            "/tracker/{tracker_id}/game/{game_id}/lock",
+            put(tracker::lock_game),
+        )
+        .route(
+            "/tracker/{tracker_id}/game/{game_id}/claimant",
+            put(tracker::put_co_claimant),
+        )
+        .route(
+            "/tracker/{tracker_id}/game/{game_id}/claimant/{ct_user_id}",
+            delete(tracker::delete_co_claimant),
+        )
+        .route(
+            "/tracker/{tracker_id}/game/{game_id}/claim_request",
+            post(tracker::create_claim_request),
+        )
+        .route(
+            "/tracker/{tracker_id}/claim_request/{request_id}",
+            delete(tracker::withdraw_claim_request),
+        )
+        .route(
+            "/tracker/{tracker_id}/claim_request/{request_id}/approve",
+            post(tracker::approve_claim_request),
+        )
+        .route(
+            "/tracker/{tracker_id}/claim_request/{request_id}/deny",
+            post(tracker::deny_claim_request),
+        )
+        .route(
+            "/tracker/{tracker_id}/game/{game_id}/todo",
+            get(tracker::get_game_todo),
+        )
         .route(
             "/tracker/{tracker_id}/hint/{hint_id}",
             put(tracker::update_hint),
         )
+        .route(
+            "/tracker/{tracker_id}/member/{ct_user_id}",
+            put(tracker::put_tracker_member),
+        )
+        .route(
+            "/tracker/{tracker_id}/member/{ct_user_id}",
+            delete(tracker::delete_tracker_member),
+        )
+        .route(
+            "/tracker/{tracker_id}/ownership_transfer",
+            put(tracker::put_ownership_transfer),
+        )
+        .route(
+            "/tracker/{tracker_id}/ownership_transfer",
+            delete(tracker::delete_ownership_transfer),
+        )
+        .route(
+            "/tracker/{tracker_id}/ownership_transfer/accept",
+            post(tracker::accept_ownership_transfer),
+        )
         .route(
             "/tracker/{tracker_id}/dashboard_override",
             get(tracker::get_tracker_dashboard_override),
@@ -54,16 +118,66 @@ where
             "/tracker/{tracker_id}/dashboard_override",
             put(tracker::put_tracker_dashboard_override),
         )
+        .route(
+            "/tracker/{tracker_id}/notification_mute",
+            get(tracker::get_tracker_notification_mute),
+        )
+        .route(
+            "/tracker/{tracker_id}/notification_mute",
+            put(tracker::put_tracker_notification_mute),
+        )
         .route(
             "/tracker/{tracker_id}/checks_history",
             get(tracker::get_checks_history),
         )
+        .route(
+            "/tracker/{tracker_id}/stats",
+            get(tracker::get_tracker_stats),
+        )
+        .route(
+            "/tracker/{tracker_id}/blockers",
+            get(tracker::get_tracker_blockers),
+        )
         .route("/user/self", get(user::get_self))
-        .route("/user/self/api_key", get(user::get_api_key))
-        .route("/user/self/api_key", post(user::reset_api_key))
-        .route("/user/self/api_key", delete(user::clear_api_key))
+        .route("/user/self/api_keys", get(user::get_api_keys))
+        .route("/user/self/api_keys", post(user::create_api_key))
+        .route("/user/self/api_keys/{id}", delete(user::delete_api_key))
         .route("/user/self/settings", get(user::get_settings))
         .route("/user/self/settings", put(user::put_settings))
+        .route(
+            "/user/self/game_preferences",
+            get(user::get_game_preferences),
+        )
+        .route(
+            "/user/self/game_preferences/{game}",
+            put(user::put_game_preference),
+        )
+        .route(
+            "/user/self/game_preferences/{game}",
+            delete(user::delete_game_preference),
+        )
+        .route("/user/self/identities", get(user::get_identities))
+        .route("/user/self/identities", post(user::link_identity))
+        .route("/user/self/identities/link", get(user::begin_link_identity))
+        .route("/user/self/identities/{id}", delete(user::unlink_identity))
+        .route("/user/self/sessions", get(user::get_sessions))
+        .route("/user/self/sessions", delete(user::revoke_all_sessions))
+        .route(
+            "/user/self/sessions/current",
+            delete(user::revoke_current_session),
+        )
+        .route("/user/self/sessions/{id}", delete(user::revoke_session))
+        .route("/user/self/games", get(user::get_games))
+        .route("/user/self/hints", get(user::get_hints))
+        .route("/user/self/notifications", get(user::get_notifications))
+        .route(
+            "/user/self/notifications/unread_count",
+            get(user::get_unread_notification_count),
+        )
+        .route(
+            "/user/self/notifications/read",
+            post(user::mark_notifications_read),
+        )
         .route("/settings", get(get_settings))
         .route("/jserror", post(create_js_error))
         // Since UI settings are in a header added by middleware, this no-op
@@ -92,6 +206,50 @@ where
         ))
 }
 
+/// An error response with a human-readable explanation.
+///
+/// Handlers that need to tell the user why a request was rejected can return
+/// this instead of a bare [`StatusCode`].  Status codes convert into this type,
+/// so `.unexpected()?` can still be used in those handlers.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: Cow<'static, str>,
+}
+
+impl ApiError {
+    /// Creates an error with the given status code and message.
+    pub fn new(status: StatusCode, message: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        Self::new(status, status.canonical_reason().unwrap_or_default())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> axum::response::Response {
+        #[derive(serde::Serialize)]
+        struct ErrorResponse {
+            error: Cow<'static, str>,
+        }
+
+        (
+            self.status,
+            Json(ErrorResponse {
+                error: self.message,
+            }),
+        )
+            .into_response()
+    }
+}
+
 /// UI settings.
 ///
 /// The API router will encode this as JSON and put it in the `x-ct-settings`
@@ -110,6 +268,10 @@ pub struct UiSettings {
     /// Banners that should be displayed in the frontend.
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub banners: Vec<Banner>,
+    /// Display name of the OpenID Connect provider, if OIDC sign-in is
+    /// enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oidc_provider: Option<String>,
 }
 
 /// `GET /api/settings`: Get the current [UI settings](UiSettings).
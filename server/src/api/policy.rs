@@ -0,0 +1,519 @@
+//! Tracker permission policy.
+//!
+//! All decisions about what a user may change on a tracker are made here, based
+//! on the user's [role](TrackerRole) in the tracker and the tracker's settings.
+//!
+//! * The owner and organizers can change all tracker settings, including the
+//!   claim expiry policy, edit locked slots, and moderate slots.
+//! * Only the owner can manage the tracker's members, though members may leave
+//!   a tracker on their own.
+//! * Only the owner can offer to transfer ownership, and only the recipient of
+//!   the offer can accept it.
+//! * Requests to take over a slot's claim can be approved or denied by the
+//!   slot's claimant and the tracker's organizers.
+//! * Co-claimants can be removed by themselves, the slot's claimant, and the
+//!   tracker's organizers.
+//! * When a tracker's settings are locked, other users can't change tracker
+//!   settings and can only change hints involving slots they have claimed or
+//!   co-claimed.
+//! * Viewers have no permissions beyond those of other users.
+//! * Requests made with an API key are additionally limited to the key's
+//!   scopes: updating slots, updating hints, and changing tracker settings,
+//!   members and ownership.  Keys may be limited to specific trackers.
+
+use std::future::ready;
+
+use axum::http::StatusCode;
+use futures::TryStreamExt;
+
+use crate::{
+    api::ApiError,
+    auth::{api_key::ApiKeyScopes, token::AuthenticatedUser},
+    db::{
+        DataAccess,
+        model::{ApGame, ApGameClaimRequest, ApTracker, ApTrackerOwnershipTransfer, TrackerRole},
+    },
+};
+
+/// The permissions of a user on a tracker.
+#[derive(Debug, Clone)]
+pub struct TrackerPolicy {
+    user_id: Option<i32>,
+    role: Option<TrackerRole>,
+    /// The IDs of the tracker's slots the user is a co-claimant of.
+    co_claimed_game_ids: Vec<i32>,
+    lock_settings: bool,
+    require_authentication_to_claim: bool,
+    scopes: ApiKeyScopes,
+}
+
+impl TrackerPolicy {
+    /// Determines the permissions of a user on a tracker.  `user` is `None` for
+    /// unauthenticated requests.
+    pub async fn load(
+        db: &mut (impl DataAccess + Send),
+        tracker: &ApTracker,
+        user: Option<&AuthenticatedUser>,
+    ) -> sqlx::Result<Self> {
+        let user_id = user.map(|u| u.user.id);
+
+        let role = match user_id {
+            None => None,
+            Some(uid) if tracker.owner_ct_user_id == Some(uid) => Some(TrackerRole::Owner),
+            Some(uid) => db
+                .get_ap_tracker_member(uid, tracker.id)
+                .await?
+                .map(|m| m.role),
+        };
+
+        let co_claimed_game_ids = match user_id {
+            None => vec![],
+            Some(uid) => {
+                db.get_ap_game_claimants_by_tracker_id(tracker.id)
+                    .try_filter(|c| ready(c.ct_user_id == uid))
+                    .map_ok(|c| c.ap_game_id)
+                    .try_collect()
+                    .await?
+            }
+        };
+
+        Ok(Self {
+            user_id,
+            role,
+            co_claimed_game_ids,
+            lock_settings: tracker.lock_settings,
+            require_authentication_to_claim: tracker.require_authentication_to_claim,
+            scopes: user.map_or(ApiKeyScopes::ALL, |u| u.scopes_for(tracker.id)),
+        })
+    }
+
+    /// Whether the user is the tracker's owner or one of its organizers.
+    pub fn is_organizer(&self) -> bool {
+        matches!(self.role, Some(TrackerRole::Owner | TrackerRole::Organizer))
+    }
+
+    /// Whether the user is the claimant or one of the co-claimants of a slot.
+    fn is_claimant(&self, game: &ApGame) -> bool {
+        self.user_id.is_some()
+            && (game.claimed_by_ct_user_id == self.user_id
+                || self.co_claimed_game_ids.contains(&game.id))
+    }
+
+    /// Rejects the request if the user isn't authenticated.
+    fn require_user(&self, message: &'static str) -> Result<i32, ApiError> {
+        self.user_id
+            .ok_or_else(|| ApiError::new(StatusCode::UNAUTHORIZED, message))
+    }
+
+    /// Rejects the request if it was made with an API key whose scopes don't
+    /// include the change.
+    fn require_scope(allowed: bool, message: &'static str) -> Result<(), ApiError> {
+        if !allowed {
+            return Err(ApiError::new(StatusCode::FORBIDDEN, message));
+        }
+
+        Ok(())
+    }
+
+    /// Rejects the request if it may not update slots.
+    fn require_games_scope(&self) -> Result<(), ApiError> {
+        Self::require_scope(
+            self.scopes.update_games,
+            "This API key can't update slots on this tracker.",
+        )
+    }
+
+    /// Rejects the request if it may not update hints.
+    fn require_hints_scope(&self) -> Result<(), ApiError> {
+        Self::require_scope(
+            self.scopes.update_hints,
+            "This API key can't update hints on this tracker.",
+        )
+    }
+
+    /// Rejects the request if it may not change the tracker itself.
+    fn require_trackers_scope(&self) -> Result<(), ApiError> {
+        Self::require_scope(
+            self.scopes.update_trackers,
+            "This API key can't change this tracker's settings, members or ownership.",
+        )
+    }
+
+    /// Checks that the user may change the tracker's owner from `old` to `new`.
+    ///
+    /// The only valid changes are claiming ownership of a tracker without an
+    /// owner and disclaiming ownership, and either way the affected user must
+    /// be the authenticated user.
+    pub fn authorize_ownership_change(
+        user: Option<&AuthenticatedUser>,
+        old: Option<i32>,
+        new: Option<i32>,
+    ) -> Result<(), ApiError> {
+        let user = user.ok_or_else(|| {
+            ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "You must sign in to change the tracker's owner.",
+            )
+        })?;
+
+        match (old, new) {
+            (None, Some(uid)) | (Some(uid), None) if uid == user.user.id => Ok(()),
+            _ => Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "You can only claim a tracker without an owner or disclaim your own tracker.",
+            )),
+        }
+    }
+
+    /// Checks that the user may update the tracker's settings.
+    ///
+    /// `restricted_changed` indicates whether the update changes settings that
+    /// only organizers may change: locking settings, the description,
+    /// requiring authentication to claim, or the claim expiry policy.
+    pub fn authorize_settings_update(&self, restricted_changed: bool) -> Result<(), ApiError> {
+        self.require_trackers_scope()?;
+
+        if self.is_organizer() {
+            return Ok(());
+        }
+
+        if self.lock_settings {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "This tracker's settings are locked.  Only the tracker's organizers can change them.",
+            ));
+        }
+
+        if restricted_changed {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "Only the tracker's organizers can lock settings, change the description, \
+                require authentication to claim, or change the claim expiry policy.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the user may update a game.
+    pub fn authorize_game_update(&self, game: &ApGame) -> Result<(), ApiError> {
+        self.require_games_scope()?;
+
+        if game.locked && !self.is_organizer() {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "This slot has been locked by the tracker's organizers.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the user may change the claim on a game to the given user ID
+    /// or unauthenticated username.
+    pub fn authorize_claim(
+        &self,
+        game: &ApGame,
+        claimed_by_ct_user_id: Option<i32>,
+        discord_username: Option<&str>,
+    ) -> Result<(), ApiError> {
+        self.require_games_scope()?;
+
+        // If the claimed user ID is changing to a value other than None, it
+        // must match the authenticated user's ID.
+        if claimed_by_ct_user_id != game.claimed_by_ct_user_id
+            && let Some(uid) = claimed_by_ct_user_id
+        {
+            // A user trying to claim while unauthenticated probably has an
+            // expired token.
+            let user_id = self.require_user("You must sign in to claim this slot.")?;
+
+            if uid != user_id {
+                return Err(ApiError::new(
+                    StatusCode::FORBIDDEN,
+                    "You can't claim a slot on behalf of another user.",
+                ));
+            }
+        }
+
+        // Don't allow a new unauthenticated claim if the tracker disallows it.
+        if claimed_by_ct_user_id.is_none()
+            && discord_username.is_some()
+            && discord_username != game.discord_username.as_deref()
+            && self.require_authentication_to_claim
+        {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "This tracker requires you to sign in to claim slots.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the user may update a hint found by `finder` for
+    /// `receiver`.
+    ///
+    /// When settings are locked, only the claimants and co-claimants of the
+    /// finding and receiving slots and the tracker's organizers may change
+    /// hints.
+    pub fn authorize_hint_update(
+        &self,
+        finder: &ApGame,
+        receiver: Option<&ApGame>,
+    ) -> Result<(), ApiError> {
+        self.require_hints_scope()?;
+
+        if !self.lock_settings || self.is_organizer() {
+            return Ok(());
+        }
+
+        self.require_user(
+            "This tracker's settings are locked.  You must sign in to change hints.",
+        )?;
+
+        if !self.is_claimant(finder) && receiver.is_none_or(|r| !self.is_claimant(r)) {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "This tracker's settings are locked.  Only the claimants of the finding and \
+                receiving slots and the tracker's organizers can change this hint.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the user may change the acknowledging user of a hint found
+    /// by `finder` from `old` to `new`.
+    ///
+    /// Users can only acknowledge hints as themselves.  An existing
+    /// acknowledgement can only be removed or replaced by the user who made it,
+    /// the claimants and co-claimants of the finding slot, and the tracker's
+    /// organizers.
+    pub fn authorize_hint_acknowledgement(
+        &self,
+        finder: &ApGame,
+        old: Option<i32>,
+        new: Option<i32>,
+    ) -> Result<(), ApiError> {
+        self.require_hints_scope()?;
+
+        if old == new {
+            return Ok(());
+        }
+
+        if let Some(uid) = new
+            && uid != self.require_user("You must sign in to acknowledge hints.")?
+        {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "You can't acknowledge a hint on behalf of another user.",
+            ));
+        }
+
+        if let Some(uid) = old
+            && uid != self.require_user("You must sign in to remove hint acknowledgements.")?
+            && !self.is_claimant(finder)
+            && !self.is_organizer()
+        {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "Only the user who acknowledged this hint, the claimants of the finding slot \
+                and the tracker's organizers can remove its acknowledgement.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the user may moderate the tracker's slots.
+    pub fn authorize_moderation(&self) -> Result<(), ApiError> {
+        self.require_user("You must sign in to moderate slots.")?;
+        self.require_games_scope()?;
+
+        if !self.is_organizer() {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "Only the tracker's organizers can moderate slots.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the user may manage the tracker's members.
+    pub fn authorize_member_management(&self) -> Result<(), ApiError> {
+        self.require_user("You must sign in to manage members.")?;
+        self.require_trackers_scope()?;
+
+        if self.role != Some(TrackerRole::Owner) {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "Only the tracker's owner can manage members.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the user may remove the given user from the tracker's
+    /// members.  Members may always remove themselves.
+    pub fn authorize_member_removal(&self, ct_user_id: i32) -> Result<(), ApiError> {
+        self.require_trackers_scope()?;
+
+        if self.require_user("You must sign in to manage members.")? == ct_user_id {
+            return Ok(());
+        }
+
+        self.authorize_member_management()
+    }
+
+    /// Checks that the user may offer to transfer ownership of the tracker.
+    pub fn authorize_ownership_transfer_offer(&self) -> Result<(), ApiError> {
+        self.require_user("You must sign in to transfer ownership.")?;
+        self.require_trackers_scope()?;
+
+        if self.role != Some(TrackerRole::Owner) {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "Only the tracker's owner can transfer ownership.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the user may withdraw or decline an ownership transfer
+    /// offer.  This is allowed for the tracker's owner and the offer's
+    /// recipient.
+    pub fn authorize_ownership_transfer_cancel(
+        &self,
+        transfer: &ApTrackerOwnershipTransfer,
+    ) -> Result<(), ApiError> {
+        self.require_trackers_scope()?;
+
+        if self.require_user("You must sign in to decline an ownership transfer.")?
+            == transfer.to_ct_user_id
+        {
+            return Ok(());
+        }
+
+        self.authorize_ownership_transfer_offer()
+    }
+
+    /// Checks that the user may accept an ownership transfer offer.
+    pub fn authorize_ownership_transfer_accept(
+        &self,
+        transfer: &ApTrackerOwnershipTransfer,
+    ) -> Result<(), ApiError> {
+        self.require_trackers_scope()?;
+
+        if self.require_user("You must sign in to accept an ownership transfer.")?
+            != transfer.to_ct_user_id
+        {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "This ownership transfer was offered to another user.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the user may join a game as a co-claimant or change their
+    /// co-claim.
+    ///
+    /// Only organizers may join a game directly.  Other users must request to
+    /// join it, and the request must be approved by the game's claimant or the
+    /// tracker's organizers.  Existing co-claimants may change their co-claim.
+    pub fn authorize_co_claim(&self, game: &ApGame, is_co_claimant: bool) -> Result<i32, ApiError> {
+        let user_id = self.require_user("You must sign in to co-claim this slot.")?;
+
+        self.authorize_game_update(game)?;
+
+        if !is_co_claimant && !self.is_organizer() {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "You must ask the slot's claimant to let you join this slot.",
+            ));
+        }
+
+        Ok(user_id)
+    }
+
+    /// Checks that the user may remove the given co-claimant from a game.
+    ///
+    /// Co-claimants may remove themselves, and the game's claimant may remove
+    /// its co-claimants.
+    pub fn authorize_co_claimant_removal(
+        &self,
+        game: &ApGame,
+        ct_user_id: i32,
+    ) -> Result<(), ApiError> {
+        let user_id = self.require_user("You must sign in to remove co-claimants.")?;
+        self.require_games_scope()?;
+
+        if self.is_organizer() {
+            return Ok(());
+        }
+
+        if user_id != ct_user_id && game.claimed_by_ct_user_id != Some(user_id) {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "Only the slot's claimant and the tracker's organizers can remove other \
+                co-claimants.",
+            ));
+        }
+
+        self.authorize_game_update(game)
+    }
+
+    /// Checks that the user may request to take over the claim on a game.
+    pub fn authorize_claim_request(&self) -> Result<i32, ApiError> {
+        let user_id = self.require_user("You must sign in to request a slot.")?;
+        self.require_games_scope()?;
+
+        Ok(user_id)
+    }
+
+    /// Checks that the user may approve or deny a request to take over the
+    /// claim on a game.
+    pub fn authorize_claim_request_resolution(&self, game: &ApGame) -> Result<(), ApiError> {
+        let user_id = self.require_user("You must sign in to respond to slot requests.")?;
+        self.require_games_scope()?;
+
+        if self.is_organizer() {
+            return Ok(());
+        }
+
+        if game.claimed_by_ct_user_id != Some(user_id) {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "Only the slot's claimant and the tracker's organizers can respond to requests \
+                for this slot.",
+            ));
+        }
+
+        self.authorize_game_update(game)
+    }
+
+    /// Checks that the user may withdraw a request to take over the claim on a
+    /// game.  Only the user who made the request may withdraw it.
+    pub fn authorize_claim_request_withdrawal(
+        &self,
+        request: &ApGameClaimRequest,
+    ) -> Result<(), ApiError> {
+        self.require_games_scope()?;
+
+        if self.require_user("You must sign in to withdraw a slot request.")?
+            != request.requested_by_ct_user_id
+        {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "Only the user who made this request can withdraw it.",
+            ));
+        }
+
+        Ok(())
+    }
+}
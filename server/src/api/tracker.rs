@@ -1,36 +1,40 @@
 //! Tracker endpoints.
 
-use std::{
-    collections::{HashMap, HashSet, hash_map::Entry},
-    future::ready,
-    sync::Arc,
-};
+use std::{collections::HashMap, future::ready, net::IpAddr, sync::Arc};
 
 use axum::{
     Json,
-    extract::{Path, State},
-    http::{HeaderName, StatusCode},
+    extract::{Path, Query, State},
+    http::{HeaderName, StatusCode, header},
     response::IntoResponse,
 };
 use axum_client_ip::ClientIp;
 use axum_extra::{TypedHeader, headers::Header};
 use chrono::{DateTime, DurationRound, TimeDelta, Utc};
-use futures::{StreamExt, TryStreamExt};
+use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
+use url::Url;
 
 use crate::{
     ap_api::UrlEncodedUuid,
+    api::{ApiError, policy::TrackerPolicy},
     auth::token::AuthenticatedUser,
     db::{
         DataAccess, DataAccessProvider, Transactable, Transaction, create_audit_for,
+        create_co_claimant_audit,
         model::{
-            ApGame, ApGameIden, ApHint, ApHintIden, ApTracker, ApTrackerDashboardOverride,
-            ApTrackerIden, AvailabilityStatus, CompletionStatus, HintClassification,
-            PingPreference, ProgressionStatus, UpdateCompletionStatus,
+            ApGame, ApGameClaimRequest, ApGameClaimRequestIden, ApGameClaimRequestInsertion,
+            ApGameClaimant, ApGameIden, ApHint, ApHintIden, ApTracker, ApTrackerDashboardOverride,
+            ApTrackerIden, ApTrackerMember, ApTrackerNotificationMute, ApTrackerOwnershipTransfer,
+            ApTrackerOwnershipTransferInsertion, AvailabilityStatus, ClaimRequestStatus,
+            CompletionStatus, CtUser, HintClassification, InactivityColor, PingPreference,
+            ProgressionStatus, TrackerRole, UpdateCompletionStatus,
         },
     },
-    diff::FieldDiff,
+    forecast::{FORECAST_WINDOW, TrackerForecast, forecast_tracker},
+    hints::{blocker_graph, rank_todo_hints, render_dot_svg},
     logging::{UnexpectedResultExt, log},
+    notify::{self, Event, TrackerReference},
     send_hack::{send_future, send_stream},
     state::{AppState, GetRoomLinkError, TrackerUpdateError},
 };
@@ -66,6 +70,9 @@ where
         pub inactivity_threshold_yellow_hours: i32,
         pub inactivity_threshold_red_hours: i32,
         pub require_authentication_to_claim: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub claim_expiry_days: Option<i32>,
+        pub claim_expiry_notify: bool,
     }
 
     impl Tracker {
@@ -89,6 +96,8 @@ where
                 inactivity_threshold_yellow_hours: tracker.inactivity_threshold_yellow_hours,
                 inactivity_threshold_red_hours: tracker.inactivity_threshold_red_hours,
                 require_authentication_to_claim: tracker.require_authentication_to_claim,
+                claim_expiry_days: tracker.claim_expiry_days,
+                claim_expiry_notify: tracker.claim_expiry_notify,
             })
         }
     }
@@ -100,9 +109,18 @@ where
         #[serde(skip_serializing_if = "Option::is_none")]
         pub owner_discord_username: Option<String>,
         pub games: Vec<ApGame>,
-        pub hints: Vec<ApHint>,
+        pub hints: Vec<HintResponse>,
+        pub members: Vec<TrackerMemberResponse>,
+        pub claim_requests: Vec<ClaimRequestResponse>,
+        pub co_claimants: Vec<CoClaimantResponse>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub ownership_transfer: Option<OwnershipTransferResponse>,
         #[serde(skip_serializing_if = "Option::is_none")]
         pub dashboard_override_visibility: Option<bool>,
+        /// Whether the user muted in-app notifications about the tracker.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub notifications_muted: Option<bool>,
+        pub forecast: TrackerForecast,
     }
 
     let upstream_url = state
@@ -164,7 +182,7 @@ where
         }
     };
 
-    let games = tx
+    let games: Vec<_> = tx
         .get_ap_games_by_tracker_id(tracker.id)
         .try_collect()
         .await
@@ -176,6 +194,43 @@ where
         .await
         .unexpected()?;
 
+    let hints = HintResponse::from_hints(&mut tx, hints)
+        .await
+        .unexpected()?;
+
+    let members = get_tracker_members(&mut tx, tracker.id)
+        .await
+        .unexpected()?;
+
+    let claim_requests = get_claim_requests(&mut tx, tracker.id).await.unexpected()?;
+
+    let co_claimants = get_co_claimants(&mut tx, tracker.id).await.unexpected()?;
+
+    let now = Utc::now();
+
+    let ownership_transfer = get_ownership_transfer(&mut tx, &tracker, now)
+        .await
+        .unexpected()?;
+
+    let recent_checks_history: Vec<_> = tx
+        .get_checks_history_by_tracker_id(tracker.id, None, Some(now - FORECAST_WINDOW), None)
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    let forecast = forecast_tracker(&tracker, &games, &recent_checks_history, now);
+
+    let notifications_muted = match &user {
+        None => None,
+        Some(u) => Some(
+            !send_stream(tx.get_ap_tracker_notification_mutes(tracker.id, &[u.user.id]))
+                .try_collect::<Vec<_>>()
+                .await
+                .unexpected()?
+                .is_empty(),
+        ),
+    };
+
     let dashboard_override_visibility = match user {
         None => None,
         Some(u) => tx
@@ -193,7 +248,13 @@ where
         owner_discord_username,
         games,
         hints,
+        members,
+        claim_requests,
+        co_claimants,
+        ownership_transfer,
         dashboard_override_visibility,
+        notifications_muted,
+        forecast,
     }))
 }
 
@@ -276,6 +337,15 @@ pub struct UpdateTrackerRequest {
     pub inactivity_threshold_yellow_hours: i32,
     pub inactivity_threshold_red_hours: i32,
     pub require_authentication_to_claim: bool,
+    #[serde(default)] // Backwards-compatibility
+    pub claim_expiry_days: Option<i32>,
+    #[serde(default = "default_claim_expiry_notify")] // Backwards-compatibility
+    pub claim_expiry_notify: bool,
+}
+
+#[doc(hidden)]
+fn default_claim_expiry_notify() -> bool {
+    true
 }
 
 /// `PUT /tracker/{tracker_id}`: Update tracker.
@@ -285,7 +355,7 @@ pub async fn update_tracker<D>(
     user: Option<AuthenticatedUser>,
     Path(tracker_id): Path<UrlEncodedUuid>,
     Json(tracker_update): Json<UpdateTrackerRequest>,
-) -> Result<impl IntoResponse, StatusCode>
+) -> Result<impl IntoResponse, ApiError>
 where
     D: DataAccessProvider + Send + Sync + 'static,
 {
@@ -293,8 +363,9 @@ where
         || tracker_update.inactivity_threshold_red_hours < 0
         || tracker_update.inactivity_threshold_yellow_hours
             > tracker_update.inactivity_threshold_red_hours
+        || tracker_update.claim_expiry_days.is_some_and(|d| d <= 0)
     {
-        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+        return Err(StatusCode::UNPROCESSABLE_ENTITY.into());
     }
 
     let mut db = state
@@ -315,70 +386,72 @@ where
 
     // Update settings.  Some settings are handled specially:
     //
-    // * owner_ct_user_id can only be updated if it's not set and the current
-    //   user is setting it to their own user ID (claiming) or if it's set to
-    //   the current user's ID and the current user is unsetting it
-    //   (disclaiming).  In all other cases, the update request is rejected.
-    // * lock_settings can only be changed by the organizer, and if there is no
-    //   organizer then there is no point in setting it because anyone could
+    // * owner_ct_user_id can only be claimed or disclaimed by the current user.
+    // * lock_settings can only be changed by organizers, and if there are no
+    //   organizers then there is no point in setting it because anyone could
     //   unset it.
     // * description allows adding arbitrary text and links.  This could allow
     //   CT to become an unwitting accomplice in e.g. phishing schemes.
-    //   Therefore, this field can only be edited by the organizer.
+    //   Therefore, this field can only be edited by organizers.
+    // * The claim expiry policy removes other users' claims, so it can only be
+    //   changed by organizers.
+    //
+    // See the policy module for the full rules.
 
     if tracker_update.owner_ct_user_id != tracker.owner_ct_user_id {
-        // A change in ownership requires authentication.
-        let user = user.as_ref().ok_or(StatusCode::UNAUTHORIZED)?;
+        TrackerPolicy::authorize_ownership_change(
+            user.as_ref(),
+            tracker.owner_ct_user_id,
+            tracker_update.owner_ct_user_id,
+        )?;
 
-        // The only valid changes are claiming or disclaiming ownership, and the
-        // user ID must match the authenticated user.
-        tracker.owner_ct_user_id = match (tracker.owner_ct_user_id, tracker_update.owner_ct_user_id)
-        {
-            (None, Some(uid)) | (Some(uid), None) if uid == user.user.id => {
-                tracker_update.owner_ct_user_id
-            }
-            _ => return Err(StatusCode::FORBIDDEN),
-        };
+        tracker.owner_ct_user_id = tracker_update.owner_ct_user_id;
     }
 
-    match (tracker.owner_ct_user_id, &user, tracker.lock_settings) {
-        // The current user is the owner.  They can change all settings.
-        (Some(uid), Some(u), _) if uid == u.user.id => {
-            tracker.lock_settings = tracker_update.lock_settings;
-            tracker.description = tracker_update.description;
-
-            // Some settings are not useful if settings aren't locked.
-            if !tracker_update.lock_settings
-                && (!tracker.require_authentication_to_claim
-                    && tracker_update.require_authentication_to_claim)
-            {
-                return Err(StatusCode::FORBIDDEN);
-            }
-
-            tracker.require_authentication_to_claim =
-                tracker_update.lock_settings && tracker_update.require_authentication_to_claim;
+    // The policy is determined after the ownership change so that users who
+    // just claimed the tracker can change all settings.  Owners disclaiming
+    // the tracker keep their role for the rest of the update, so they can
+    // unlock the settings as part of disclaiming it.
+    let disclaimed = old_tracker.owner_ct_user_id.is_some() && tracker.owner_ct_user_id.is_none();
+
+    let policy = TrackerPolicy::load(
+        &mut tx,
+        if disclaimed { &old_tracker } else { &tracker },
+        user.as_ref(),
+    )
+    .await
+    .unexpected()?;
+
+    policy.authorize_settings_update(
+        tracker_update.lock_settings != tracker.lock_settings
+            || tracker_update.description != tracker.description
+            || tracker_update.require_authentication_to_claim
+                != tracker.require_authentication_to_claim
+            || tracker_update.claim_expiry_days != tracker.claim_expiry_days
+            || tracker_update.claim_expiry_notify != tracker.claim_expiry_notify,
+    )?;
+
+    if policy.is_organizer() {
+        // Some settings are not useful if settings aren't locked.
+        if !tracker_update.lock_settings
+            && (!tracker.require_authentication_to_claim
+                && tracker_update.require_authentication_to_claim)
+        {
+            return Err(StatusCode::FORBIDDEN.into());
         }
 
-        // The current user is not the owner and settings are locked.  They
-        // cannot change anything.
-        (Some(_), _, true) => return Err(StatusCode::FORBIDDEN),
-
-        // There is no current owner or the current user is not the owner but
-        // settings are unlocked.  In both cases, they can change almost
-        // anything.  Some settings do not make sense to change when settings
-        // aren't locked.
-        (None, _, _) | (_, _, false) => {
-            if tracker_update.lock_settings
-                || tracker_update.description != tracker.description
-                || tracker_update.require_authentication_to_claim
-            {
-                return Err(StatusCode::FORBIDDEN);
-            }
-
-            tracker.lock_settings = false;
-            tracker.require_authentication_to_claim = false;
-        }
-    };
+        tracker.lock_settings = tracker_update.lock_settings;
+        tracker.description = tracker_update.description;
+        tracker.require_authentication_to_claim =
+            tracker_update.lock_settings && tracker_update.require_authentication_to_claim;
+        tracker.claim_expiry_days = tracker_update.claim_expiry_days;
+        tracker.claim_expiry_notify = tracker_update.claim_expiry_notify;
+    } else {
+        // Settings that do not make sense when settings aren't locked are
+        // cleared.
+        tracker.lock_settings = false;
+        tracker.require_authentication_to_claim = false;
+    }
 
     tracker.title = tracker_update.title;
     tracker.global_ping_policy = tracker_update.global_ping_policy;
@@ -398,7 +471,7 @@ where
                     Ok((port, check)) => (Some(port.into()), Some(check)),
 
                     Err(GetRoomLinkError::UrlParse(_) | GetRoomLinkError::InvalidRoomLink) => {
-                        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+                        return Err(StatusCode::UNPROCESSABLE_ENTITY.into());
                     }
 
                     Err(e) => {
@@ -432,6 +505,8 @@ where
                 ApTrackerIden::InactivityThresholdYellowHours,
                 ApTrackerIden::InactivityThresholdRedHours,
                 ApTrackerIden::RequireAuthenticationToClaim,
+                ApTrackerIden::ClaimExpiryDays,
+                ApTrackerIden::ClaimExpiryNotify,
             ],
         )
         .await
@@ -445,13 +520,80 @@ where
 
     send_future(tx.commit()).await.unexpected()?;
 
-    get_tracker(State(state), Path(tracker_id), user).await
+    get_tracker(State(state), Path(tracker_id), user)
+        .await
+        .map_err(ApiError::from)
+}
+
+/// A hint as returned by the API.
+#[derive(Debug, Clone, Serialize)]
+pub struct HintResponse {
+    #[serde(flatten)]
+    pub hint: ApHint,
+    /// The username of the user who acknowledged the hint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acknowledged_by_discord_username: Option<String>,
+}
+
+impl HintResponse {
+    /// Creates responses for hints, looking up the users who acknowledged them.
+    pub async fn from_hints(
+        db: &mut (impl DataAccess + Send),
+        hints: Vec<ApHint>,
+    ) -> sqlx::Result<Vec<Self>> {
+        let mut user_ids: Vec<_> = hints
+            .iter()
+            .filter_map(|h| h.acknowledged_by_ct_user_id)
+            .collect();
+
+        user_ids.sort_unstable();
+        user_ids.dedup();
+
+        let usernames: HashMap<_, _> = if user_ids.is_empty() {
+            HashMap::new()
+        } else {
+            send_stream(db.get_ct_users_by_ids(&user_ids))
+                .map_ok(|u| (u.id, u.discord_username))
+                .try_collect()
+                .await?
+        };
+
+        Ok(hints
+            .into_iter()
+            .map(|hint| Self {
+                acknowledged_by_discord_username: hint
+                    .acknowledged_by_ct_user_id
+                    .and_then(|id| usernames.get(&id).cloned()),
+                hint,
+            })
+            .collect())
+    }
 }
 
+/// The maximum length of a hint's notes, in characters.
+const MAX_HINT_NOTES_LEN: usize = 2000;
+
 /// Request body for [`update_hint`].
 #[derive(Debug, serde::Deserialize)]
 pub struct UpdateHintRequest {
     pub classification: HintClassification,
+    /// If omitted, the hint's notes are left unchanged.
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// If omitted, the hint's acknowledgement is left unchanged.  `null`
+    /// removes the acknowledgement.
+    #[serde(default, deserialize_with = "de_present")]
+    pub acknowledged_by_ct_user_id: Option<Option<i32>>,
+}
+
+/// Deserializes a field that may be `null` as `Some`, so that an omitted field
+/// (`None`) can be told apart from a `null` one (`Some(None)`).
+fn de_present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
 }
 
 /// `PUT /tracker/{tracker_id}/hint/{hint_id}`: Update hint.
@@ -461,10 +603,21 @@ pub async fn update_hint<D>(
     user: Option<AuthenticatedUser>,
     Path((tracker_id, hint_id)): Path<(UrlEncodedUuid, i32)>,
     Json(hint_update): Json<UpdateHintRequest>,
-) -> Result<impl IntoResponse, StatusCode>
+) -> Result<impl IntoResponse, ApiError>
 where
     D: DataAccessProvider + Send + Sync + 'static,
 {
+    if hint_update
+        .notes
+        .as_ref()
+        .is_some_and(|n| n.chars().count() > MAX_HINT_NOTES_LEN)
+    {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Notes must be at most {MAX_HINT_NOTES_LEN} characters long."),
+        ));
+    }
+
     let mut db = state
         .data_provider
         .create_data_access()
@@ -492,17 +645,52 @@ where
         .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
 
     if game.tracker_id != tracker.id {
-        return Err(StatusCode::NOT_FOUND);
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+
+    let receiver = match old_hint.receiver_game_id {
+        Some(id) => tx.get_ap_game(id).await.unexpected()?,
+        None => None,
+    };
+
+    let policy = TrackerPolicy::load(&mut tx, &tracker, user.as_ref())
+        .await
+        .unexpected()?;
+
+    policy.authorize_hint_update(&game, receiver.as_ref())?;
+    if let Some(acknowledged_by_ct_user_id) = hint_update.acknowledged_by_ct_user_id {
+        policy.authorize_hint_acknowledgement(
+            &game,
+            old_hint.acknowledged_by_ct_user_id,
+            acknowledged_by_ct_user_id,
+        )?;
     }
 
     let mut hint = old_hint.clone();
 
     hint.classification = hint_update.classification;
+    if let Some(notes) = hint_update.notes {
+        hint.notes = notes;
+    }
+    if let Some(acknowledged_by_ct_user_id) = hint_update.acknowledged_by_ct_user_id {
+        hint.acknowledged_by_ct_user_id = acknowledged_by_ct_user_id;
+    }
+
+    let now = Utc::now();
+    hint.updated_at = Some(now);
 
-    let audit = create_audit_for(Some(ip), user.as_ref(), Utc::now(), &old_hint, &hint);
+    let audit = create_audit_for(Some(ip), user.as_ref(), now, &old_hint, &hint);
 
     let hint = tx
-        .update_ap_hint(hint, &[ApHintIden::Classification])
+        .update_ap_hint(
+            hint,
+            &[
+                ApHintIden::Classification,
+                ApHintIden::Notes,
+                ApHintIden::AcknowledgedByCtUserId,
+                ApHintIden::UpdatedAt,
+            ],
+        )
         .await
         .unexpected()?
         .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -512,6 +700,28 @@ where
         .await
         .unexpected()?;
 
+    if hint.classification == HintClassification::Critical
+        && old_hint.classification != HintClassification::Critical
+    {
+        notify_hint_critical(
+            &mut tx,
+            &state.public_url,
+            &tracker,
+            &game,
+            &hint,
+            user.as_ref(),
+            now,
+        )
+        .await
+        .unexpected()?;
+    }
+
+    let hint = HintResponse::from_hints(&mut tx, vec![hint])
+        .await
+        .unexpected()?
+        .pop()
+        .unwrap();
+
     send_future(tx.commit()).await.unexpected()?;
 
     Ok(Json(hint))
@@ -522,8 +732,14 @@ where
 pub struct UpdateGameRequest {
     pub claimed_by_ct_user_id: Option<i32>,
     pub discord_username: Option<String>,
-    pub discord_ping: PingPreference,
-    pub availability_status: AvailabilityStatus,
+    /// If omitted, the ping preference is left unchanged, or set from the
+    /// user's claim preferences if they newly claim the game.
+    #[serde(default)]
+    pub discord_ping: Option<PingPreference>,
+    /// If omitted, the availability status is left unchanged, or set from the
+    /// user's claim preferences if they newly claim the game.
+    #[serde(default)]
+    pub availability_status: Option<AvailabilityStatus>,
     pub completion_status: CompletionStatus,
     pub progression_status: ProgressionStatus,
     pub last_checked: Option<DateTime<Utc>>,
@@ -582,7 +798,7 @@ pub async fn update_game<D>(
     Path((tracker_id, game_id)): Path<(UrlEncodedUuid, i32)>,
     TypedHeader(expected_owner): TypedHeader<IfOwnerIs>,
     Json(game_update): Json<UpdateGameRequest>,
-) -> Result<impl IntoResponse, StatusCode>
+) -> Result<impl IntoResponse, ApiError>
 where
     D: DataAccessProvider + Send + Sync + 'static,
 {
@@ -607,12 +823,20 @@ where
         .ok_or(StatusCode::NOT_FOUND)?;
 
     if game.tracker_id != tracker.id {
-        return Err(StatusCode::NOT_FOUND);
+        return Err(StatusCode::NOT_FOUND.into());
     }
 
+    let policy = TrackerPolicy::load(&mut tx, &tracker, user.as_ref())
+        .await
+        .unexpected()?;
+
+    policy.authorize_game_update(&game)?;
+
     // Test the owner precondition if it's present.
     let has_owner_precondition = match expected_owner.condition {
-        Some(expected) if !expected.matches(&game) => return Err(StatusCode::PRECONDITION_FAILED),
+        Some(expected) if !expected.matches(&game) => {
+            return Err(StatusCode::PRECONDITION_FAILED.into());
+        }
 
         Some(_) => true,
         None => false,
@@ -625,59 +849,78 @@ where
         || game_update.discord_username != game.discord_username)
         && !has_owner_precondition
     {
-        return Err(StatusCode::PRECONDITION_REQUIRED);
+        return Err(StatusCode::PRECONDITION_REQUIRED.into());
     }
 
-    // If the claimed user ID is changing to a value other than None, it must
-    // match the authenticated user's ID.
-    if game_update.claimed_by_ct_user_id != game.claimed_by_ct_user_id
-        && game_update
-            .claimed_by_ct_user_id
-            .is_some_and(|id| user.as_ref().is_none_or(|u| u.user.id != id))
-    {
-        return Err(match user {
-            // A user is trying to claim on behalf of another user.
-            Some(_) => StatusCode::FORBIDDEN,
-            // A user is trying to claim while unauthenticated; their token
-            // probably expired.
-            None => StatusCode::UNAUTHORIZED,
-        });
-    }
+    policy.authorize_claim(
+        &game,
+        game_update.claimed_by_ct_user_id,
+        game_update.discord_username.as_deref(),
+    )?;
 
     let old_game = game;
     let mut game = old_game.clone();
 
-    // Update the username.
+    // If claimed by an authenticated user, the username is not needed and can
+    // be set to NULL.
     game.discord_username = match game_update.claimed_by_ct_user_id {
-        // If claimed by an authenticated user, this username is not needed and
-        // can be set to NULL.
         Some(_) => None,
-        // Otherwise, take the unauthenticated username from the update.
-        None => {
-            // But don't allow a new unauthenticated claim if the tracker
-            // disallows it.
-            if game_update.discord_username != game.discord_username
-                && tracker.require_authentication_to_claim
-                && game_update.discord_username.is_some()
-            {
-                return Err(StatusCode::FORBIDDEN);
-            }
-
-            game_update.discord_username
-        }
+        None => game_update.discord_username,
     };
 
-    game.claimed_by_ct_user_id = game_update.claimed_by_ct_user_id;
-    game.discord_ping = game_update.discord_ping;
-    game.availability_status = game_update.availability_status;
     game.completion_status = game_update.completion_status;
     game.progression_status = game_update.progression_status;
     game.last_checked = game_update.last_checked;
     game.notes = game_update.notes;
 
+    // When the user newly claims the game, their claim preferences are used for
+    // the settings the request leaves unset.
+    match &user {
+        Some(user)
+            if game_update.claimed_by_ct_user_id == Some(user.user.id)
+                && old_game.claimed_by_ct_user_id != Some(user.user.id) =>
+        {
+            assign_claim(&mut game, user.user.id);
+
+            apply_claim_preferences(&mut tx, &mut game, &user.user)
+                .await
+                .unexpected()?;
+        }
+
+        _ => game.claimed_by_ct_user_id = game_update.claimed_by_ct_user_id,
+    }
+
+    if let Some(ping) = game_update.discord_ping {
+        game.discord_ping = ping;
+    }
+
+    if let Some(status) = game_update.availability_status {
+        game.availability_status = status;
+    }
+
     game.update_completion_status();
 
-    let audit = create_audit_for(Some(ip), user.as_ref(), Utc::now(), &old_game, &game);
+    let now = Utc::now();
+    let audit = create_audit_for(Some(ip), user.as_ref(), now, &old_game, &game);
+
+    // A co-claimant who takes over the claim is no longer a co-claimant.
+    if let Some(uid) = game.claimed_by_ct_user_id
+        && old_game.claimed_by_ct_user_id != Some(uid)
+    {
+        tx.delete_ap_game_claimant(game.id, uid)
+            .await
+            .unexpected()?;
+    }
+
+    // Co-claimants join the claimant, so they are removed along with the claim.
+    if game.claimed_by_ct_user_id.is_none()
+        && game.discord_username.is_none()
+        && (old_game.claimed_by_ct_user_id.is_some() || old_game.discord_username.is_some())
+    {
+        tx.delete_ap_game_claimants_by_game_id(game.id)
+            .await
+            .unexpected()?;
+    }
 
     let game_id = game.id;
     let game = tx
@@ -706,107 +949,188 @@ where
         .await
         .unexpected()?;
 
+    notify_claim_change(
+        &mut tx,
+        &state.public_url,
+        &tracker,
+        &old_game,
+        &game,
+        user.as_ref(),
+        now,
+    )
+    .await
+    .unexpected()?;
+
     send_future(tx.commit()).await.unexpected()?;
 
     Ok(Json(game))
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct DashboardOverrideStatus {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(default)]
-    pub visibility: Option<bool>,
+/// The columns of [`ApGame`] changed when the claim on a game changes hands.
+const CLAIM_COLUMNS: &[ApGameIden] = &[
+    ApGameIden::ClaimedByCtUserId,
+    ApGameIden::DiscordUsername,
+    ApGameIden::DiscordPing,
+    ApGameIden::AvailabilityStatus,
+];
+
+/// Gives the claim on a game to a user on their behalf.
+fn assign_claim(game: &mut ApGame, ct_user_id: i32) {
+    // The previous claimant's ping preference shouldn't apply to the new
+    // claimant.
+    if game.claimed_by_ct_user_id != Some(ct_user_id) {
+        game.discord_ping = PingPreference::Never;
+    }
+
+    game.claimed_by_ct_user_id = Some(ct_user_id);
+    game.discord_username = None;
+
+    if matches!(
+        game.availability_status,
+        AvailabilityStatus::Unknown | AvailabilityStatus::Open
+    ) {
+        game.availability_status = AvailabilityStatus::Claimed;
+    }
 }
 
-/// `GET /tracker/{tracker_id}/dashboard_override`: Get dashboard override
-/// status.
-pub async fn get_tracker_dashboard_override<D>(
-    State(state): State<Arc<AppState<D>>>,
-    Path(tracker_id): Path<UrlEncodedUuid>,
-    user: AuthenticatedUser,
-) -> Result<impl IntoResponse, StatusCode>
-where
-    D: DataAccessProvider + Send + Sync + 'static,
-{
-    let mut db = state
-        .data_provider
-        .create_data_access()
-        .await
-        .unexpected()?;
+/// Applies a user's claim preferences to a game they newly claimed.
+///
+/// The user's preferences for the game's game take precedence over their
+/// defaults.  Settings without a preference are left unchanged.
+async fn apply_claim_preferences(
+    db: &mut (impl DataAccess + Send),
+    game: &mut ApGame,
+    user: &CtUser,
+) -> sqlx::Result<()> {
+    let preference = db.get_ct_user_game_preference(user.id, &game.game).await?;
+
+    if let Some(ping) = preference
+        .as_ref()
+        .and_then(|p| p.discord_ping)
+        .or(user.default_ping_preference)
+    {
+        game.discord_ping = ping;
+    }
 
-    let mut tx = db.begin().await.unexpected()?;
+    if let Some(status) = preference
+        .as_ref()
+        .and_then(|p| p.availability_status)
+        .or(user.default_availability_status)
+    {
+        game.availability_status = status;
+    }
 
-    let tracker = tx
-        .get_tracker_by_tracker_id(tracker_id.into())
-        .await
-        .unexpected()?
-        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(())
+}
 
-    let r = tx
-        .get_ap_tracker_dashboard_override(user.user.id, tracker.id)
-        .await
-        .unexpected()?;
+/// Records an in-app notification for the former claimant of a game when
+/// another user removed their claim or gave it to someone else.
+async fn notify_claim_change(
+    db: &mut (impl DataAccess + Send),
+    public_url: &Url,
+    tracker: &ApTracker,
+    old_game: &ApGame,
+    game: &ApGame,
+    actor: Option<&AuthenticatedUser>,
+    now: DateTime<Utc>,
+) -> sqlx::Result<()> {
+    let Some(old_claimant) = old_game.claimed_by_ct_user_id else {
+        return Ok(());
+    };
 
-    send_future(tx.commit()).await.unexpected()?;
+    if game.claimed_by_ct_user_id == Some(old_claimant)
+        || actor.is_some_and(|a| a.user.id == old_claimant)
+    {
+        return Ok(());
+    }
 
-    Ok(Json(DashboardOverrideStatus {
-        visibility: r.map(|o| o.visibility),
-    }))
+    let event = Event::ClaimTakenOver {
+        tracker: TrackerReference::new(
+            public_url,
+            tracker.tracker_id.into(),
+            tracker.title.clone(),
+        ),
+        game_id: game.id,
+        game_name: game.name.clone(),
+        changed_by: actor_name(actor),
+        new_claimant: game
+            .claimed_by_ct_user_id
+            .and(game.effective_discord_username.clone())
+            .or_else(|| game.discord_username.clone()),
+    };
+
+    send_future(notify::record(db, tracker.id, [old_claimant], &event, now)).await
 }
 
-/// `PUT /tracker/{tracker_id}/dashboard_override`: Set dashboard override
-/// status.
-pub async fn put_tracker_dashboard_override<D>(
-    State(state): State<Arc<AppState<D>>>,
-    Path(tracker_id): Path<UrlEncodedUuid>,
-    user: AuthenticatedUser,
-    Json(status): Json<DashboardOverrideStatus>,
-) -> Result<impl IntoResponse, StatusCode>
-where
-    D: DataAccessProvider + Send + Sync + 'static,
-{
-    let mut db = state
-        .data_provider
-        .create_data_access()
-        .await
-        .unexpected()?;
+/// Records an in-app notification for the claimants of a hint's finding game
+/// that the hint was classified as critical by another user.
+async fn notify_hint_critical(
+    db: &mut (impl DataAccess + Send),
+    public_url: &Url,
+    tracker: &ApTracker,
+    finder: &ApGame,
+    hint: &ApHint,
+    actor: Option<&AuthenticatedUser>,
+    now: DateTime<Utc>,
+) -> sqlx::Result<()> {
+    let mut claimants: Vec<_> = send_stream(db.get_ap_game_claimants_by_game_id(finder.id))
+        .map_ok(|c| c.ct_user_id)
+        .try_collect()
+        .await?;
 
-    let mut tx = db.begin().await.unexpected()?;
+    claimants.extend(finder.claimed_by_ct_user_id);
+    claimants.retain(|&id| actor.is_none_or(|a| a.user.id != id));
 
-    let tracker = tx
-        .get_tracker_by_tracker_id(tracker_id.into())
-        .await
-        .unexpected()?
-        .ok_or(StatusCode::NOT_FOUND)?;
+    if claimants.is_empty() {
+        return Ok(());
+    }
 
-    match status.visibility {
-        Some(v) => {
-            tx.upsert_ap_tracker_dashboard_override(ApTrackerDashboardOverride {
-                ct_user_id: user.user.id,
-                ap_tracker_id: tracker.id,
-                visibility: v,
-            })
-            .await
-            .unexpected()?;
-        }
+    let receiver = match hint.receiver_game_id {
+        Some(id) => send_future(db.get_ap_game(id)).await?,
+        None => None,
+    };
 
-        None => {
-            tx.delete_ap_tracker_dashboard_override(user.user.id, tracker.id)
-                .await
-                .unexpected()?;
-        }
+    let event = Event::HintClassifiedCritical {
+        tracker: TrackerReference::new(
+            public_url,
+            tracker.tracker_id.into(),
+            tracker.title.clone(),
+        ),
+        hint_id: hint.id,
+        item: hint.item.clone(),
+        finder_game_name: finder.name.clone(),
+        receiver_game_name: receiver.map(|g| g.name),
+        classified_by: actor_name(actor),
     };
 
-    send_future(tx.commit()).await.unexpected()?;
+    send_future(notify::record(db, tracker.id, claimants, &event, now)).await
+}
 
-    Ok(Json(status))
+/// The name of the user who made a change, for use in notifications.
+fn actor_name(actor: Option<&AuthenticatedUser>) -> String {
+    actor.map_or_else(|| "Someone".to_owned(), |a| a.user.discord_username.clone())
 }
 
-/// `GET /tracker/{tracker_id}/checks_history`: Get history of checks over time.
-pub async fn get_checks_history<D>(
-    State(state): State<Arc<AppState<D>>>,
-    Path(tracker_id): Path<UrlEncodedUuid>,
-) -> Result<impl IntoResponse, StatusCode>
+/// A moderation action performed on a game by a tracker organizer.
+enum GameModeration {
+    /// Remove the game's claim.
+    Unclaim,
+    /// Give the game's claim to another user.
+    Reassign { ct_user_id: i32 },
+    /// Lock or unlock the game against edits by other users.
+    SetLocked { locked: bool },
+}
+
+/// Performs a moderation action on a game, ensuring that the user is one of
+/// the tracker's organizers.  The change is audited with the user as the actor.
+async fn moderate_game<D>(
+    state: &AppState<D>,
+    ip: IpAddr,
+    user: &AuthenticatedUser,
+    tracker_id: UrlEncodedUuid,
+    game_id: i32,
+    action: GameModeration,
+) -> Result<ApGame, ApiError>
 where
     D: DataAccessProvider + Send + Sync + 'static,
 {
@@ -816,121 +1140,2070 @@ where
         .await
         .unexpected()?;
 
-    let tracker = db
+    let mut tx = db.begin().await.unexpected()?;
+
+    let tracker = tx
         .get_tracker_by_tracker_id(tracker_id.into())
         .await
         .unexpected()?
         .ok_or(StatusCode::NOT_FOUND)?;
 
-    let slots: Vec<_> = db
-        .get_ap_games_by_tracker_id(tracker.id)
-        .try_collect()
+    let old_game = tx
+        .get_ap_game(game_id)
         .await
-        .unexpected()?;
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
 
-    #[derive(Serialize)]
-    struct ChecksDataPoint {
-        time: DateTime<Utc>,
-        slots: HashMap<i32, i32>,
+    if old_game.tracker_id != tracker.id {
+        return Err(StatusCode::NOT_FOUND.into());
     }
 
-    #[derive(Deserialize)]
-    struct ChecksDoneAudit {
-        checks_done: Option<FieldDiff<i32>>,
-    }
+    TrackerPolicy::load(&mut tx, &tracker, Some(user))
+        .await
+        .unexpected()?
+        .authorize_moderation()?;
 
-    let mut data_points = db
-        .get_system_audits_for_tracker(tracker.id)
-        .filter_map(|audit| {
-            let r: Result<_, sqlx::Error> = (|| {
-                let audit = audit?;
+    let mut game = old_game.clone();
 
-                let checks_done: ChecksDoneAudit =
-                    serde_json::from_str(&audit.diff).map_err(|e| sqlx::Error::ColumnDecode {
-                        index: "diff".into(),
-                        source: e.into(),
-                    })?;
+    let columns: &[ApGameIden] = match action {
+        GameModeration::Unclaim => {
+            game.claimed_by_ct_user_id = None;
+            game.discord_username = None;
+            game.discord_ping = PingPreference::Never;
 
-                Ok(checks_done
-                    .checks_done
-                    .map(|checks_done| (audit, checks_done)))
-            })();
+            if game.availability_status == AvailabilityStatus::Claimed {
+                game.availability_status = AvailabilityStatus::Open;
+            }
 
-            ready(r.transpose())
-        })
-        .try_fold(
-            vec![],
-            |mut items: Vec<ChecksDataPoint>, (audit, checks_done)| {
-                let minute = audit
-                    .changed_at
-                    .duration_trunc(TimeDelta::minutes(10))
-                    .unwrap();
-
-                if let Some(last) = items.last_mut() {
-                    let is_new;
-
-                    if last.time == minute {
-                        let entry = last.slots.entry(audit.entity_id);
-                        is_new = matches!(entry, Entry::Vacant(_));
-                        *entry.or_default() = checks_done.new;
-                    } else {
-                        let mut next = ChecksDataPoint {
-                            time: minute,
-                            slots: last.slots.clone(),
-                        };
-
-                        let entry = next.slots.entry(audit.entity_id);
-                        is_new = matches!(entry, Entry::Vacant(_));
-                        *entry.or_default() = checks_done.new;
-                        items.push(next);
-                    }
+            tx.delete_ap_game_claimants_by_game_id(game.id)
+                .await
+                .unexpected()?;
 
-                    if is_new {
-                        // If we inserted a new record, then this slot is
-                        // missing from all prior entries and the old value must
-                        // be backfilled to them.
-                        let len = items.len();
-                        for i in &mut items[0..(len - 1)] {
-                            i.slots.insert(audit.entity_id, checks_done.old);
-                        }
-                    }
-                } else {
-                    items.push(ChecksDataPoint {
-                        time: minute,
-                        slots: [(audit.entity_id, checks_done.new)].into_iter().collect(),
-                    });
-                }
+            CLAIM_COLUMNS
+        }
 
-                ready(Ok(items))
+        GameModeration::Reassign { ct_user_id } => {
+            let Some(claimant) = tx.get_ct_user_by_id(ct_user_id).await.unexpected()? else {
+                return Err(ApiError::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "The user to reassign the slot to does not exist.",
+                ));
+            };
+
+            assign_claim(&mut game, ct_user_id);
+
+            if old_game.claimed_by_ct_user_id != Some(ct_user_id) {
+                apply_claim_preferences(&mut tx, &mut game, &claimant)
+                    .await
+                    .unexpected()?;
+            }
+
+            tx.delete_ap_game_claimant(game.id, ct_user_id)
+                .await
+                .unexpected()?;
+
+            CLAIM_COLUMNS
+        }
+
+        GameModeration::SetLocked { locked } => {
+            game.locked = locked;
+
+            &[ApGameIden::Locked]
+        }
+    };
+
+    let now = Utc::now();
+    let audit = create_audit_for(Some(ip), Some(user), now, &old_game, &game);
+
+    let game = tx
+        .update_ap_game(game, columns)
+        .await
+        .unexpected()?
+        .ok_or_else(|| format!("ApGame {game_id} did not exist on update"))
+        .unexpected()?;
+
+    send_stream(tx.create_audits(audit))
+        .try_for_each(|_| ready(Ok(())))
+        .await
+        .unexpected()?;
+
+    notify_claim_change(
+        &mut tx,
+        &state.public_url,
+        &tracker,
+        &old_game,
+        &game,
+        Some(user),
+        now,
+    )
+    .await
+    .unexpected()?;
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    Ok(game)
+}
+
+/// `POST /tracker/{tracker_id}/game/{game_id}/unclaim`: Remove a game's claim.
+///
+/// Only the tracker's owner and organizers may use this endpoint.
+pub async fn force_unclaim_game<D>(
+    State(state): State<Arc<AppState<D>>>,
+    ClientIp(ip): ClientIp,
+    user: AuthenticatedUser,
+    Path((tracker_id, game_id)): Path<(UrlEncodedUuid, i32)>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    moderate_game(
+        &state,
+        ip,
+        &user,
+        tracker_id,
+        game_id,
+        GameModeration::Unclaim,
+    )
+    .await
+    .map(Json)
+}
+
+/// Request body for [`reassign_game`].
+#[derive(Debug, Deserialize)]
+pub struct ReassignGameRequest {
+    pub claimed_by_ct_user_id: i32,
+}
+
+/// `POST /tracker/{tracker_id}/game/{game_id}/reassign`: Give a game's claim to
+/// another user.
+///
+/// Only the tracker's owner and organizers may use this endpoint.
+pub async fn reassign_game<D>(
+    State(state): State<Arc<AppState<D>>>,
+    ClientIp(ip): ClientIp,
+    user: AuthenticatedUser,
+    Path((tracker_id, game_id)): Path<(UrlEncodedUuid, i32)>,
+    Json(request): Json<ReassignGameRequest>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    moderate_game(
+        &state,
+        ip,
+        &user,
+        tracker_id,
+        game_id,
+        GameModeration::Reassign {
+            ct_user_id: request.claimed_by_ct_user_id,
+        },
+    )
+    .await
+    .map(Json)
+}
+
+/// Request body for [`lock_game`].
+#[derive(Debug, Deserialize)]
+pub struct LockGameRequest {
+    pub locked: bool,
+}
+
+/// `PUT /tracker/{tracker_id}/game/{game_id}/lock`: Lock or unlock a game.
+///
+/// While a game is locked, only the tracker's owner and organizers can change
+/// it, and only they may use this endpoint.
+pub async fn lock_game<D>(
+    State(state): State<Arc<AppState<D>>>,
+    ClientIp(ip): ClientIp,
+    user: AuthenticatedUser,
+    Path((tracker_id, game_id)): Path<(UrlEncodedUuid, i32)>,
+    Json(request): Json<LockGameRequest>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    moderate_game(
+        &state,
+        ip,
+        &user,
+        tracker_id,
+        game_id,
+        GameModeration::SetLocked {
+            locked: request.locked,
+        },
+    )
+    .await
+    .map(Json)
+}
+
+/// A co-claimant of a game as returned by the API.
+#[derive(Debug, Clone, Serialize)]
+pub struct CoClaimantResponse {
+    #[serde(flatten)]
+    pub claimant: ApGameClaimant,
+    pub discord_username: String,
+}
+
+/// Gets the co-claimants of a tracker's games, in the order they joined.
+async fn get_co_claimants(
+    db: &mut (impl DataAccess + Send),
+    tracker_id: i32,
+) -> sqlx::Result<Vec<CoClaimantResponse>> {
+    let claimants: Vec<_> = db
+        .get_ap_game_claimants_by_tracker_id(tracker_id)
+        .try_collect()
+        .await?;
+
+    let ids: Vec<_> = claimants.iter().map(|c| c.ct_user_id).collect();
+
+    let usernames: HashMap<_, _> = db
+        .get_ct_users_by_ids(&ids)
+        .map_ok(|u| (u.id, u.discord_username))
+        .try_collect()
+        .await?;
+
+    Ok(claimants
+        .into_iter()
+        .filter_map(|claimant| {
+            Some(CoClaimantResponse {
+                discord_username: usernames.get(&claimant.ct_user_id)?.clone(),
+                claimant,
+            })
+        })
+        .collect())
+}
+
+/// Gets a game and its tracker, ensuring that the game belongs to the tracker.
+async fn get_tracker_game(
+    db: &mut (impl DataAccess + Send),
+    tracker_id: UrlEncodedUuid,
+    game_id: i32,
+) -> Result<(ApTracker, ApGame), ApiError> {
+    let tracker = db
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let game = db
+        .get_ap_game(game_id)
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if game.tracker_id != tracker.id {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+
+    Ok((tracker, game))
+}
+
+/// Request body for [`put_co_claimant`].
+#[derive(Debug, Deserialize)]
+pub struct PutCoClaimantRequest {
+    pub discord_ping: PingPreference,
+}
+
+/// `PUT /tracker/{tracker_id}/game/{game_id}/claimant`: Join a claimed game as
+/// a co-claimant, or change the ping preference of an existing co-claim.
+///
+/// Only the tracker's organizers may join a game directly.  Other users must
+/// create a claim request to join the game.  Responds with the game's
+/// co-claimants.
+pub async fn put_co_claimant<D>(
+    State(state): State<Arc<AppState<D>>>,
+    ClientIp(ip): ClientIp,
+    user: AuthenticatedUser,
+    Path((tracker_id, game_id)): Path<(UrlEncodedUuid, i32)>,
+    Json(request): Json<PutCoClaimantRequest>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let (tracker, game) = get_tracker_game(&mut tx, tracker_id, game_id).await?;
+
+    let old_claimant = tx
+        .get_ap_game_claimants_by_game_id(game.id)
+        .try_filter(|c| ready(c.ct_user_id == user.user.id))
+        .try_collect::<Vec<_>>()
+        .await
+        .unexpected()?
+        .pop();
+
+    let user_id = TrackerPolicy::load(&mut tx, &tracker, Some(&user))
+        .await
+        .unexpected()?
+        .authorize_co_claim(&game, old_claimant.is_some())?;
+
+    if game.effective_discord_username.is_none() {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "This slot isn't claimed.  You can claim it directly.",
+        ));
+    }
+
+    if game.claimed_by_ct_user_id == Some(user_id) {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "You have already claimed this slot.",
+        ));
+    }
+
+    let now = Utc::now();
+
+    tx.upsert_ap_game_claimant(ApGameClaimant {
+        ap_game_id: game.id,
+        ct_user_id: user_id,
+        discord_ping: request.discord_ping,
+        claimed_at: now,
+    })
+    .await
+    .unexpected()?;
+
+    send_stream(tx.create_audits(create_co_claimant_audit(
+        Some(ip),
+        Some(&user),
+        now,
+        game.id,
+        user_id,
+        old_claimant.map(|c| c.discord_ping),
+        Some(request.discord_ping),
+    )))
+    .try_for_each(|_| ready(Ok(())))
+    .await
+    .unexpected()?;
+
+    let claimants: Vec<_> = get_co_claimants(&mut tx, tracker.id)
+        .await
+        .unexpected()?
+        .into_iter()
+        .filter(|c| c.claimant.ap_game_id == game.id)
+        .collect();
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    Ok(Json(claimants))
+}
+
+/// `DELETE /tracker/{tracker_id}/game/{game_id}/claimant/{ct_user_id}`: Remove
+/// a co-claimant from a game.
+///
+/// Co-claimants may remove themselves.  The game's claimant and the tracker's
+/// organizers may remove any co-claimant.  Responds with the game's remaining
+/// co-claimants.
+pub async fn delete_co_claimant<D>(
+    State(state): State<Arc<AppState<D>>>,
+    ClientIp(ip): ClientIp,
+    user: AuthenticatedUser,
+    Path((tracker_id, game_id, ct_user_id)): Path<(UrlEncodedUuid, i32, i32)>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let (tracker, game) = get_tracker_game(&mut tx, tracker_id, game_id).await?;
+
+    TrackerPolicy::load(&mut tx, &tracker, Some(&user))
+        .await
+        .unexpected()?
+        .authorize_co_claimant_removal(&game, ct_user_id)?;
+
+    let claimant = tx
+        .delete_ap_game_claimant(game.id, ct_user_id)
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    send_stream(tx.create_audits(create_co_claimant_audit(
+        Some(ip),
+        Some(&user),
+        Utc::now(),
+        game.id,
+        ct_user_id,
+        Some(claimant.discord_ping),
+        None,
+    )))
+    .try_for_each(|_| ready(Ok(())))
+    .await
+    .unexpected()?;
+
+    let claimants: Vec<_> = get_co_claimants(&mut tx, tracker.id)
+        .await
+        .unexpected()?
+        .into_iter()
+        .filter(|c| c.claimant.ap_game_id == game.id)
+        .collect();
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    Ok(Json(claimants))
+}
+
+/// A request to take over the claim on a game as returned by the API.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClaimRequestResponse {
+    #[serde(flatten)]
+    pub request: ApGameClaimRequest,
+    pub requested_by_discord_username: String,
+}
+
+impl ClaimRequestResponse {
+    /// Converts claim requests into responses, looking up the usernames of the
+    /// requesting users.
+    async fn from_requests(
+        db: &mut (impl DataAccess + Send),
+        requests: Vec<ApGameClaimRequest>,
+    ) -> sqlx::Result<Vec<Self>> {
+        let ids: Vec<_> = requests.iter().map(|r| r.requested_by_ct_user_id).collect();
+
+        let usernames: HashMap<_, _> = db
+            .get_ct_users_by_ids(&ids)
+            .map_ok(|u| (u.id, u.discord_username))
+            .try_collect()
+            .await?;
+
+        Ok(requests
+            .into_iter()
+            .filter_map(|request| {
+                Some(Self {
+                    requested_by_discord_username: usernames
+                        .get(&request.requested_by_ct_user_id)?
+                        .clone(),
+                    request,
+                })
+            })
+            .collect())
+    }
+}
+
+/// Gets the pending requests to take over the claims on a tracker's games.
+async fn get_claim_requests(
+    db: &mut (impl DataAccess + Send),
+    tracker_id: i32,
+) -> sqlx::Result<Vec<ClaimRequestResponse>> {
+    let requests = db
+        .get_pending_ap_game_claim_requests_by_tracker_id(tracker_id)
+        .try_collect()
+        .await?;
+
+    ClaimRequestResponse::from_requests(db, requests).await
+}
+
+/// Request body for [`create_claim_request`].
+#[derive(Debug, Deserialize)]
+pub struct CreateClaimRequestRequest {
+    /// A message to the claimant.
+    #[serde(default)]
+    pub message: String,
+    /// Whether to ask to join the claimant as a co-claimant instead of taking
+    /// over the claim.
+    #[serde(default)]
+    pub co_claim: bool,
+}
+
+/// `POST /tracker/{tracker_id}/game/{game_id}/claim_request`: Request to take
+/// over the claim on a game, or to join it as a co-claimant.
+///
+/// The request can be approved or denied by the game's claimant and the
+/// tracker's organizers.
+pub async fn create_claim_request<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+    Path((tracker_id, game_id)): Path<(UrlEncodedUuid, i32)>,
+    Json(request): Json<CreateClaimRequestRequest>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let tracker = tx
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let game = tx
+        .get_ap_game(game_id)
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if game.tracker_id != tracker.id {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+
+    let user_id = TrackerPolicy::load(&mut tx, &tracker, Some(&user))
+        .await
+        .unexpected()?
+        .authorize_claim_request()?;
+
+    if game.effective_discord_username.is_none() {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "This slot isn't claimed.  You can claim it directly.",
+        ));
+    }
+
+    if game.claimed_by_ct_user_id == Some(user_id) {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "You have already claimed this slot.",
+        ));
+    }
+
+    let pending: Vec<_> = tx
+        .get_pending_ap_game_claim_requests_by_tracker_id(tracker.id)
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    if pending
+        .iter()
+        .any(|r| r.ap_game_id == game.id && r.requested_by_ct_user_id == user_id)
+    {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "You have already requested this slot.",
+        ));
+    }
+
+    if request.co_claim
+        && tx
+            .get_ap_game_claimants_by_game_id(game.id)
+            .try_any(|c| ready(c.ct_user_id == user_id))
+            .await
+            .unexpected()?
+    {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "You have already joined this slot.",
+        ));
+    }
+
+    let request = {
+        let requests =
+            send_stream(
+                tx.create_ap_game_claim_requests([ApGameClaimRequestInsertion {
+                    ap_game_id: game.id,
+                    requested_by_ct_user_id: user_id,
+                    message: request.message,
+                    status: ClaimRequestStatus::Pending,
+                    created_at: Utc::now(),
+                    resolved_at: None,
+                    resolved_by_ct_user_id: None,
+                    co_claim: request.co_claim,
+                }]),
+            );
+
+        tokio::pin!(requests);
+
+        requests
+            .try_next()
+            .await
+            .unexpected()?
+            .ok_or("Claim request insertion returned no rows")
+            .unexpected()?
+    };
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    Ok(Json(ClaimRequestResponse {
+        request,
+        requested_by_discord_username: user.user.discord_username,
+    }))
+}
+
+/// Resolves a pending request to take over the claim on a game or to join it as
+/// a co-claimant.
+///
+/// If the request is approved, the claim is given to the requesting user, or
+/// the requesting user is added as a co-claimant.  The change is audited with
+/// the resolving user as the actor.
+async fn resolve_claim_request<D>(
+    state: &AppState<D>,
+    ip: IpAddr,
+    user: &AuthenticatedUser,
+    tracker_id: UrlEncodedUuid,
+    request_id: i32,
+    status: ClaimRequestStatus,
+) -> Result<ClaimRequestResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let tracker = tx
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let request = tx
+        .get_ap_game_claim_request(request_id)
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let old_game = tx
+        .get_ap_game(request.ap_game_id)
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if old_game.tracker_id != tracker.id {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+
+    let policy = TrackerPolicy::load(&mut tx, &tracker, Some(user))
+        .await
+        .unexpected()?;
+
+    match status {
+        ClaimRequestStatus::Withdrawn => policy.authorize_claim_request_withdrawal(&request)?,
+        _ => policy.authorize_claim_request_resolution(&old_game)?,
+    }
+
+    let already_resolved = || {
+        ApiError::new(
+            StatusCode::CONFLICT,
+            "This request has already been resolved.",
+        )
+    };
+
+    if request.status != ClaimRequestStatus::Pending {
+        return Err(already_resolved());
+    }
+
+    let now = Utc::now();
+
+    let mut request = request;
+    request.status = status;
+    request.resolved_at = Some(now);
+    request.resolved_by_ct_user_id = Some(user.user.id);
+
+    // Only updating the request while it's pending ensures that it can only be
+    // resolved once.
+    let request = tx
+        .update_pending_ap_game_claim_request(
+            request,
+            &[
+                ApGameClaimRequestIden::Status,
+                ApGameClaimRequestIden::ResolvedAt,
+                ApGameClaimRequestIden::ResolvedByCtUserId,
+            ],
+        )
+        .await
+        .unexpected()?
+        .ok_or_else(already_resolved)?;
+
+    if status == ClaimRequestStatus::Approved && request.co_claim {
+        if old_game.effective_discord_username.is_none()
+            || old_game.claimed_by_ct_user_id == Some(request.requested_by_ct_user_id)
+        {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                "The slot's claim changed since the request was made.",
+            ));
+        }
+
+        let preference = tx
+            .get_ct_user_game_preference(request.requested_by_ct_user_id, &old_game.game)
+            .await
+            .unexpected()?;
+
+        let requester = tx
+            .get_ct_user_by_id(request.requested_by_ct_user_id)
+            .await
+            .unexpected()?;
+
+        let discord_ping = preference
+            .and_then(|p| p.discord_ping)
+            .or(requester.and_then(|u| u.default_ping_preference))
+            .unwrap_or(PingPreference::Never);
+
+        tx.upsert_ap_game_claimant(ApGameClaimant {
+            ap_game_id: old_game.id,
+            ct_user_id: request.requested_by_ct_user_id,
+            discord_ping,
+            claimed_at: now,
+        })
+        .await
+        .unexpected()?;
+
+        send_stream(tx.create_audits(create_co_claimant_audit(
+            Some(ip),
+            Some(user),
+            now,
+            old_game.id,
+            request.requested_by_ct_user_id,
+            None,
+            Some(discord_ping),
+        )))
+        .try_for_each(|_| ready(Ok(())))
+        .await
+        .unexpected()?;
+    } else if status == ClaimRequestStatus::Approved {
+        let mut game = old_game.clone();
+        assign_claim(&mut game, request.requested_by_ct_user_id);
+
+        if let Some(claimant) = tx
+            .get_ct_user_by_id(request.requested_by_ct_user_id)
+            .await
+            .unexpected()?
+        {
+            apply_claim_preferences(&mut tx, &mut game, &claimant)
+                .await
+                .unexpected()?;
+        }
+
+        let audit = create_audit_for(Some(ip), Some(user), now, &old_game, &game);
+
+        tx.delete_ap_game_claimant(game.id, request.requested_by_ct_user_id)
+            .await
+            .unexpected()?;
+
+        // The slot has a new claimant, so other requests to take it over are
+        // no longer meaningful.
+        send_stream(tx.deny_pending_ap_game_claim_requests_by_game_id(game.id, user.user.id, now))
+            .try_for_each(|_| ready(Ok(())))
+            .await
+            .unexpected()?;
+
+        // Only changing the claim if it's still held by the same claimant
+        // ensures that a concurrent claim change isn't clobbered.
+        let game = tx
+            .update_ap_game_if_claimed_by(
+                game,
+                CLAIM_COLUMNS,
+                old_game.claimed_by_ct_user_id,
+                old_game.discord_username.as_deref(),
+            )
+            .await
+            .unexpected()?
+            .ok_or_else(|| {
+                ApiError::new(
+                    StatusCode::CONFLICT,
+                    "The slot's claim changed while approving the request.",
+                )
+            })?;
+
+        send_stream(tx.create_audits(audit))
+            .try_for_each(|_| ready(Ok(())))
+            .await
+            .unexpected()?;
+
+        notify_claim_change(
+            &mut tx,
+            &state.public_url,
+            &tracker,
+            &old_game,
+            &game,
+            Some(user),
+            now,
+        )
+        .await
+        .unexpected()?;
+    }
+
+    let request = ClaimRequestResponse::from_requests(&mut tx, vec![request])
+        .await
+        .unexpected()?
+        .pop()
+        .ok_or("Claim request user does not exist")
+        .unexpected()?;
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    Ok(request)
+}
+
+/// `POST /tracker/{tracker_id}/claim_request/{request_id}/approve`: Approve a
+/// request to take over the claim on a game, giving the claim to the requesting
+/// user.
+///
+/// Only the game's claimant and the tracker's organizers may use this endpoint.
+pub async fn approve_claim_request<D>(
+    State(state): State<Arc<AppState<D>>>,
+    ClientIp(ip): ClientIp,
+    user: AuthenticatedUser,
+    Path((tracker_id, request_id)): Path<(UrlEncodedUuid, i32)>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    resolve_claim_request(
+        &state,
+        ip,
+        &user,
+        tracker_id,
+        request_id,
+        ClaimRequestStatus::Approved,
+    )
+    .await
+    .map(Json)
+}
+
+/// `POST /tracker/{tracker_id}/claim_request/{request_id}/deny`: Deny a request
+/// to take over the claim on a game.
+///
+/// Only the game's claimant and the tracker's organizers may use this endpoint.
+pub async fn deny_claim_request<D>(
+    State(state): State<Arc<AppState<D>>>,
+    ClientIp(ip): ClientIp,
+    user: AuthenticatedUser,
+    Path((tracker_id, request_id)): Path<(UrlEncodedUuid, i32)>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    resolve_claim_request(
+        &state,
+        ip,
+        &user,
+        tracker_id,
+        request_id,
+        ClaimRequestStatus::Denied,
+    )
+    .await
+    .map(Json)
+}
+
+/// `DELETE /tracker/{tracker_id}/claim_request/{request_id}`: Withdraw a
+/// request to take over the claim on a game.
+///
+/// Only the user who made the request may use this endpoint.
+pub async fn withdraw_claim_request<D>(
+    State(state): State<Arc<AppState<D>>>,
+    ClientIp(ip): ClientIp,
+    user: AuthenticatedUser,
+    Path((tracker_id, request_id)): Path<(UrlEncodedUuid, i32)>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    resolve_claim_request(
+        &state,
+        ip,
+        &user,
+        tracker_id,
+        request_id,
+        ClaimRequestStatus::Withdrawn,
+    )
+    .await
+    .map(Json)
+}
+
+/// A tracker member as returned by the API.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackerMemberResponse {
+    pub ct_user_id: i32,
+    pub discord_username: String,
+    pub role: TrackerRole,
+}
+
+/// Gets the members of a tracker, excluding its owner.
+async fn get_tracker_members(
+    db: &mut (impl DataAccess + Send),
+    ap_tracker_id: i32,
+) -> sqlx::Result<Vec<TrackerMemberResponse>> {
+    let members: Vec<_> = db
+        .get_ap_tracker_members_by_tracker_id(ap_tracker_id)
+        .try_collect()
+        .await?;
+
+    let ids: Vec<_> = members.iter().map(|m| m.ct_user_id).collect();
+
+    let usernames: HashMap<_, _> = db
+        .get_ct_users_by_ids(&ids)
+        .map_ok(|u| (u.id, u.discord_username))
+        .try_collect()
+        .await?;
+
+    let mut members: Vec<_> = members
+        .into_iter()
+        .filter_map(|m| {
+            Some(TrackerMemberResponse {
+                ct_user_id: m.ct_user_id,
+                discord_username: usernames.get(&m.ct_user_id)?.clone(),
+                role: m.role,
+            })
+        })
+        .collect();
+
+    members.sort_by(|a, b| a.discord_username.cmp(&b.discord_username));
+
+    Ok(members)
+}
+
+/// Request body for [`put_tracker_member`].
+#[derive(Debug, Deserialize)]
+pub struct PutTrackerMemberRequest {
+    pub role: TrackerRole,
+}
+
+/// `PUT /tracker/{tracker_id}/member/{ct_user_id}`: Add a member to a tracker
+/// or change their role.
+///
+/// Only the tracker's owner may use this endpoint.  Responds with the
+/// tracker's members.
+pub async fn put_tracker_member<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+    Path((tracker_id, ct_user_id)): Path<(UrlEncodedUuid, i32)>,
+    Json(request): Json<PutTrackerMemberRequest>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let tracker = tx
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    TrackerPolicy::load(&mut tx, &tracker, Some(&user))
+        .await
+        .unexpected()?
+        .authorize_member_management()?;
+
+    if request.role == TrackerRole::Owner || tracker.owner_ct_user_id == Some(ct_user_id) {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "The tracker's owner can't be changed through its members.",
+        ));
+    }
+
+    if tx
+        .get_ct_user_by_id(ct_user_id)
+        .await
+        .unexpected()?
+        .is_none()
+    {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "The user to add does not exist.",
+        ));
+    }
+
+    tx.upsert_ap_tracker_member(ApTrackerMember {
+        ct_user_id,
+        ap_tracker_id: tracker.id,
+        role: request.role,
+    })
+    .await
+    .unexpected()?;
+
+    let members = get_tracker_members(&mut tx, tracker.id)
+        .await
+        .unexpected()?;
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    Ok(Json(members))
+}
+
+/// `DELETE /tracker/{tracker_id}/member/{ct_user_id}`: Remove a member from a
+/// tracker.
+///
+/// Only the tracker's owner may use this endpoint, except that members may
+/// remove themselves.  Responds with the tracker's remaining members.
+pub async fn delete_tracker_member<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+    Path((tracker_id, ct_user_id)): Path<(UrlEncodedUuid, i32)>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let tracker = tx
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    TrackerPolicy::load(&mut tx, &tracker, Some(&user))
+        .await
+        .unexpected()?
+        .authorize_member_removal(ct_user_id)?;
+
+    tx.delete_ap_tracker_member(ct_user_id, tracker.id)
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let members = get_tracker_members(&mut tx, tracker.id)
+        .await
+        .unexpected()?;
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    Ok(Json(members))
+}
+
+/// A pending ownership transfer offer as returned by the API.
+#[derive(Debug, Clone, Serialize)]
+pub struct OwnershipTransferResponse {
+    pub to_ct_user_id: i32,
+    pub to_discord_username: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl OwnershipTransferResponse {
+    fn new(transfer: ApTrackerOwnershipTransfer, to_discord_username: String) -> Self {
+        Self {
+            to_ct_user_id: transfer.to_ct_user_id,
+            to_discord_username,
+            created_at: transfer.created_at,
+            expires_at: transfer.expires_at,
+        }
+    }
+}
+
+/// Whether an ownership transfer offer can still be accepted.  Offers become
+/// invalid when they expire or when the tracker's owner changes.
+fn ownership_transfer_is_valid(
+    transfer: &ApTrackerOwnershipTransfer,
+    tracker: &ApTracker,
+    now: DateTime<Utc>,
+) -> bool {
+    transfer.expires_at > now && tracker.owner_ct_user_id == Some(transfer.from_ct_user_id)
+}
+
+/// Gets the pending ownership transfer offer for a tracker, if there is a valid
+/// one.
+async fn get_ownership_transfer(
+    db: &mut (impl DataAccess + Send),
+    tracker: &ApTracker,
+    now: DateTime<Utc>,
+) -> sqlx::Result<Option<OwnershipTransferResponse>> {
+    let Some(transfer) = db
+        .get_ap_tracker_ownership_transfer(tracker.id)
+        .await?
+        .filter(|t| ownership_transfer_is_valid(t, tracker, now))
+    else {
+        return Ok(None);
+    };
+
+    Ok(db
+        .get_ct_user_by_id(transfer.to_ct_user_id)
+        .await?
+        .map(|u| OwnershipTransferResponse::new(transfer, u.discord_username)))
+}
+
+/// Request body for [`put_ownership_transfer`].
+#[derive(Debug, Deserialize)]
+pub struct PutOwnershipTransferRequest {
+    pub to_ct_user_id: i32,
+}
+
+/// `PUT /tracker/{tracker_id}/ownership_transfer`: Offer to transfer ownership
+/// of a tracker to another user.
+///
+/// Only the tracker's owner may use this endpoint.  Any previous offer is
+/// replaced.  The offer expires after the configured time.
+pub async fn put_ownership_transfer<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+    Path(tracker_id): Path<UrlEncodedUuid>,
+    Json(request): Json<PutOwnershipTransferRequest>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let tracker = tx
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    TrackerPolicy::load(&mut tx, &tracker, Some(&user))
+        .await
+        .unexpected()?
+        .authorize_ownership_transfer_offer()?;
+
+    if request.to_ct_user_id == user.user.id {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "You already own this tracker.",
+        ));
+    }
+
+    let to_user = tx
+        .get_ct_user_by_id(request.to_ct_user_id)
+        .await
+        .unexpected()?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "The user to transfer ownership to does not exist.",
+            )
+        })?;
+
+    tx.delete_ap_tracker_ownership_transfer(tracker.id)
+        .await
+        .unexpected()?;
+
+    let now = Utc::now();
+
+    let transfer = {
+        let transfers = send_stream(tx.create_ap_tracker_ownership_transfers([
+            ApTrackerOwnershipTransferInsertion {
+                ap_tracker_id: tracker.id,
+                from_ct_user_id: user.user.id,
+                to_ct_user_id: to_user.id,
+                created_at: now,
+                expires_at: now + state.ownership_transfer_expiry,
             },
+        ]));
+
+        tokio::pin!(transfers);
+
+        transfers
+            .try_next()
+            .await
+            .unexpected()?
+            .ok_or("Ownership transfer insertion returned no rows")
+            .unexpected()?
+    };
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    Ok(Json(OwnershipTransferResponse::new(
+        transfer,
+        to_user.discord_username,
+    )))
+}
+
+/// `DELETE /tracker/{tracker_id}/ownership_transfer`: Withdraw or decline an
+/// offer to transfer ownership of a tracker.
+///
+/// Only the tracker's owner and the recipient of the offer may use this
+/// endpoint.
+pub async fn delete_ownership_transfer<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+    Path(tracker_id): Path<UrlEncodedUuid>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let tracker = tx
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let transfer = tx
+        .get_ap_tracker_ownership_transfer(tracker.id)
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    TrackerPolicy::load(&mut tx, &tracker, Some(&user))
+        .await
+        .unexpected()?
+        .authorize_ownership_transfer_cancel(&transfer)?;
+
+    tx.delete_ap_tracker_ownership_transfer(tracker.id)
+        .await
+        .unexpected()?;
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `POST /tracker/{tracker_id}/ownership_transfer/accept`: Accept an offer to
+/// transfer ownership of a tracker.
+///
+/// Only the recipient of the offer may use this endpoint.  The ownership change
+/// is audited with the recipient as the actor.  Responds with the tracker in
+/// the same format as [`get_tracker`].
+pub async fn accept_ownership_transfer<D>(
+    State(state): State<Arc<AppState<D>>>,
+    ClientIp(ip): ClientIp,
+    user: AuthenticatedUser,
+    Path(tracker_id): Path<UrlEncodedUuid>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let old_tracker = tx
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let transfer = tx
+        .get_ap_tracker_ownership_transfer(old_tracker.id)
+        .await
+        .unexpected()?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                "There is no pending ownership transfer for this tracker.",
+            )
+        })?;
+
+    TrackerPolicy::load(&mut tx, &old_tracker, Some(&user))
+        .await
+        .unexpected()?
+        .authorize_ownership_transfer_accept(&transfer)?;
+
+    let now = Utc::now();
+
+    if !ownership_transfer_is_valid(&transfer, &old_tracker, now) {
+        return Err(ApiError::new(
+            StatusCode::GONE,
+            "This ownership transfer has expired.",
+        ));
+    }
+
+    // Deleting the offer ensures it can only be accepted once, and changing
+    // the owner only if it's still the user who made the offer ensures that a
+    // concurrent ownership change isn't clobbered.
+    let changed = tx
+        .delete_ap_tracker_ownership_transfer(old_tracker.id)
+        .await
+        .unexpected()?
+        .is_some_and(|t| t.id == transfer.id);
+
+    let tracker = match changed {
+        true => tx
+            .transfer_ap_tracker_ownership(
+                old_tracker.id,
+                transfer.from_ct_user_id,
+                transfer.to_ct_user_id,
+            )
+            .await
+            .unexpected()?,
+        false => None,
+    }
+    .ok_or_else(|| {
+        ApiError::new(
+            StatusCode::CONFLICT,
+            "The tracker's ownership changed while accepting the transfer.",
+        )
+    })?;
+
+    // The owner is not recorded as a member.
+    tx.delete_ap_tracker_member(user.user.id, tracker.id)
+        .await
+        .unexpected()?;
+
+    let audit = create_audit_for(Some(ip), Some(&user), now, &old_tracker, &tracker);
+
+    send_stream(tx.create_audits(audit))
+        .try_for_each(|_| ready(Ok(())))
+        .await
+        .unexpected()?;
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    get_tracker(State(state), Path(tracker_id), Some(user))
+        .await
+        .map_err(ApiError::from)
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DashboardOverrideStatus {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub visibility: Option<bool>,
+}
+
+/// `GET /tracker/{tracker_id}/dashboard_override`: Get dashboard override
+/// status.
+pub async fn get_tracker_dashboard_override<D>(
+    State(state): State<Arc<AppState<D>>>,
+    Path(tracker_id): Path<UrlEncodedUuid>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let tracker = tx
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let r = tx
+        .get_ap_tracker_dashboard_override(user.user.id, tracker.id)
+        .await
+        .unexpected()?;
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    Ok(Json(DashboardOverrideStatus {
+        visibility: r.map(|o| o.visibility),
+    }))
+}
+
+/// `PUT /tracker/{tracker_id}/dashboard_override`: Set dashboard override
+/// status.
+pub async fn put_tracker_dashboard_override<D>(
+    State(state): State<Arc<AppState<D>>>,
+    Path(tracker_id): Path<UrlEncodedUuid>,
+    user: AuthenticatedUser,
+    Json(status): Json<DashboardOverrideStatus>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    user.authorize_account_update()?;
+
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let tracker = tx
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    match status.visibility {
+        Some(v) => {
+            tx.upsert_ap_tracker_dashboard_override(ApTrackerDashboardOverride {
+                ct_user_id: user.user.id,
+                ap_tracker_id: tracker.id,
+                visibility: v,
+            })
+            .await
+            .unexpected()?;
+        }
+
+        None => {
+            tx.delete_ap_tracker_dashboard_override(user.user.id, tracker.id)
+                .await
+                .unexpected()?;
+        }
+    };
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    Ok(Json(status))
+}
+
+/// Request and response body for the notification mute endpoints.
+#[derive(Deserialize, Serialize)]
+pub struct NotificationMuteStatus {
+    pub muted: bool,
+}
+
+/// `GET /tracker/{tracker_id}/notification_mute`: Get whether the user muted
+/// in-app notifications about the tracker.
+pub async fn get_tracker_notification_mute<D>(
+    State(state): State<Arc<AppState<D>>>,
+    Path(tracker_id): Path<UrlEncodedUuid>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let tracker = db
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let mutes: Vec<_> = db
+        .get_ap_tracker_notification_mutes(tracker.id, &[user.user.id])
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    Ok(Json(NotificationMuteStatus {
+        muted: !mutes.is_empty(),
+    }))
+}
+
+/// `PUT /tracker/{tracker_id}/notification_mute`: Mute or unmute in-app
+/// notifications about the tracker for the user.
+pub async fn put_tracker_notification_mute<D>(
+    State(state): State<Arc<AppState<D>>>,
+    Path(tracker_id): Path<UrlEncodedUuid>,
+    user: AuthenticatedUser,
+    Json(status): Json<NotificationMuteStatus>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    user.authorize_account_update()?;
+
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let tracker = db
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if status.muted {
+        db.upsert_ap_tracker_notification_mute(ApTrackerNotificationMute {
+            ct_user_id: user.user.id,
+            ap_tracker_id: tracker.id,
+        })
+        .await
+        .unexpected()?;
+    } else {
+        db.delete_ap_tracker_notification_mute(user.user.id, tracker.id)
+            .await
+            .unexpected()?;
+    }
+
+    Ok(Json(status))
+}
+
+/// `GET /tracker/{tracker_id}/stats`: Get aggregated statistics about a
+/// tracker.
+///
+/// Unlike [`get_tracker`], this does not refresh the tracker from the upstream
+/// tracker.
+pub async fn get_tracker_stats<D>(
+    State(state): State<Arc<AppState<D>>>,
+    Path(tracker_id): Path<UrlEncodedUuid>,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    #[derive(Default, Serialize)]
+    struct HintCounts {
+        found: i64,
+        unfound: i64,
+    }
+
+    #[derive(Serialize)]
+    struct GameStats {
+        game: String,
+        slots: i64,
+        finished_slots: i64,
+        checks_done: i64,
+        checks_total: i64,
+    }
+
+    #[derive(Default, Serialize)]
+    struct TrackerStats {
+        slots: i64,
+        unclaimed_slots: i64,
+        completion_status: HashMap<CompletionStatus, i64>,
+        progression_status: HashMap<ProgressionStatus, i64>,
+        availability_status: HashMap<AvailabilityStatus, i64>,
+        inactivity_color: HashMap<InactivityColor, i64>,
+        hints: HashMap<HintClassification, HintCounts>,
+        games: Vec<GameStats>,
+    }
+
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let tracker = tx
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut stats = TrackerStats::default();
+
+    {
+        let slot_stats = tx.get_tracker_slot_stats(tracker.id);
+
+        tokio::pin!(slot_stats);
+
+        while let Some(s) = slot_stats.try_next().await.unexpected()? {
+            stats.slots += s.slots;
+
+            if !s.is_claimed {
+                stats.unclaimed_slots += s.slots;
+            }
+
+            *stats
+                .completion_status
+                .entry(s.completion_status)
+                .or_default() += s.slots;
+            *stats
+                .progression_status
+                .entry(s.progression_status)
+                .or_default() += s.slots;
+            *stats
+                .availability_status
+                .entry(s.availability_status)
+                .or_default() += s.slots;
+            *stats
+                .inactivity_color
+                .entry(s.inactivity_color)
+                .or_default() += s.slots;
+        }
+    }
+
+    {
+        let hint_stats = tx.get_tracker_hint_stats(tracker.id);
+
+        tokio::pin!(hint_stats);
+
+        while let Some(h) = hint_stats.try_next().await.unexpected()? {
+            let counts = stats.hints.entry(h.classification).or_default();
+
+            match h.found {
+                true => counts.found += h.hints,
+                false => counts.unfound += h.hints,
+            }
+        }
+    }
+
+    stats.games = tx
+        .get_tracker_game_stats(tracker.id)
+        .map_ok(|g| GameStats {
+            game: g.game,
+            slots: g.slots,
+            finished_slots: g.finished_slots,
+            checks_done: g.checks_done,
+            checks_total: g.checks_total,
+        })
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    stats.games.sort_by(|a, b| a.game.cmp(&b.game));
+
+    send_future(tx.rollback()).await.unexpected()?;
+
+    Ok(Json(stats))
+}
+
+/// `GET /tracker/{tracker_id}/game/{game_id}/todo`: Get the unfound hints that
+/// a slot is the finder of, ranked by how urgently they should be found.
+///
+/// See [`rank_todo_hints`] for the ranking rules.
+pub async fn get_game_todo<D>(
+    State(state): State<Arc<AppState<D>>>,
+    Path((tracker_id, game_id)): Path<(UrlEncodedUuid, i32)>,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    #[derive(Serialize)]
+    struct TodoReceiver<'a> {
+        id: i32,
+        name: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        effective_discord_username: Option<&'a str>,
+        progression_status: ProgressionStatus,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        last_active_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Serialize)]
+    struct TodoHintResponse<'a> {
+        #[serde(flatten)]
+        hint: &'a ApHint,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        receiver: Option<TodoReceiver<'a>>,
+    }
+
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let tracker = tx
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let games: Vec<_> = tx
+        .get_ap_games_by_tracker_id(tracker.id)
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    if !games.iter().any(|g| g.id == game_id) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let hints: Vec<_> = tx
+        .get_ap_hints_by_tracker_id(tracker.id)
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    send_future(tx.rollback()).await.unexpected()?;
+
+    let todo: Vec<_> = rank_todo_hints(game_id, &games, &hints)
+        .into_iter()
+        .map(|t| TodoHintResponse {
+            hint: t.hint,
+            receiver: t.receiver.map(|r| TodoReceiver {
+                id: r.id,
+                name: &r.name,
+                effective_discord_username: r.effective_discord_username.as_deref(),
+                progression_status: r.progression_status,
+                last_active_at: r.last_active_at(),
+            }),
+        })
+        .collect();
+
+    Ok(Json(todo).into_response())
+}
+
+/// Output format for [`get_tracker_blockers`].
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockersFormat {
+    #[default]
+    Json,
+    /// Graphviz DOT language.
+    Dot,
+    /// SVG image rendered by Graphviz.
+    Svg,
+}
+
+/// Query parameters for [`get_tracker_blockers`].
+#[derive(Debug, Deserialize)]
+pub struct BlockersQuery {
+    #[serde(default)]
+    pub format: BlockersFormat,
+}
+
+/// `GET /tracker/{tracker_id}/blockers`: Get the graph of which slots hold
+/// critical or progression items needed by slots in BK.
+///
+/// See [`blocker_graph`] for how the graph is built.  The graph can also be
+/// requested in the DOT language or as an SVG image with the `format` query
+/// parameter.
+pub async fn get_tracker_blockers<D>(
+    State(state): State<Arc<AppState<D>>>,
+    Path(tracker_id): Path<UrlEncodedUuid>,
+    Query(query): Query<BlockersQuery>,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    #[derive(Serialize)]
+    struct BlockerSlotResponse<'a> {
+        id: i32,
+        name: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        effective_discord_username: Option<&'a str>,
+        progression_status: ProgressionStatus,
+        completion_status: CompletionStatus,
+        inactivity_color: InactivityColor,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        last_active_at: Option<DateTime<Utc>>,
+        blocked: bool,
+        blocking: usize,
+    }
+
+    #[derive(Serialize)]
+    struct BlockerEdgeResponse<'a> {
+        hint_id: i32,
+        receiver_game_id: Option<i32>,
+        finder_game_id: i32,
+        item: &'a str,
+        location: &'a str,
+        classification: HintClassification,
+    }
+
+    #[derive(Serialize)]
+    struct BlockersResponse<'a> {
+        slots: Vec<BlockerSlotResponse<'a>>,
+        edges: Vec<BlockerEdgeResponse<'a>>,
+    }
+
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let tracker = tx
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let games: Vec<_> = tx
+        .get_ap_games_by_tracker_id(tracker.id)
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    let hints: Vec<_> = tx
+        .get_ap_hints_by_tracker_id(tracker.id)
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    send_future(tx.rollback()).await.unexpected()?;
+    drop(db);
+
+    let graph = blocker_graph(&games, &hints);
+    let now = Utc::now();
+
+    Ok(match query.format {
+        BlockersFormat::Json => Json(BlockersResponse {
+            slots: graph
+                .slots
+                .iter()
+                .map(|s| BlockerSlotResponse {
+                    id: s.game.id,
+                    name: &s.game.name,
+                    effective_discord_username: s.game.effective_discord_username.as_deref(),
+                    progression_status: s.game.progression_status,
+                    completion_status: s.game.completion_status,
+                    inactivity_color: s.game.inactivity_color(&tracker, now),
+                    last_active_at: s.game.last_active_at(),
+                    blocked: s.blocked,
+                    blocking: s.blocking,
+                })
+                .collect(),
+            edges: graph
+                .edges
+                .iter()
+                .map(|h| BlockerEdgeResponse {
+                    hint_id: h.id,
+                    receiver_game_id: h.receiver_game_id,
+                    finder_game_id: h.finder_game_id,
+                    item: &h.item,
+                    location: &h.location,
+                    classification: h.classification,
+                })
+                .collect(),
+        })
+        .into_response(),
+
+        BlockersFormat::Dot => (
+            [(header::CONTENT_TYPE, "text/vnd.graphviz; charset=utf-8")],
+            graph.to_dot(&tracker, now),
+        )
+            .into_response(),
+
+        BlockersFormat::Svg => {
+            let svg = match render_dot_svg(graph.to_dot(&tracker, now)).await {
+                Ok(svg) => svg,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    log!("Cannot render SVG; Graphviz is not installed");
+                    return Err(StatusCode::NOT_IMPLEMENTED);
+                }
+                r => r.unexpected()?,
+            };
+
+            ([(header::CONTENT_TYPE, "image/svg+xml")], svg).into_response()
+        }
+    })
+}
+
+/// The maximum size of a single bucket of checks history, in minutes.
+const MAX_CHECKS_HISTORY_BUCKET_MINS: i64 = 7 * 24 * 60;
+
+/// Query parameters for [`get_checks_history`].
+#[derive(Debug, Deserialize)]
+pub struct ChecksHistoryQuery {
+    /// The size of each bucket in minutes.
+    ///
+    /// Changes within the same bucket are merged into a single data point.
+    #[serde(default = "default_checks_history_bucket_mins")]
+    pub bucket_mins: i64,
+    /// Omit changes that happened before this time.
+    pub from: Option<DateTime<Utc>>,
+    /// Omit changes that happened at or after this time.
+    pub until: Option<DateTime<Utc>>,
+    /// Comma-separated list of slot IDs to include.
+    ///
+    /// If omitted, all slots are included.
+    pub slots: Option<String>,
+    /// Include a completion forecast of the included slots.
+    ///
+    /// If set, the response is an object with the data points in
+    /// `data_points` and the forecast in `forecast`.  Otherwise, the response
+    /// is the array of data points.
+    #[serde(default)]
+    pub forecast: bool,
+}
+
+#[doc(hidden)]
+fn default_checks_history_bucket_mins() -> i64 {
+    10
+}
+
+/// `GET /tracker/{tracker_id}/checks_history`: Get history of checks over time.
+pub async fn get_checks_history<D>(
+    State(state): State<Arc<AppState<D>>>,
+    Path(tracker_id): Path<UrlEncodedUuid>,
+    Query(query): Query<ChecksHistoryQuery>,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    #[derive(Serialize)]
+    struct ChecksDataPoint {
+        time: DateTime<Utc>,
+        slots: HashMap<i32, i32>,
+    }
+
+    #[derive(Serialize)]
+    struct ChecksHistoryResponse {
+        data_points: Vec<ChecksDataPoint>,
+        forecast: TrackerForecast,
+    }
+
+    if !(1..=MAX_CHECKS_HISTORY_BUCKET_MINS).contains(&query.bucket_mins)
+        || query.from.zip(query.until).is_some_and(|(f, u)| f >= u)
+    {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let bucket = TimeDelta::minutes(query.bucket_mins);
+
+    let slot_filter: Option<Vec<i32>> = query
+        .slots
+        .as_deref()
+        .map(|s| {
+            s.split(',')
+                .map(|id| id.trim().parse::<i32>())
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let tracker = db
+        .get_tracker_by_tracker_id(tracker_id.into())
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let slots: Vec<_> = db
+        .get_ap_games_by_tracker_id(tracker.id)
+        .try_filter(|g| ready(slot_filter.as_ref().is_none_or(|ids| ids.contains(&g.id))))
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    // The forecast is always based on recent history, regardless of the
+    // requested range.
+    let forecast = match query.forecast {
+        false => None,
+        true => {
+            let now = Utc::now();
+
+            let recent_checks_history: Vec<_> = db
+                .get_checks_history_by_tracker_id(
+                    tracker.id,
+                    slot_filter.as_deref(),
+                    Some(now - FORECAST_WINDOW),
+                    None,
+                )
+                .try_collect()
+                .await
+                .unexpected()?;
+
+            Some(forecast_tracker(
+                &tracker,
+                &slots,
+                &recent_checks_history,
+                now,
+            ))
+        }
+    };
+
+    // Tracks the number of checks each slot had at the point in time being
+    // considered.  Slots not selected by the filter are never inserted.
+    let mut current: HashMap<i32, i32> = slots.iter().map(|g| (g.id, g.checks_done)).collect();
+
+    let entries: Vec<_> = db
+        .get_checks_history_by_tracker_id(
+            tracker.id,
+            slot_filter.as_deref(),
+            query.from,
+            query.until,
         )
+        .try_collect()
         .await
         .unexpected()?;
 
-    // Patch up possibly-missing data: if there are any slots that don't appear
-    // in the audit records, populate all data points with that slot's current
-    // check count.  This situation is possible in a few scenarios:
-    //
-    // * The slot has not sent any checks since the CT tracker was first
-    //   created.
-    // * The slot has not sent any checks since CT started recording history.
+    // Determine the number of checks each slot had at the start of the range.
+    // In order of precedence, this is:
     //
-    // All data points should have exactly the same set of slots due to the
-    // backfilling performed above.
-
-    if let Some(last) = data_points.last() {
-        // Make a set of the slots in the last entry.  This is required because
-        // we are going to mutate data_points.
-        let data_point_slots: HashSet<_> = last.slots.keys().copied().collect();
-
-        for slot in slots {
-            if !data_point_slots.contains(&slot.id) {
-                for p in &mut data_points {
-                    p.slots.insert(slot.id, slot.checks_done);
-                }
+    // * The value from the last change before the start of the range.
+    // * The value before the first change in the range.
+    // * The value before the first change after the end of the range.
+    // * The current value, if the slot has no recorded changes.  This happens
+    //   if the slot has not sent any checks since the CT tracker was first
+    //   created or since CT started recording history.
+    if let Some(until) = query.until {
+        let after = db.get_first_checks_history_since(tracker.id, until);
+
+        tokio::pin!(after);
+
+        while let Some(entry) = after.try_next().await.unexpected()? {
+            if let Some(v) = current.get_mut(&entry.ap_game_id) {
+                *v = entry.previous_checks_done;
+            }
+        }
+    }
+
+    for entry in entries.iter().rev() {
+        if let Some(v) = current.get_mut(&entry.ap_game_id) {
+            *v = entry.previous_checks_done;
+        }
+    }
+
+    if let Some(from) = query.from {
+        let before = db.get_last_checks_history_before(tracker.id, from);
+
+        tokio::pin!(before);
+
+        while let Some(entry) = before.try_next().await.unexpected()? {
+            if let Some(v) = current.get_mut(&entry.ap_game_id) {
+                *v = entry.checks_done;
             }
         }
     }
 
-    Ok(Json(data_points))
+    // Each data point contains the state of all slots after all of the changes
+    // in its bucket were applied.  Buckets without changes are omitted.
+    let mut data_points = vec![];
+    let mut open_bucket = None;
+
+    for entry in entries {
+        let time = entry.changed_at.duration_trunc(bucket).unexpected()?;
+
+        if let Some(open) = open_bucket.filter(|&t| t != time) {
+            data_points.push(ChecksDataPoint {
+                time: open,
+                slots: current.clone(),
+            });
+        }
+
+        open_bucket = Some(time);
+
+        if let Some(v) = current.get_mut(&entry.ap_game_id) {
+            *v = entry.checks_done;
+        }
+    }
+
+    if let Some(open) = open_bucket {
+        data_points.push(ChecksDataPoint {
+            time: open,
+            slots: current,
+        });
+    }
+
+    Ok(match forecast {
+        None => Json(data_points).into_response(),
+        Some(forecast) => Json(ChecksHistoryResponse {
+            data_points,
+            forecast,
+        })
+        .into_response(),
+    })
 }
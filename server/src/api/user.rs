@@ -1,17 +1,43 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    future::ready,
+    net::IpAddr,
+    sync::Arc,
+};
 
-use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 use crate::{
-    auth::token::{AuthenticatedUser, TokenAuthenticatedUser},
+    ap_api::UrlEncodedUuid,
+    api::{
+        ApiError,
+        auth::{AuthProvider, CompleteAuthRequest, begin_provider_auth, complete_provider_auth},
+        tracker::HintResponse,
+    },
+    auth::{
+        api_key, discord,
+        token::{AuthenticatedUser, RecentlyAuthenticatedUser, TokenAuthenticatedUser},
+    },
     db::{
-        DataAccess, DataAccessProvider,
-        model::{CtUser, CtUserIden},
+        DataAccess, DataAccessProvider, Transactable, Transaction,
+        model::{
+            ApGame, ApHint, ApTracker, ApiKey, ApiKeyInsertion, ApiKeyTracker, AvailabilityStatus,
+            CompletionStatus, CtUser, CtUserGamePreference, CtUserIden, CtUserIdentity,
+            CtUserIdentityInsertion, CtUserNotification, HintClassification, InactivityColor,
+            PingPreference, ProgressionStatus,
+        },
     },
     logging::{UnexpectedResultExt, log},
-    state::AppState,
+    send_hack::{send_future, send_stream},
+    state::{AppState, UPSTREAM_TIMEOUT},
 };
 
 /// `GET /user/self`: Get current user details.
@@ -28,17 +54,45 @@ pub async fn get_self(auth_user: AuthenticatedUser) -> impl IntoResponse {
     })
 }
 
-/// `GET /user/self/api_key`: Get current API key.
-pub async fn get_api_key(
-    TokenAuthenticatedUser(user): TokenAuthenticatedUser,
-) -> Result<impl IntoResponse, StatusCode> {
-    Ok(Json(user.api_key))
+/// Response body for an [`ApiKey`].  The key's secret is never included.
+#[derive(Debug, Serialize)]
+pub struct ApiKeyResponse {
+    pub id: i32,
+    pub name: String,
+    pub can_update_games: bool,
+    pub can_update_hints: bool,
+    pub can_update_trackers: bool,
+    pub can_update_account: bool,
+    /// The trackers the key may change, or `None` if it may change all
+    /// trackers.
+    trackers: Option<Vec<TrackerSummary>>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
 }
 
-/// `POST /user/self/api_key`: Create new API key.
-pub async fn reset_api_key<D>(
+impl ApiKeyResponse {
+    fn new(key: ApiKey, trackers: &[ApTracker]) -> Self {
+        Self {
+            id: key.id,
+            name: key.name,
+            can_update_games: key.can_update_games,
+            can_update_hints: key.can_update_hints,
+            can_update_trackers: key.can_update_trackers,
+            can_update_account: key.can_update_account,
+            trackers: (!key.all_trackers)
+                .then(|| trackers.iter().map(TrackerSummary::new).collect()),
+            created_at: key.created_at,
+            expires_at: key.expires_at,
+            last_used_at: key.last_used_at,
+        }
+    }
+}
+
+/// `GET /user/self/api_keys`: Get the user's API keys.
+pub async fn get_api_keys<D>(
     State(state): State<Arc<AppState<D>>>,
-    TokenAuthenticatedUser(mut user): TokenAuthenticatedUser,
+    TokenAuthenticatedUser(user): TokenAuthenticatedUser,
 ) -> Result<impl IntoResponse, StatusCode>
 where
     D: DataAccessProvider + Send + Sync + 'static,
@@ -49,21 +103,172 @@ where
         .await
         .unexpected()?;
 
-    let new_key = Uuid::new_v4();
+    let keys: Vec<_> = db
+        .get_api_keys_by_user_id(user.id)
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    let mut responses = Vec::with_capacity(keys.len());
 
-    user.api_key = Some(new_key);
+    for key in keys {
+        let trackers: Vec<_> = if key.all_trackers {
+            vec![]
+        } else {
+            db.get_ap_trackers_by_api_key_id(key.id)
+                .try_collect()
+                .await
+                .unexpected()?
+        };
+
+        responses.push(ApiKeyResponse::new(key, &trackers));
+    }
+
+    Ok(Json(responses))
+}
+
+/// Request body for [`create_api_key`].
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    #[serde(default)]
+    pub can_update_games: bool,
+    #[serde(default)]
+    pub can_update_hints: bool,
+    #[serde(default)]
+    pub can_update_trackers: bool,
+    #[serde(default)]
+    pub can_update_account: bool,
+    /// The trackers the key may change.  If omitted, the key may change all
+    /// trackers.
+    #[serde(default)]
+    pub tracker_ids: Option<Vec<UrlEncodedUuid>>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Response body for [`create_api_key`].
+#[derive(Debug, Serialize)]
+pub struct CreateApiKeyResponse {
+    #[serde(flatten)]
+    pub key: ApiKeyResponse,
+    /// The key's secret.  This is only returned when the key is created.
+    pub secret: String,
+}
+
+/// `POST /user/self/api_keys`: Create a new API key.
+pub async fn create_api_key<D>(
+    State(state): State<Arc<AppState<D>>>,
+    TokenAuthenticatedUser(user): TokenAuthenticatedUser,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let now = Utc::now();
 
-    db.update_ct_user(user, &[CtUserIden::ApiKey])
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "API keys must have a name.",
+        ));
+    }
+
+    if request.expires_at.is_some_and(|e| e <= now) {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "The expiry time must be in the future.",
+        ));
+    }
+
+    if request
+        .tracker_ids
+        .as_ref()
+        .is_some_and(|ids| ids.is_empty())
+    {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Select at least one tracker, or allow all trackers.",
+        ));
+    }
+
+    let mut db = state
+        .data_provider
+        .create_data_access()
         .await
         .unexpected()?;
 
-    Ok(Json(new_key))
+    let mut tx = db.begin().await.unexpected()?;
+
+    let mut trackers = vec![];
+    for tracker_id in request.tracker_ids.iter().flatten() {
+        let tracker = tx
+            .get_tracker_by_tracker_id((*tracker_id).into())
+            .await
+            .unexpected()?
+            .ok_or_else(|| {
+                ApiError::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    format!("Tracker {tracker_id} does not exist."),
+                )
+            })?;
+
+        if !trackers.iter().any(|t: &ApTracker| t.id == tracker.id) {
+            trackers.push(tracker);
+        }
+    }
+
+    let (secret, secret_hash) = api_key::generate();
+
+    let key = {
+        let keys = send_stream(tx.create_api_keys([ApiKeyInsertion {
+            ct_user_id: user.id,
+            name: name.to_owned(),
+            secret_hash,
+            can_update_games: request.can_update_games,
+            can_update_hints: request.can_update_hints,
+            can_update_trackers: request.can_update_trackers,
+            can_update_account: request.can_update_account,
+            all_trackers: request.tracker_ids.is_none(),
+            created_at: now,
+            expires_at: request.expires_at,
+            last_used_at: None,
+        }]));
+        tokio::pin!(keys);
+
+        keys.try_next()
+            .await
+            .unexpected()?
+            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+    };
+
+    tx.create_api_key_trackers(trackers.iter().map(|t| ApiKeyTracker {
+        api_key_id: key.id,
+        ap_tracker_id: t.id,
+    }))
+    .await
+    .unexpected()?;
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateApiKeyResponse {
+            key: ApiKeyResponse::new(key, &trackers),
+            secret,
+        }),
+    ))
 }
 
-/// `DELETE /user/self/api_key`: Delete API key.
-pub async fn clear_api_key<D>(
+/// `DELETE /user/self/api_keys/{id}`: Delete an API key.
+///
+/// Requests already authenticated with the key may continue to succeed for a
+/// short time on other servers.
+pub async fn delete_api_key<D>(
     State(state): State<Arc<AppState<D>>>,
-    TokenAuthenticatedUser(mut user): TokenAuthenticatedUser,
+    TokenAuthenticatedUser(user): TokenAuthenticatedUser,
+    Path(id): Path<i32>,
 ) -> Result<impl IntoResponse, StatusCode>
 where
     D: DataAccessProvider + Send + Sync + 'static,
@@ -74,11 +279,13 @@ where
         .await
         .unexpected()?;
 
-    user.api_key = None;
-
-    db.update_ct_user(user, &[CtUserIden::ApiKey])
+    let key = db
+        .delete_api_key(user.id, id)
         .await
-        .unexpected()?;
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    state.api_key_cache.invalidate(&key.secret_hash).await;
 
     Ok(StatusCode::NO_CONTENT)
 }
@@ -86,13 +293,68 @@ where
 #[derive(Deserialize, Serialize, Debug)]
 pub struct UserSettings {
     pub is_away: bool,
+    /// When to automatically mark the user as away.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub away_from: Option<DateTime<Utc>>,
+    /// When to automatically mark the user as back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub away_until: Option<DateTime<Utc>>,
+    /// A message to other players while the user is away.
+    #[serde(default)]
+    pub away_message: String,
+    /// The ping preference applied to slots the user claims.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_ping_preference: Option<PingPreference>,
+    /// The availability status applied to slots the user claims.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_availability_status: Option<AvailabilityStatus>,
 }
 
 impl UserSettings {
-    pub const COLUMNS: [CtUserIden; 1] = [CtUserIden::IsAway];
+    pub const COLUMNS: [CtUserIden; 6] = [
+        CtUserIden::IsAway,
+        CtUserIden::AwayFrom,
+        CtUserIden::AwayUntil,
+        CtUserIden::AwayMessage,
+        CtUserIden::DefaultPingPreference,
+        CtUserIden::DefaultAvailabilityStatus,
+    ];
+
+    /// Checks that the away period is valid as of `now`.
+    pub fn validate(&self, now: DateTime<Utc>) -> Result<(), ApiError> {
+        if let (Some(from), Some(until)) = (self.away_from, self.away_until)
+            && from >= until
+        {
+            return Err(ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "The away period must end after it starts.",
+            ));
+        }
+
+        if self.away_until.is_some_and(|until| until <= now) {
+            return Err(ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "The away period must end in the future.",
+            ));
+        }
 
-    pub fn apply_to(self, user: &mut CtUser) {
+        validate_claim_availability_status(self.default_availability_status)
+    }
+
+    /// Applies the settings to a user.  An away period that has already
+    /// started marks the user as away immediately.
+    pub fn apply_to(self, user: &mut CtUser, now: DateTime<Utc>) {
         user.is_away = self.is_away;
+        user.away_from = self.away_from;
+        user.away_until = self.away_until;
+        user.away_message = self.away_message;
+        user.default_ping_preference = self.default_ping_preference;
+        user.default_availability_status = self.default_availability_status;
+
+        if user.away_from.is_some_and(|from| from <= now) {
+            user.is_away = true;
+            user.away_from = None;
+        }
     }
 }
 
@@ -100,24 +362,52 @@ impl From<CtUser> for UserSettings {
     fn from(value: CtUser) -> Self {
         Self {
             is_away: value.is_away,
+            away_from: value.away_from,
+            away_until: value.away_until,
+            away_message: value.away_message,
+            default_ping_preference: value.default_ping_preference,
+            default_availability_status: value.default_availability_status,
         }
     }
 }
 
+/// Checks that an availability status can be applied to slots the user claims.
+///
+/// Open and unknown slots look unclaimed, so they can't be used as defaults.
+fn validate_claim_availability_status(status: Option<AvailabilityStatus>) -> Result<(), ApiError> {
+    match status {
+        Some(AvailabilityStatus::Open | AvailabilityStatus::Unknown) => Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Claimed slots can't default to being open or unknown.",
+        )),
+
+        _ => Ok(()),
+    }
+}
+
 /// `GET /user/self/settings`: Get user settings.
 pub async fn get_settings(user: AuthenticatedUser) -> impl IntoResponse {
     Json(UserSettings::from(user.user))
 }
 
 /// `PUT /user/self/settings`: Update user settings.
+///
+/// An away period can be scheduled with `away_from` and `away_until`.  The
+/// user is marked as away when the period starts and as back when it ends.
 pub async fn put_settings<D>(
     State(state): State<Arc<AppState<D>>>,
     user: AuthenticatedUser,
     Json(settings): Json<UserSettings>,
-) -> Result<impl IntoResponse, StatusCode>
+) -> Result<impl IntoResponse, ApiError>
 where
     D: DataAccessProvider + Send + Sync + 'static,
 {
+    user.authorize_account_update()?;
+
+    let now = Utc::now();
+
+    settings.validate(now)?;
+
     let mut db = state
         .data_provider
         .create_data_access()
@@ -126,7 +416,7 @@ where
 
     let mut user = user.user;
 
-    settings.apply_to(&mut user);
+    settings.apply_to(&mut user, now);
 
     let user = db
         .update_ct_user(user, &UserSettings::COLUMNS)
@@ -139,3 +429,935 @@ where
 
     Ok(Json(UserSettings::from(user)))
 }
+
+/// `GET /user/self/game_preferences`: Get the user's per-game claim
+/// preferences.
+pub async fn get_game_preferences<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut preferences: Vec<_> = db
+        .get_ct_user_game_preferences_by_user_id(user.user.id)
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    preferences.sort_by(|a, b| a.game.cmp(&b.game));
+
+    Ok(Json(preferences))
+}
+
+/// Request body for [`put_game_preference`].
+#[derive(Debug, Deserialize)]
+pub struct PutGamePreferenceRequest {
+    #[serde(default)]
+    pub discord_ping: Option<PingPreference>,
+    #[serde(default)]
+    pub availability_status: Option<AvailabilityStatus>,
+}
+
+/// `PUT /user/self/game_preferences/{game}`: Set the user's claim preferences
+/// for slots of a game, overriding their defaults.
+pub async fn put_game_preference<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+    Path(game): Path<String>,
+    Json(request): Json<PutGamePreferenceRequest>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    user.authorize_account_update()?;
+
+    if request.discord_ping.is_none() && request.availability_status.is_none() {
+        return Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "A game preference must override at least one setting.",
+        ));
+    }
+
+    validate_claim_availability_status(request.availability_status)?;
+
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let preference = CtUserGamePreference {
+        ct_user_id: user.user.id,
+        game,
+        discord_ping: request.discord_ping,
+        availability_status: request.availability_status,
+    };
+
+    db.upsert_ct_user_game_preference(preference.clone())
+        .await
+        .unexpected()?;
+
+    Ok(Json(preference))
+}
+
+/// `DELETE /user/self/game_preferences/{game}`: Remove the user's claim
+/// preferences for slots of a game.
+pub async fn delete_game_preference<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+    Path(game): Path<String>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    user.authorize_account_update()?;
+
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    db.delete_ct_user_game_preference(user.user.id, &game)
+        .await
+        .unexpected()?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// A login identity linked to the user.
+#[derive(Debug, Clone, Serialize)]
+pub struct IdentityResponse {
+    pub id: i32,
+    /// The identifier of the login provider.
+    pub provider: String,
+    /// Name of the login provider shown to users.
+    pub provider_name: String,
+    /// The account's display name at the provider.
+    pub username: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl IdentityResponse {
+    fn new<D>(state: &AppState<D>, identity: CtUserIdentity) -> Self {
+        let provider_name = if identity.provider == discord::PROVIDER_ID {
+            "Discord".to_owned()
+        } else {
+            state
+                .oidc_auth_client
+                .as_ref()
+                .filter(|client| client.provider_id() == identity.provider)
+                .map_or_else(
+                    || identity.provider.clone(),
+                    |client| client.display_name().to_owned(),
+                )
+        };
+
+        Self {
+            id: identity.id,
+            provider: identity.provider,
+            provider_name,
+            username: identity.username,
+            created_at: identity.created_at,
+        }
+    }
+}
+
+/// `GET /user/self/identities`: Get the login identities linked to the user.
+pub async fn get_identities<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let identities: Vec<_> = db
+        .get_ct_user_identities_by_user_id(user.user.id)
+        .map_ok(|identity| IdentityResponse::new(&state, identity))
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    Ok(Json(identities))
+}
+
+/// Query parameters for [`begin_link_identity`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct LinkIdentityQuery {
+    pub provider: AuthProvider,
+}
+
+/// `GET /user/self/identities/link`: Begin authenticating with a login provider
+/// to link the account to the user.
+///
+/// Requires the user to have signed in recently.  The attempt is completed
+/// with [`link_identity`].
+pub async fn begin_link_identity<D>(
+    State(state): State<Arc<AppState<D>>>,
+    _user: RecentlyAuthenticatedUser,
+    Query(query): Query<LinkIdentityQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    begin_provider_auth(&state, query.provider).await.map(Json)
+}
+
+/// Request body for [`link_identity`].
+#[derive(Deserialize)]
+pub struct LinkIdentityRequest {
+    pub provider: AuthProvider,
+    #[serde(flatten)]
+    pub auth: CompleteAuthRequest,
+}
+
+/// `POST /user/self/identities`: Complete authenticating with a login provider
+/// and link the account to the user.
+///
+/// Requires the user to have signed in recently.  Fails if the account is
+/// already linked to a different user.
+pub async fn link_identity<D>(
+    State(state): State<Arc<AppState<D>>>,
+    RecentlyAuthenticatedUser(user): RecentlyAuthenticatedUser,
+    Json(request): Json<LinkIdentityRequest>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    const ALREADY_LINKED: &str = "That account is already linked to another user.";
+
+    let account = complete_provider_auth(&state, request.provider, request.auth).await?;
+
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    match tx
+        .get_ct_user_identity(&account.provider_id, &account.subject)
+        .await
+        .unexpected()?
+    {
+        Some(identity) if identity.ct_user_id == user.user.id => {
+            return Ok(Json(IdentityResponse::new(&state, identity)));
+        }
+        Some(_) => return Err(ApiError::new(StatusCode::CONFLICT, ALREADY_LINKED)),
+        None => {}
+    }
+
+    let r = {
+        let identities = send_stream(tx.create_ct_user_identities([CtUserIdentityInsertion {
+            ct_user_id: user.user.id,
+            provider: account.provider_id,
+            subject: account.subject,
+            username: account.username,
+            created_at: Utc::now(),
+        }]));
+
+        tokio::pin!(identities);
+
+        identities.try_next().await
+    };
+
+    let identity = match r {
+        Err(e)
+            if e.as_database_error()
+                .is_some_and(|dbe| dbe.is_unique_violation()) =>
+        {
+            return Err(ApiError::new(StatusCode::CONFLICT, ALREADY_LINKED));
+        }
+        v => v
+            .unexpected()?
+            .ok_or("Identity insertion returned no rows")
+            .unexpected()?,
+    };
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    Ok(Json(IdentityResponse::new(&state, identity)))
+}
+
+/// `DELETE /user/self/identities/{id}`: Unlink a login identity from the user.
+///
+/// Requires the user to have signed in recently.  The user's last remaining
+/// identity cannot be unlinked, since they would no longer be able to sign in.
+pub async fn unlink_identity<D>(
+    State(state): State<Arc<AppState<D>>>,
+    RecentlyAuthenticatedUser(user): RecentlyAuthenticatedUser,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    let identities: Vec<_> = send_stream(tx.get_ct_user_identities_by_user_id(user.user.id))
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    if !identities.iter().any(|i| i.id == id) {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+
+    if identities.len() == 1 {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "Your only linked account cannot be unlinked.",
+        ));
+    }
+
+    tx.delete_ct_user_identity(user.user.id, id)
+        .await
+        .unexpected()?;
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// A signed-in session of the user.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionResponse {
+    pub id: i32,
+    /// When the user signed in.
+    pub created_at: DateTime<Utc>,
+    /// When the session was last refreshed.
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// The user agent of the client that last used the session.
+    pub user_agent: Option<String>,
+    /// The IP address of the client that last used the session.
+    pub ipaddr: Option<IpAddr>,
+    /// Whether this is the session making the request.
+    pub current: bool,
+}
+
+/// `GET /user/self/sessions`: Get the user's active sessions, most recently
+/// used first.
+pub async fn get_sessions<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let sessions: Vec<_> = db
+        .get_active_ct_user_sessions_by_user_id(user.user.id, Utc::now())
+        .map_ok(|session| SessionResponse {
+            id: session.id,
+            created_at: session.created_at,
+            last_used_at: session.last_used_at,
+            expires_at: session.expires_at,
+            user_agent: session.user_agent,
+            ipaddr: session.ipaddr.map(|ip| ip.ip()),
+            current: user.session_id == Some(session.id),
+        })
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    Ok(Json(sessions))
+}
+
+/// Revokes the user's sessions, or only the session with the given ID.
+///
+/// Returns the number of sessions revoked.
+async fn revoke_sessions<D>(
+    state: &AppState<D>,
+    ct_user_id: i32,
+    id: Option<i32>,
+) -> Result<usize, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let revoked: Vec<_> = db
+        .revoke_ct_user_sessions(ct_user_id, id, Utc::now())
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    for session in &revoked {
+        state.session_cache.invalidate(&session.jti).await;
+    }
+
+    Ok(revoked.len())
+}
+
+/// `DELETE /user/self/sessions`: Revoke all of the user's sessions, signing
+/// them out everywhere.
+pub async fn revoke_all_sessions<D>(
+    State(state): State<Arc<AppState<D>>>,
+    TokenAuthenticatedUser(user): TokenAuthenticatedUser,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    revoke_sessions(&state, user.id, None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `DELETE /user/self/sessions/current`: Revoke the session making the
+/// request, signing out.
+pub async fn revoke_current_session<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let id = user.session_id.ok_or(StatusCode::NOT_FOUND)?;
+
+    revoke_sessions(&state, user.user.id, Some(id)).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `DELETE /user/self/sessions/{id}`: Revoke one of the user's sessions.
+pub async fn revoke_session<D>(
+    State(state): State<Arc<AppState<D>>>,
+    TokenAuthenticatedUser(user): TokenAuthenticatedUser,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    match revoke_sessions(&state, user.id, Some(id)).await? {
+        0 => Err(StatusCode::NOT_FOUND),
+        _ => Ok(StatusCode::NO_CONTENT),
+    }
+}
+
+/// Identifies the tracker of a slot or hint returned by [`get_games`] or
+/// [`get_hints`].
+#[derive(Debug, Clone, Serialize)]
+struct TrackerSummary {
+    tracker_id: UrlEncodedUuid,
+    title: String,
+}
+
+impl TrackerSummary {
+    fn new(tracker: &ApTracker) -> Self {
+        Self {
+            tracker_id: tracker.tracker_id.into(),
+            title: tracker.title.clone(),
+        }
+    }
+}
+
+/// Query parameters for [`get_games`].
+///
+/// Each filter is optional.  When given, only slots that do (`true`) or do not
+/// (`false`) match the filter are returned.
+#[derive(Debug, Deserialize)]
+pub struct UserGamesQuery {
+    /// Whether the slot is done or released.
+    #[serde(default)]
+    pub finished: Option<bool>,
+    /// Whether the slot is in BK or soft BK.
+    #[serde(default)]
+    pub bk: Option<bool>,
+    /// Whether the slot has crossed its tracker's red inactivity threshold.
+    #[serde(default)]
+    pub stale: Option<bool>,
+}
+
+/// `GET /user/self/games`: Get all slots claimed or co-claimed by the user
+/// across all trackers.
+pub async fn get_games<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+    Query(query): Query<UserGamesQuery>,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    #[derive(Serialize)]
+    struct UserGame {
+        #[serde(flatten)]
+        game: ApGame,
+        tracker: TrackerSummary,
+        inactivity_color: InactivityColor,
+        /// Unfound hints that the slot needs to find, by classification.
+        waiting_hints: HashMap<HintClassification, i64>,
+    }
+
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let games: Vec<_> = db
+        .get_ap_games_claimed_by_user(user.user.id)
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    let mut tracker_ids: Vec<_> = games.iter().map(|g| g.tracker_id).collect();
+    tracker_ids.sort_unstable();
+    tracker_ids.dedup();
+
+    let trackers: HashMap<_, _> = db
+        .get_trackers_by_ids(&tracker_ids)
+        .map_ok(|t| (t.id, t))
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    let mut hint_stats: HashMap<i32, HashMap<_, _>> = HashMap::new();
+
+    {
+        let stats = db.get_user_game_hint_stats(user.user.id);
+
+        tokio::pin!(stats);
+
+        while let Some(s) = stats.try_next().await.unexpected()? {
+            hint_stats
+                .entry(s.ap_game_id)
+                .or_default()
+                .insert(s.classification, s.hints);
+        }
+    }
+
+    let now = Utc::now();
+
+    let mut games: Vec<_> = games
+        .into_iter()
+        .filter_map(|game| {
+            let tracker = trackers.get(&game.tracker_id)?;
+            let inactivity_color = game.inactivity_color(tracker, now);
+
+            let finished = matches!(
+                game.completion_status,
+                CompletionStatus::Done | CompletionStatus::Released
+            );
+            let bk = matches!(
+                game.progression_status,
+                ProgressionStatus::Bk | ProgressionStatus::SoftBk
+            );
+            let stale = inactivity_color == InactivityColor::Red;
+
+            if query.finished.is_some_and(|f| f != finished)
+                || query.bk.is_some_and(|f| f != bk)
+                || query.stale.is_some_and(|f| f != stale)
+            {
+                return None;
+            }
+
+            Some(UserGame {
+                waiting_hints: hint_stats.remove(&game.id).unwrap_or_default(),
+                tracker: TrackerSummary::new(tracker),
+                inactivity_color,
+                game,
+            })
+        })
+        .collect();
+
+    games.sort_by(|a, b| {
+        (&a.tracker.title, a.game.tracker_id, a.game.position).cmp(&(
+            &b.tracker.title,
+            b.game.tracker_id,
+            b.game.position,
+        ))
+    });
+
+    Ok(Json(games))
+}
+
+/// Query parameters for [`get_hints`].
+#[derive(Debug, Deserialize)]
+pub struct UserHintsQuery {
+    /// Only return hints that changed after this time.
+    ///
+    /// Hints that changed shortly before this time may be returned again.
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+}
+
+/// How far [`get_hints`] looks back before the `since` time it is given.
+///
+/// Tracker updates stamp their changes before fetching from the upstream
+/// tracker and commit them afterwards, so a poll can finish between the two
+/// and miss the changes if the next poll starts exactly where it ended.
+fn hint_poll_overlap() -> TimeDelta {
+    TimeDelta::from_std(UPSTREAM_TIMEOUT * 2).unwrap()
+}
+
+/// How a hint returned by [`get_hints`] concerns the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserHintRole {
+    /// One of the user's slots has to find the hinted item.
+    Finder,
+    /// One of the user's slots is waiting on the hinted item.
+    Receiver,
+}
+
+/// `GET /user/self/hints`: Get the hint inbox of the user across all trackers.
+///
+/// The inbox contains unfound hints that the user's slots need to find and that
+/// the receiver classified as critical or progression, as well as unfound hints
+/// that the user's slots are waiting on and that were not classified as trash.
+///
+/// The response includes an `as_of` time, which can be passed as `since` to
+/// only get hints that changed after the previous request.  In that case, hints
+/// that no longer belong in the inbox (such as hints that were found) are also
+/// returned, with `in_inbox` unset, so that clients can remove them.  All inbox
+/// hints of slots the user claimed or joined since then are returned as well.
+pub async fn get_hints<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+    Query(query): Query<UserHintsQuery>,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    #[derive(Serialize)]
+    struct UserHint {
+        #[serde(flatten)]
+        hint: HintResponse,
+        role: UserHintRole,
+        in_inbox: bool,
+        tracker: TrackerSummary,
+        finder_name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        receiver_name: Option<String>,
+    }
+
+    #[derive(Serialize)]
+    struct UserHints {
+        as_of: DateTime<Utc>,
+        hints: Vec<UserHint>,
+    }
+
+    // Captured before querying so that changes made while the request is
+    // processed are returned by the next poll.
+    let as_of = Utc::now();
+
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut games: HashMap<_, _> = db
+        .get_ap_games_claimed_by_user(user.user.id)
+        .map_ok(|g| (g.id, g))
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    if games.is_empty() {
+        return Ok(Json(UserHints {
+            as_of,
+            hints: vec![],
+        }));
+    }
+
+    let own_game_ids: HashSet<_> = games.keys().copied().collect();
+    let game_ids: Vec<_> = own_game_ids.iter().copied().collect();
+
+    let since = query.since.map(|s| s - hint_poll_overlap());
+
+    let mut hints: Vec<_> = db
+        .get_ap_hints_by_game_ids(&game_ids, since.is_some(), since)
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    // The existing hints of newly claimed slots haven't changed, but the user
+    // hasn't seen them yet.
+    if let Some(since) = since {
+        let claimed_game_ids: Vec<_> = db
+            .get_ap_game_ids_with_claim_changes(&game_ids, since)
+            .try_collect()
+            .await
+            .unexpected()?;
+
+        if !claimed_game_ids.is_empty() {
+            let seen: HashSet<_> = hints.iter().map(|h| h.id).collect();
+
+            let claimed_hints: Vec<_> = db
+                .get_ap_hints_by_game_ids(&claimed_game_ids, false, None)
+                .try_filter(|h| ready(!seen.contains(&h.id)))
+                .try_collect()
+                .await
+                .unexpected()?;
+
+            hints.extend(claimed_hints);
+        }
+    }
+
+    let is_in_inbox = |hint: &ApHint, role: UserHintRole| {
+        !hint.found
+            && match role {
+                UserHintRole::Finder => matches!(
+                    hint.classification,
+                    HintClassification::Critical | HintClassification::Progression
+                ),
+                UserHintRole::Receiver => hint.classification != HintClassification::Trash,
+            }
+    };
+
+    let role_of = |hint: &ApHint| match own_game_ids.contains(&hint.finder_game_id) {
+        true => UserHintRole::Finder,
+        false => UserHintRole::Receiver,
+    };
+
+    // Without a previous poll, only hints currently in the inbox are of
+    // interest.
+    let hints: Vec<_> = hints
+        .into_iter()
+        .filter(|h| query.since.is_some() || is_in_inbox(h, role_of(h)))
+        .collect();
+
+    let mut other_game_ids: Vec<_> = hints
+        .iter()
+        .flat_map(|h| [Some(h.finder_game_id), h.receiver_game_id])
+        .flatten()
+        .filter(|id| !games.contains_key(id))
+        .collect();
+
+    other_game_ids.sort_unstable();
+    other_game_ids.dedup();
+
+    if !other_game_ids.is_empty() {
+        games.extend(
+            db.get_ap_games_by_ids(&other_game_ids)
+                .map_ok(|g| (g.id, g))
+                .try_collect::<Vec<_>>()
+                .await
+                .unexpected()?,
+        );
+    }
+
+    let mut tracker_ids: Vec<_> = games.values().map(|g| g.tracker_id).collect();
+    tracker_ids.sort_unstable();
+    tracker_ids.dedup();
+
+    let trackers: HashMap<_, _> = db
+        .get_trackers_by_ids(&tracker_ids)
+        .map_ok(|t| (t.id, t))
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    let mut hints: Vec<_> = HintResponse::from_hints(&mut db, hints)
+        .await
+        .unexpected()?
+        .into_iter()
+        .filter_map(|hint| {
+            let finder = games.get(&hint.hint.finder_game_id)?;
+            let tracker = trackers.get(&finder.tracker_id)?;
+            let role = role_of(&hint.hint);
+
+            Some(UserHint {
+                role,
+                in_inbox: is_in_inbox(&hint.hint, role),
+                tracker: TrackerSummary::new(tracker),
+                finder_name: finder.name.clone(),
+                receiver_name: hint
+                    .hint
+                    .receiver_game_id
+                    .and_then(|id| games.get(&id))
+                    .map(|g| g.name.clone()),
+                hint,
+            })
+        })
+        .collect();
+
+    hints.sort_by(|a, b| {
+        (&a.tracker.title, a.hint.hint.id).cmp(&(&b.tracker.title, b.hint.hint.id))
+    });
+
+    Ok(Json(UserHints { as_of, hints }))
+}
+
+/// Query parameters for [`get_notifications`].
+#[derive(Debug, Deserialize)]
+pub struct NotificationsQuery {
+    /// Only return notifications older than the notification with this ID.
+    #[serde(default)]
+    pub before: Option<i32>,
+    /// The maximum number of notifications to return.
+    #[serde(default = "default_notifications_limit")]
+    pub limit: u64,
+}
+
+#[doc(hidden)]
+fn default_notifications_limit() -> u64 {
+    50
+}
+
+/// The maximum number of notifications returned by [`get_notifications`].
+const MAX_NOTIFICATIONS_LIMIT: u64 = 200;
+
+/// A notification as returned by the API.
+#[derive(Debug, Serialize)]
+pub struct NotificationResponse {
+    pub id: i32,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_at: Option<DateTime<Utc>>,
+    pub event: serde_json::Value,
+    pub message: String,
+}
+
+impl TryFrom<CtUserNotification> for NotificationResponse {
+    type Error = serde_json::Error;
+
+    fn try_from(notification: CtUserNotification) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: notification.id,
+            created_at: notification.created_at,
+            read_at: notification.read_at,
+            event: serde_json::from_str(&notification.event)?,
+            message: notification.message,
+        })
+    }
+}
+
+/// `GET /user/self/notifications`: Get the user's notifications, newest first.
+///
+/// Older notifications can be fetched by passing the ID of the oldest
+/// notification received as `before`.
+pub async fn get_notifications<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+    Query(query): Query<NotificationsQuery>,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let notifications: Vec<_> = db
+        .get_ct_user_notifications(
+            user.user.id,
+            query.before,
+            query.limit.min(MAX_NOTIFICATIONS_LIMIT),
+        )
+        .try_collect()
+        .await
+        .unexpected()?;
+
+    Ok(Json(
+        notifications
+            .into_iter()
+            .map(NotificationResponse::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .unexpected()?,
+    ))
+}
+
+/// Response body for endpoints returning the user's unread notification
+/// count.
+#[derive(Debug, Serialize)]
+pub struct UnreadNotificationCount {
+    pub unread: i64,
+}
+
+/// `GET /user/self/notifications/unread_count`: Get the number of the user's
+/// unread notifications.
+pub async fn get_unread_notification_count<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, StatusCode>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let unread = db
+        .get_ct_user_unread_notification_count(user.user.id)
+        .await
+        .unexpected()?;
+
+    Ok(Json(UnreadNotificationCount { unread }))
+}
+
+/// Request body for [`mark_notifications_read`].
+#[derive(Debug, Deserialize)]
+pub struct MarkNotificationsReadRequest {
+    /// The notifications to mark as read.  If omitted, all of the user's
+    /// notifications are marked as read.
+    #[serde(default)]
+    pub ids: Option<Vec<i32>>,
+}
+
+/// `POST /user/self/notifications/read`: Mark the user's notifications as read.
+///
+/// Returns the number of notifications that are still unread.
+pub async fn mark_notifications_read<D>(
+    State(state): State<Arc<AppState<D>>>,
+    user: AuthenticatedUser,
+    Json(request): Json<MarkNotificationsReadRequest>,
+) -> Result<impl IntoResponse, ApiError>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    user.authorize_account_update()?;
+
+    let mut db = state
+        .data_provider
+        .create_data_access()
+        .await
+        .unexpected()?;
+
+    let mut tx = db.begin().await.unexpected()?;
+
+    tx.mark_ct_user_notifications_read(user.user.id, request.ids.as_deref(), Utc::now())
+        .await
+        .unexpected()?;
+
+    let unread = tx
+        .get_ct_user_unread_notification_count(user.user.id)
+        .await
+        .unexpected()?;
+
+    send_future(tx.commit()).await.unexpected()?;
+
+    Ok(Json(UnreadNotificationCount { unread }))
+}
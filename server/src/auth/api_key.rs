@@ -0,0 +1,196 @@
+//! API keys.
+
+use uuid::Uuid;
+
+use crate::db::model::ApiKey;
+
+use super::secret;
+
+/// Prefix of API key secrets, which distinguishes them from session tokens.
+pub const PREFIX: &str = "ctk_";
+
+/// Generates a new API key secret, returning the secret and its hash.
+pub fn generate() -> (String, String) {
+    let (secret, hash) = secret::generate();
+
+    (format!("{PREFIX}{secret}"), hash)
+}
+
+/// The length of API key secrets, excluding the prefix.
+const SECRET_LEN: usize = 64;
+
+/// Hashes a bearer token for lookup if it is an API key.
+///
+/// Keys created before keys were hashed are UUIDs.  These are hashed in their
+/// hyphenated form regardless of how they were sent.  Bearer tokens with the
+/// key prefix that aren't shaped like a generated secret aren't API keys, so
+/// they are never looked up.
+pub fn hash_bearer(bearer: &str) -> Option<String> {
+    match bearer.strip_prefix(PREFIX) {
+        Some(secret)
+            if secret.len() == SECRET_LEN
+                && secret
+                    .bytes()
+                    .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b)) =>
+        {
+            Some(secret::hash(secret))
+        }
+        Some(_) => None,
+        None => bearer
+            .parse::<Uuid>()
+            .ok()
+            .map(|key| secret::hash(&key.hyphenated().to_string())),
+    }
+}
+
+/// The changes a request is allowed to make to a tracker.
+///
+/// Requests authenticated with a session token may make all changes, subject
+/// to the tracker's policy.  Requests authenticated with an API key are
+/// limited to the key's scopes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApiKeyScopes {
+    pub update_games: bool,
+    pub update_hints: bool,
+    pub update_trackers: bool,
+}
+
+impl ApiKeyScopes {
+    /// Scopes allowing all changes.
+    pub const ALL: Self = Self {
+        update_games: true,
+        update_hints: true,
+        update_trackers: true,
+    };
+
+    /// Scopes allowing no changes.
+    pub const NONE: Self = Self {
+        update_games: false,
+        update_hints: false,
+        update_trackers: false,
+    };
+}
+
+/// An API key used to authenticate a request, with the trackers it is limited
+/// to.
+#[derive(Debug, Clone)]
+pub struct ApiKeyGrant {
+    pub key: ApiKey,
+    /// The IDs of the trackers the key may change.  Empty if the key may change
+    /// all trackers.
+    pub ap_tracker_ids: Vec<i32>,
+}
+
+impl ApiKeyGrant {
+    /// The changes the key allows on the given tracker.
+    pub fn scopes_for(&self, ap_tracker_id: i32) -> ApiKeyScopes {
+        if !self.key.all_trackers && !self.ap_tracker_ids.contains(&ap_tracker_id) {
+            return ApiKeyScopes::NONE;
+        }
+
+        ApiKeyScopes {
+            update_games: self.key.can_update_games,
+            update_hints: self.key.can_update_hints,
+            update_trackers: self.key.can_update_trackers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn grant(all_trackers: bool, ap_tracker_ids: Vec<i32>) -> ApiKeyGrant {
+        ApiKeyGrant {
+            key: ApiKey {
+                id: 1,
+                ct_user_id: 1,
+                name: "Key".to_owned(),
+                secret_hash: String::new(),
+                can_update_games: true,
+                can_update_hints: false,
+                can_update_trackers: true,
+                can_update_account: false,
+                all_trackers,
+                created_at: Utc::now(),
+                expires_at: None,
+                last_used_at: None,
+            },
+            ap_tracker_ids,
+        }
+    }
+
+    #[test]
+    fn unrestricted_keys_grant_their_scopes_on_all_trackers() {
+        let grant = grant(true, vec![]);
+
+        for ap_tracker_id in [1, 2, 3] {
+            assert_eq!(
+                grant.scopes_for(ap_tracker_id),
+                ApiKeyScopes {
+                    update_games: true,
+                    update_hints: false,
+                    update_trackers: true,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn restricted_keys_grant_nothing_on_other_trackers() {
+        let grant = grant(false, vec![2]);
+
+        assert_eq!(grant.scopes_for(1), ApiKeyScopes::NONE);
+        assert_eq!(
+            grant.scopes_for(2),
+            ApiKeyScopes {
+                update_games: true,
+                update_hints: false,
+                update_trackers: true,
+            }
+        );
+    }
+
+    #[test]
+    fn restricted_keys_without_trackers_grant_nothing() {
+        assert_eq!(grant(false, vec![]).scopes_for(1), ApiKeyScopes::NONE);
+    }
+
+    #[test]
+    fn generated_secrets_are_hashed() {
+        let (secret, hash) = generate();
+
+        assert_eq!(hash_bearer(&secret), Some(hash));
+    }
+
+    #[test]
+    fn legacy_keys_are_hashed_hyphenated() {
+        let key = Uuid::new_v4();
+        let expected = secret::hash(&key.hyphenated().to_string());
+
+        assert_eq!(
+            hash_bearer(&key.hyphenated().to_string()),
+            Some(expected.clone())
+        );
+        assert_eq!(hash_bearer(&key.simple().to_string()), Some(expected));
+    }
+
+    #[test]
+    fn malformed_bearers_are_not_hashed() {
+        let valid = "0123456789abcdef".repeat(4);
+
+        for bearer in [
+            String::new(),
+            "not a key".to_owned(),
+            PREFIX.to_owned(),
+            format!("{PREFIX}{}", &valid[1..]),
+            format!("{PREFIX}{valid}0"),
+            format!("{PREFIX}{}", valid.to_uppercase()),
+            format!("{PREFIX}{}g", &valid[1..]),
+        ] {
+            assert_eq!(hash_bearer(&bearer), None, "{bearer:?}");
+        }
+    }
+}
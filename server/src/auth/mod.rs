@@ -1,4 +1,8 @@
 //! Authentication.
 
+pub mod api_key;
+pub mod continuation;
 pub mod discord;
+pub mod oidc;
+pub mod secret;
 pub mod token;
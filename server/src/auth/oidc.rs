@@ -0,0 +1,270 @@
+//! Generic OpenID Connect authentication.
+//!
+//! The provider's endpoints are discovered from its issuer URL the first time
+//! they are needed.  The signed-in account is identified by fetching the
+//! provider's userinfo endpoint with the access token obtained from the code
+//! exchange, so no ID token validation is required.
+
+use chacha20poly1305::XChaCha20Poly1305;
+use oauth2::{
+    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, PkceCodeChallenge, RedirectUrl,
+    Scope, TokenResponse, TokenUrl, basic::BasicClient, reqwest::async_http_client,
+};
+use tokio::sync::OnceCell;
+use url::Url;
+
+use crate::conf::Oidc;
+
+use super::continuation::{
+    AuthState, ContinuationTokenDecryptError, decrypt_continuation_token,
+    encrypt_continuation_token,
+};
+
+/// OpenID Connect authentication client.
+///
+/// This wraps the provider configuration, the lazily-discovered provider
+/// endpoints, and the cipher used to encrypt and decrypt continuation tokens.
+pub struct OidcAuthClient {
+    /// The identifier of the provider, as recorded in user identities.
+    provider_id: String,
+    /// Name of the provider shown to users.
+    display_name: String,
+    /// The provider's issuer URL.
+    issuer: Url,
+    client_id: ClientId,
+    client_secret: Option<ClientSecret>,
+    redirect_url: RedirectUrl,
+    /// The cipher used to encrypt and decrypt continuation tokens.
+    continuation_token_cipher: XChaCha20Poly1305,
+    /// Client used for discovery and userinfo requests.
+    http_client: reqwest::Client,
+    /// The provider's endpoints, once discovered.
+    provider: OnceCell<Provider>,
+}
+
+/// A discovered provider.
+struct Provider {
+    /// The OAuth2 client for the provider's endpoints.
+    client: BasicClient,
+    /// The provider's userinfo endpoint.
+    userinfo_endpoint: Url,
+}
+
+/// The subset of the provider metadata document that we use.
+#[derive(serde::Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: Url,
+    token_endpoint: Url,
+    userinfo_endpoint: Url,
+}
+
+/// Claims returned by the provider's userinfo endpoint.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct UserInfo {
+    /// The provider's stable identifier for the account.
+    pub sub: String,
+    pub preferred_username: Option<String>,
+    pub name: Option<String>,
+}
+
+impl UserInfo {
+    /// The name to display for the account.
+    ///
+    /// This falls back to the subject identifier if the provider does not
+    /// supply a name.
+    pub fn display_name(&self) -> &str {
+        self.preferred_username
+            .as_deref()
+            .or(self.name.as_deref())
+            .unwrap_or(&self.sub)
+    }
+}
+
+impl OidcAuthClient {
+    /// Creates a new client.
+    ///
+    /// No requests are made to the provider until an authentication attempt
+    /// is started.
+    pub fn new(
+        config: Oidc,
+        public_url: &Url,
+        continuation_token_cipher: XChaCha20Poly1305,
+        http_client: reqwest::Client,
+    ) -> Self {
+        Self {
+            provider_id: config.provider_id,
+            display_name: config.display_name,
+            issuer: config.issuer,
+            client_id: ClientId::new(config.client_id),
+            client_secret: config.client_secret.map(ClientSecret::new),
+            redirect_url: RedirectUrl::from_url(public_url.join("auth/oidc/complete").unwrap()),
+            continuation_token_cipher,
+            http_client,
+            provider: OnceCell::new(),
+        }
+    }
+
+    /// The identifier of the provider, as recorded in user identities.
+    pub fn provider_id(&self) -> &str {
+        &self.provider_id
+    }
+
+    /// Name of the provider shown to users.
+    pub fn display_name(&self) -> &str {
+        &self.display_name
+    }
+
+    /// Gets the provider's endpoints, discovering them if necessary.
+    async fn provider(&self) -> Result<&Provider, DiscoveryError> {
+        self.provider
+            .get_or_try_init(|| async {
+                let url: Url = format!(
+                    "{}/.well-known/openid-configuration",
+                    self.issuer.as_str().trim_end_matches('/')
+                )
+                .parse()
+                .unwrap();
+
+                let metadata: ProviderMetadata = self
+                    .http_client
+                    .get(url)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(DiscoveryError::Request)?
+                    .json()
+                    .await
+                    .map_err(DiscoveryError::Request)?;
+
+                if metadata.issuer.trim_end_matches('/')
+                    != self.issuer.as_str().trim_end_matches('/')
+                {
+                    return Err(DiscoveryError::IssuerMismatch(metadata.issuer));
+                }
+
+                let client = BasicClient::new(
+                    self.client_id.clone(),
+                    self.client_secret.clone(),
+                    AuthUrl::from_url(metadata.authorization_endpoint),
+                    Some(TokenUrl::from_url(metadata.token_endpoint)),
+                )
+                .set_redirect_uri(self.redirect_url.clone());
+
+                Ok(Provider {
+                    client,
+                    userinfo_endpoint: metadata.userinfo_endpoint,
+                })
+            })
+            .await
+    }
+
+    /// Begins a new authentication attempt.
+    pub async fn begin(&self) -> Result<AuthState, BeginOidcAuthenticationError> {
+        let provider = self
+            .provider()
+            .await
+            .map_err(BeginOidcAuthenticationError::Discovery)?;
+
+        let (challenge, verifier) = PkceCodeChallenge::new_random_sha256();
+
+        let (auth_url, csrf_token) = provider
+            .client
+            .authorize_url(CsrfToken::new_random)
+            .add_scope(Scope::new("openid".to_owned()))
+            .add_scope(Scope::new("profile".to_owned()))
+            .set_pkce_challenge(challenge)
+            .url();
+
+        let continuation_token = encrypt_continuation_token(
+            &self.continuation_token_cipher,
+            verifier.secret(),
+            csrf_token.secret(),
+        )
+        .map_err(BeginOidcAuthenticationError::TokenEncrypt)?;
+
+        Ok(AuthState {
+            auth_url,
+            continuation_token,
+        })
+    }
+
+    /// Completes an authentication attempt, returning the claims of the
+    /// account that signed in.
+    pub async fn complete(
+        &self,
+        code: String,
+        csrf_token: &str,
+        continuation_token: &str,
+    ) -> Result<UserInfo, CompleteOidcAuthenticationError<impl std::error::Error + use<>>> {
+        let verifier = decrypt_continuation_token(
+            continuation_token,
+            csrf_token,
+            &self.continuation_token_cipher,
+        )
+        .map_err(CompleteOidcAuthenticationError::ContinuationTokenDecrypt)?;
+
+        let provider = self
+            .provider()
+            .await
+            .map_err(CompleteOidcAuthenticationError::Discovery)?;
+
+        let token = provider
+            .client
+            .exchange_code(AuthorizationCode::new(code))
+            .set_pkce_verifier(verifier)
+            .request_async(async_http_client)
+            .await
+            .map_err(CompleteOidcAuthenticationError::RequestToken)?;
+
+        self.http_client
+            .get(provider.userinfo_endpoint.clone())
+            .bearer_auth(token.access_token().secret())
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(CompleteOidcAuthenticationError::UserInfo)?
+            .json()
+            .await
+            .map_err(CompleteOidcAuthenticationError::UserInfo)
+    }
+}
+
+/// Errors that may occur while discovering the provider's endpoints.
+#[derive(Debug, thiserror::Error)]
+pub enum DiscoveryError {
+    /// The provider metadata document could not be retrieved.
+    #[error("failed to fetch provider metadata: {0}")]
+    Request(reqwest::Error),
+    /// The provider metadata names a different issuer than was configured.
+    #[error("provider metadata has unexpected issuer {0}")]
+    IssuerMismatch(String),
+}
+
+/// Errors that may occur while beginning a new authentication attempt.
+#[derive(Debug, thiserror::Error)]
+pub enum BeginOidcAuthenticationError {
+    /// The provider's endpoints could not be discovered.
+    #[error("provider discovery failed: {0}")]
+    Discovery(DiscoveryError),
+    /// The continuation token could not be encrypted.
+    #[error("token encryption failed: {0}")]
+    TokenEncrypt(chacha20poly1305::Error),
+}
+
+/// Errors that may occur when completing an authentication attempt.
+#[derive(Debug, thiserror::Error)]
+pub enum CompleteOidcAuthenticationError<RTE> {
+    /// The provider's endpoints could not be discovered.
+    #[error("provider discovery failed: {0}")]
+    Discovery(DiscoveryError),
+    /// The provided continuation token could not be decrypted.
+    #[error("failed to decrypt continuation token: {0}")]
+    ContinuationTokenDecrypt(ContinuationTokenDecryptError),
+    /// The OAuth2 code exchange operation failed.
+    #[error("failed to request token from oauth2 endpoint: {0}")]
+    RequestToken(RTE),
+    /// The userinfo endpoint request failed.
+    #[error("failed to fetch userinfo: {0}")]
+    UserInfo(reqwest::Error),
+}
@@ -0,0 +1,21 @@
+//! Random bearer secrets that are stored only as hashes.
+
+use base64::prelude::*;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Generates a new random secret, returning the secret and its hash.
+pub fn generate() -> (String, String) {
+    let secret = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    let hash = hash(&secret);
+
+    (secret, hash)
+}
+
+/// Hashes a secret for storage and lookup.
+///
+/// Secrets are long random values, so a fast, unsalted hash is sufficient to
+/// keep them from being recovered from the database.
+pub fn hash(secret: &str) -> String {
+    BASE64_STANDARD.encode(Sha256::digest(secret.as_bytes()))
+}
@@ -6,75 +6,244 @@ use axum::{
     extract::{FromRequestParts, OptionalFromRequestParts},
     http::{StatusCode, header::AUTHORIZATION},
 };
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
-    db::{DataAccess, DataAccessProvider, model::CtUser},
+    api::ApiError,
+    conf,
+    db::{
+        DataAccess, DataAccessProvider,
+        model::{ApiKeyIden, CtUser, CtUserSession},
+    },
     logging::UnexpectedResultExt,
     state::AppState,
 };
 
+use super::api_key::{self, ApiKeyGrant, ApiKeyScopes};
+
+/// How long after signing in a session may make sensitive account changes,
+/// such as linking or unlinking login identities, in seconds.
+pub const REAUTHENTICATION_WINDOW_SECS: u64 = 10 * 60;
+
 /// Type alias for results of JWT operations.
 pub type Result<T, E = jsonwebtoken::errors::Error> = std::result::Result<T, E>;
 
 /// Encodes and decodes authentication tokens.
 pub struct TokenProcessor {
-    /// Token header.
-    header: Header,
-    /// Token encryption key.
-    encoding_key: EncodingKey,
-    /// Duration in seconds for which generated tokens should be valid.
-    validity_duration_sec: u64,
+    /// Keys used to sign and verify tokens, oldest first.
+    keys: Vec<ProcessorKey>,
+    /// Duration in seconds for which generated access tokens should be valid.
+    access_token_validity_sec: u64,
+    /// How long a session remains valid after it was last refreshed.
+    session_validity: chrono::Duration,
     /// Token issuer, placed in the `iss` payload field.
     issuer: String,
+}
 
+/// A key known to a [`TokenProcessor`].
+struct ProcessorKey {
+    /// The key ID.  `None` for the legacy shared secret, whose tokens have no
+    /// `kid` header.
+    kid: Option<String>,
+    /// Header of tokens signed with the key.
+    header: Header,
+    /// The key used to sign tokens, if the key may sign tokens.
+    encoding_key: Option<EncodingKey>,
     /// Cached validation options.
     validation: Validation,
-    /// Token decryption key.
+    /// The key used to verify tokens.
     decoding_key: DecodingKey,
+    /// The key isn't used to sign tokens before this time.
+    not_before: Option<DateTime<Utc>>,
+    /// The key isn't used to verify tokens after this time.
+    not_after: Option<DateTime<Utc>>,
 }
 
-impl TokenProcessor {
-    /// Creates a new token processor.
-    pub fn new(
-        header: Header,
-        key: &str,
-        issuer: String,
-        validity_duration: chrono::Duration,
+impl ProcessorKey {
+    fn new(
+        kid: Option<String>,
+        algorithm: Algorithm,
+        issuer: &str,
+        encoding_key: Option<EncodingKey>,
+        decoding_key: DecodingKey,
     ) -> Self {
-        let mut validation = Validation::new(header.alg);
-        validation.set_issuer(&[issuer.as_str()]);
+        let mut header = Header::new(algorithm);
+        header.kid = kid.clone();
+
+        let mut validation = Validation::new(algorithm);
+        validation.set_issuer(&[issuer]);
 
         Self {
+            kid,
             header,
-            encoding_key: EncodingKey::from_secret(key.as_bytes()),
-            validity_duration_sec: u64::try_from(validity_duration.num_seconds())
-                .expect("couldn't convert validity duration to u64"),
-            issuer,
-
+            encoding_key,
             validation,
-            decoding_key: DecodingKey::from_secret(key.as_bytes()),
+            decoding_key,
+            not_before: None,
+            not_after: None,
         }
     }
 
-    /// Issues a new token for the given user ID.
-    pub fn encode(&self, user_id: i32) -> Result<String> {
-        let now = jsonwebtoken::get_current_timestamp();
+    /// Whether the key may verify tokens at the given time.
+    fn verifies_at(&self, now: DateTime<Utc>) -> bool {
+        self.not_after.is_none_or(|t| now < t)
+    }
+
+    /// Whether the key may sign tokens at the given time, if it has an
+    /// encoding key.
+    fn signs_at(&self, now: DateTime<Utc>) -> bool {
+        self.not_before.is_none_or(|t| t <= now) && self.verifies_at(now)
+    }
+}
+
+/// Errors that may occur when creating a [`TokenProcessor`].
+#[derive(Debug, thiserror::Error)]
+pub enum TokenConfigError {
+    /// Neither a shared secret nor any keys were configured.
+    #[error("no token secret or keys are configured")]
+    NoKeys,
+    /// None of the configured keys may currently sign tokens, because none has
+    /// a private key or all of their signing periods haven't started or have
+    /// ended.
+    #[error("no token key can currently sign tokens")]
+    NoSigningKey,
+    /// The shared secret was configured with an algorithm that doesn't use a
+    /// shared secret.
+    #[error("token secret can't be used with {0:?}; configure keys instead")]
+    SecretAlgorithm(Algorithm),
+    /// More than one key has the same ID.
+    #[error("token key ID {0} is used more than once")]
+    DuplicateKid(String),
+}
+
+impl TokenProcessor {
+    /// Creates a new token processor.
+    pub fn new(config: conf::Token) -> Result<Self, TokenConfigError> {
+        let mut keys = Vec::with_capacity(config.keys.len() + 1);
+
+        if let Some(secret) = config.secret {
+            if !matches!(
+                config.algorithm,
+                Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+            ) {
+                return Err(TokenConfigError::SecretAlgorithm(config.algorithm));
+            }
+
+            // Once keys are configured, the secret only verifies tokens issued
+            // before that.
+            let encoding_key = config
+                .keys
+                .is_empty()
+                .then(|| EncodingKey::from_secret(secret.as_bytes()));
+
+            keys.push(ProcessorKey {
+                not_after: config.secret_not_after,
+                ..ProcessorKey::new(
+                    None,
+                    config.algorithm,
+                    &config.issuer,
+                    encoding_key,
+                    DecodingKey::from_secret(secret.as_bytes()),
+                )
+            });
+        }
+
+        for key in config.keys {
+            if keys.iter().any(|k| k.kid.as_ref() == Some(&key.kid)) {
+                return Err(TokenConfigError::DuplicateKid(key.kid));
+            }
+
+            keys.push(ProcessorKey {
+                not_before: key.not_before,
+                not_after: key.not_after,
+                ..ProcessorKey::new(
+                    Some(key.kid),
+                    key.algorithm,
+                    &config.issuer,
+                    key.encoding_key,
+                    key.decoding_key,
+                )
+            });
+        }
+
+        if keys.is_empty() {
+            return Err(TokenConfigError::NoKeys);
+        }
+
+        let now = Utc::now();
+
+        if !keys
+            .iter()
+            .any(|k| k.encoding_key.is_some() && k.signs_at(now))
+        {
+            return Err(TokenConfigError::NoSigningKey);
+        }
+
+        Ok(Self {
+            keys,
+            access_token_validity_sec: u64::try_from(config.access_token_validity.num_seconds())
+                .expect("couldn't convert access token validity to u64"),
+            session_validity: config.validity_duration,
+            issuer: config.issuer,
+        })
+    }
+
+    /// How long a session remains valid after it was last refreshed.
+    pub fn session_validity(&self) -> chrono::Duration {
+        self.session_validity
+    }
+
+    /// Issues a new access token for the given session.
+    ///
+    /// The token is signed with the newest key that may currently sign tokens.
+    pub fn encode(&self, session: &CtUserSession) -> Result<String> {
+        let now = Utc::now();
+
+        let (header, encoding_key) = self
+            .keys
+            .iter()
+            .rev()
+            .filter(|k| k.signs_at(now))
+            .find_map(|k| Some((&k.header, k.encoding_key.as_ref()?)))
+            .ok_or(jsonwebtoken::errors::ErrorKind::InvalidKeyFormat)?;
+
+        let iat = jsonwebtoken::get_current_timestamp();
 
         let payload = TokenPayload {
-            sub: user_id,
-            iat: now,
-            exp: now + self.validity_duration_sec,
+            sub: session.ct_user_id,
+            jti: session.jti,
+            iat,
+            exp: iat + self.access_token_validity_sec,
             iss: self.issuer.as_str().into(),
+            auth_time: session
+                .created_at
+                .timestamp()
+                .try_into()
+                .unwrap_or_default(),
         };
 
-        jsonwebtoken::encode(&self.header, &payload, &self.encoding_key)
+        jsonwebtoken::encode(header, &payload, encoding_key)
     }
 
     /// Decodes a token.
+    ///
+    /// The token is verified with the key named by its `kid` header, or with
+    /// the shared secret if it has none.
     pub fn decode(&self, token: &str) -> Result<TokenPayload<'static>> {
-        jsonwebtoken::decode(token, &self.decoding_key, &self.validation).map(|d| d.claims)
+        let kid = jsonwebtoken::decode_header(token)?.kid;
+        let now = Utc::now();
+
+        let key = self
+            .keys
+            .iter()
+            .find(|k| k.kid == kid && k.verifies_at(now))
+            .ok_or(jsonwebtoken::errors::ErrorKind::InvalidSignature)?;
+
+        jsonwebtoken::decode(token, &key.decoding_key, &key.validation).map(|d| d.claims)
     }
 }
 
@@ -83,12 +252,16 @@ impl TokenProcessor {
 pub struct TokenPayload<'a> {
     /// Local user ID.
     pub sub: i32,
+    /// The [`CtUserSession::jti`] of the session the token belongs to.
+    pub jti: Uuid,
     /// JWT timestamp the token was created.
     pub iat: u64,
     /// JWT timestamp the token expires at.
     pub exp: u64,
     /// Token issuer.
     pub iss: Cow<'a, str>,
+    /// JWT timestamp the user signed in.
+    pub auth_time: u64,
 }
 
 /// Extracts a [`CtUser`] from a request, authenticated by a session token or an
@@ -101,6 +274,43 @@ pub struct TokenPayload<'a> {
 pub struct AuthenticatedUser {
     pub user: CtUser,
     pub source: AuthenticationSource,
+    /// The ID of the session the user authenticated with.  `None` for API
+    /// keys.
+    pub session_id: Option<i32>,
+    /// When the user signed in to the session, as a JWT timestamp.  `None` for
+    /// API keys.
+    pub authenticated_at: Option<u64>,
+    /// The API key the user authenticated with.  `None` for session tokens.
+    pub api_key: Option<ApiKeyGrant>,
+}
+
+impl AuthenticatedUser {
+    /// The changes the request may make to the given tracker.
+    pub fn scopes_for(&self, ap_tracker_id: i32) -> ApiKeyScopes {
+        self.api_key
+            .as_ref()
+            .map_or(ApiKeyScopes::ALL, |k| k.scopes_for(ap_tracker_id))
+    }
+
+    /// Rejects requests made with API keys that may not change the user's
+    /// account.
+    ///
+    /// This guards changes that don't belong to a tracker, such as the user's
+    /// own settings.  Changes to trackers are checked by the tracker's policy.
+    pub fn authorize_account_update(&self) -> Result<(), ApiError> {
+        if self
+            .api_key
+            .as_ref()
+            .is_some_and(|k| !k.key.can_update_account)
+        {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "This API key may not change account settings.",
+            ));
+        }
+
+        Ok(())
+    }
 }
 
 /// Identifies the source of a user's authentication.
@@ -127,31 +337,81 @@ where
             .and_then(|v| v.strip_prefix("Bearer "))
             .ok_or(StatusCode::UNAUTHORIZED)?;
 
-        match bearer_token.parse() {
-            Ok(key) => {
+        match api_key::hash_bearer(bearer_token) {
+            Some(secret_hash) => {
+                let now = Utc::now();
+
+                // Unknown keys aren't cached, so that requests with random
+                // keys can't fill the cache.
+                let grant = match state.api_key_cache.get(&secret_hash).await {
+                    Some(grant) => grant,
+                    None => {
+                        let grant = load_api_key_grant(state, &secret_hash, now)
+                            .await
+                            .unexpected()?
+                            .ok_or(StatusCode::UNAUTHORIZED)?;
+
+                        state.api_key_cache.insert(secret_hash, grant.clone()).await;
+
+                        grant
+                    }
+                };
+
+                if grant.key.expires_at.is_some_and(|e| e <= now) {
+                    return Err(StatusCode::UNAUTHORIZED);
+                }
+
                 let user = state
                     .data_provider
                     .create_data_access()
                     .await
                     .unexpected()?
-                    .get_ct_user_by_api_key(key)
+                    .get_ct_user_by_id(grant.key.ct_user_id)
                     .await
-                    .ok()
-                    .flatten()
+                    .unexpected()?
                     .ok_or(StatusCode::UNAUTHORIZED)?;
 
                 Ok(Self {
                     user,
                     source: AuthenticationSource::ApiKey,
+                    session_id: None,
+                    authenticated_at: None,
+                    api_key: Some(grant),
                 })
             }
 
-            Err(_) => {
+            None => {
                 let token = state
                     .token_processor
                     .decode(bearer_token)
                     .map_err(|_| StatusCode::UNAUTHORIZED)?;
 
+                // Reject tokens of sessions that were revoked, even though the
+                // token itself has not expired yet.
+                // As with API keys, unknown sessions aren't cached.
+                let session = match state.session_cache.get(&token.jti).await {
+                    Some(session) => session,
+                    None => {
+                        let session = state
+                            .data_provider
+                            .create_data_access()
+                            .await
+                            .unexpected()?
+                            .get_ct_user_session_by_jti(token.jti)
+                            .await
+                            .unexpected()?
+                            .ok_or(StatusCode::UNAUTHORIZED)?;
+
+                        state.session_cache.insert(token.jti, session.clone()).await;
+
+                        session
+                    }
+                };
+
+                if session.ct_user_id != token.sub || session.revoked_at.is_some() {
+                    return Err(StatusCode::UNAUTHORIZED);
+                }
+
                 let user = state
                     .data_provider
                     .create_data_access()
@@ -165,12 +425,50 @@ where
                 Ok(Self {
                     user,
                     source: AuthenticationSource::SessionToken,
+                    session_id: Some(session.id),
+                    authenticated_at: Some(token.auth_time),
+                    api_key: None,
                 })
             }
         }
     }
 }
 
+/// Looks up the API key with the given secret hash and the trackers it is
+/// limited to, recording that the key was used.
+async fn load_api_key_grant<D>(
+    state: &AppState<D>,
+    secret_hash: &str,
+    now: DateTime<Utc>,
+) -> sqlx::Result<Option<ApiKeyGrant>>
+where
+    D: DataAccessProvider + Send + Sync,
+{
+    let mut db = state.data_provider.create_data_access().await?;
+
+    let Some(mut key) = db.get_api_key_by_secret_hash(secret_hash).await? else {
+        return Ok(None);
+    };
+
+    let ap_tracker_ids = if key.all_trackers {
+        vec![]
+    } else {
+        db.get_ap_trackers_by_api_key_id(key.id)
+            .map_ok(|t| t.id)
+            .try_collect()
+            .await?
+    };
+
+    key.last_used_at = Some(now);
+
+    let key = db.update_api_key(key, &[ApiKeyIden::LastUsedAt]).await?;
+
+    Ok(key.map(|key| ApiKeyGrant {
+        key,
+        ap_tracker_ids,
+    }))
+}
+
 impl<D> OptionalFromRequestParts<Arc<AppState<D>>> for AuthenticatedUser
 where
     D: DataAccessProvider + Send + Sync,
@@ -218,3 +516,39 @@ where
         }
     }
 }
+
+/// Extracts a [`CtUser`] from a request, authenticated by a session token of a
+/// session that was signed in to within the last
+/// [`REAUTHENTICATION_WINDOW_SECS`].
+///
+/// This guards sensitive account changes.  Extraction fails with
+/// [`StatusCode::FORBIDDEN`] if the user authenticated with an API key or
+/// signed in too long ago, in which case they must sign in again.
+#[derive(Debug, Clone)]
+pub struct RecentlyAuthenticatedUser(pub AuthenticatedUser);
+
+impl<S> FromRequestParts<S> for RecentlyAuthenticatedUser
+where
+    AuthenticatedUser: FromRequestParts<S, Rejection = StatusCode>,
+    S: Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let user =
+            <AuthenticatedUser as FromRequestParts<S>>::from_request_parts(parts, state).await?;
+
+        match user.authenticated_at {
+            Some(auth_time)
+                if auth_time + REAUTHENTICATION_WINDOW_SECS
+                    >= jsonwebtoken::get_current_timestamp() =>
+            {
+                Ok(Self(user))
+            }
+            _ => Err(StatusCode::FORBIDDEN),
+        }
+    }
+}
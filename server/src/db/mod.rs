@@ -9,6 +9,7 @@ use std::{future::Future, net::IpAddr};
 use chrono::{DateTime, Utc};
 use futures::Stream;
 use sea_query::Iden;
+use serde::Serialize;
 use sqlx::migrate::MigrateError;
 
 use uuid::Uuid;
@@ -28,7 +29,7 @@ use model::*;
 
 use crate::{
     auth::token::AuthenticatedUser,
-    diff::{IntoFieldwiseDiff, IsEmpty},
+    diff::{FieldDiff, IntoFieldwiseDiff, IsEmpty},
 };
 
 /// Provides access to the database.
@@ -78,6 +79,12 @@ pub trait DataAccess {
         upstream_url: &str,
     ) -> impl Future<Output = sqlx::Result<Option<ApTracker>>> + Send;
 
+    /// Gets all [`ApTracker`]s with the given database IDs.
+    fn get_trackers_by_ids(
+        &mut self,
+        ids: &[i32],
+    ) -> impl Stream<Item = sqlx::Result<ApTracker>> + Send;
+
     /// Creates one or more new [`ApTracker`]s in the database.
     ///
     /// The `id` field of the values is ignored.  It will be populated with the
@@ -116,12 +123,46 @@ pub trait DataAccess {
         tracker_id: i32,
     ) -> impl Stream<Item = sqlx::Result<ApGame>> + Send;
 
+    /// Gets all [`ApGame`]s with the given IDs.
+    fn get_ap_games_by_ids(
+        &mut self,
+        ids: &[i32],
+    ) -> impl Stream<Item = sqlx::Result<ApGame>> + Send;
+
+    /// Gets all [`ApGame`]s claimed or co-claimed by a user, across all
+    /// trackers.
+    fn get_ap_games_claimed_by_user(
+        &mut self,
+        ct_user_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<ApGame>> + Send;
+
     /// Gets all of the [`ApHint`]s for a tracker by the tracker's ID.
     fn get_ap_hints_by_tracker_id(
         &mut self,
         tracker_id: i32,
     ) -> impl Stream<Item = sqlx::Result<ApHint>> + Send;
 
+    /// Gets all [`ApHint`]s where any of the given games is the finder or the
+    /// receiver.
+    ///
+    /// Found hints are only included if `include_found` is set.  If
+    /// `updated_after` is given, only hints changed after that time are
+    /// included.
+    fn get_ap_hints_by_game_ids(
+        &mut self,
+        game_ids: &[i32],
+        include_found: bool,
+        updated_after: Option<DateTime<Utc>>,
+    ) -> impl Stream<Item = sqlx::Result<ApHint>> + Send;
+
+    /// Gets the IDs of the given games whose claimant or co-claimants changed
+    /// after the given time, according to their audits.
+    fn get_ap_game_ids_with_claim_changes(
+        &mut self,
+        game_ids: &[i32],
+        changed_after: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<i32>> + Send;
+
     /// Gets an [`ApHint`] by its database ID.
     fn get_ap_hint(
         &mut self,
@@ -166,6 +207,19 @@ pub trait DataAccess {
         columns: &[ApGameIden],
     ) -> impl Future<Output = sqlx::Result<Option<ApGame>>> + Send;
 
+    /// Updates an existing [`ApGame`] like
+    /// [`update_ap_game`](Self::update_ap_game), but only if the game is still
+    /// claimed by the given user ID and unauthenticated username.
+    ///
+    /// Returns `None` if the game doesn't exist or its claim has changed.
+    fn update_ap_game_if_claimed_by(
+        &mut self,
+        game: ApGame,
+        columns: &[ApGameIden],
+        claimed_by_ct_user_id: Option<i32>,
+        discord_username: Option<&str>,
+    ) -> impl Future<Output = sqlx::Result<Option<ApGame>>> + Send;
+
     /// Creates one or more new [`ApHint`]s in the database.
     ///
     /// The `id` field of the values is ignored.  It will be populated with the
@@ -212,17 +266,13 @@ pub trait DataAccess {
         id: i32,
     ) -> impl Future<Output = sqlx::Result<Option<CtUser>>> + Send;
 
-    /// Gets a [`CtUser`] by its `discord_user_id` field.
-    fn get_ct_user_by_discord_user_id(
-        &mut self,
-        discord_user_id: i64,
-    ) -> impl Future<Output = sqlx::Result<Option<CtUser>>> + Send;
-
-    /// Gets a [`CtUser`] by its `api_key` field.
-    fn get_ct_user_by_api_key(
+    /// Gets all [`CtUser`]s with the given IDs.
+    ///
+    /// IDs that don't exist are ignored.
+    fn get_ct_users_by_ids(
         &mut self,
-        api_key: Uuid,
-    ) -> impl Future<Output = sqlx::Result<Option<CtUser>>> + Send;
+        ids: &[i32],
+    ) -> impl Stream<Item = sqlx::Result<CtUser>> + Send;
 
     /// Creates one or more new [`CtUser`]s in the database.
     ///
@@ -256,6 +306,206 @@ pub trait DataAccess {
         columns: &[CtUserIden],
     ) -> impl Future<Output = sqlx::Result<Option<CtUser>>> + Send;
 
+    /// Marks users whose scheduled away period has started as away.
+    ///
+    /// Returns the number of users updated.
+    fn begin_scheduled_away_periods(
+        &mut self,
+        now: DateTime<Utc>,
+    ) -> impl Future<Output = sqlx::Result<u64>> + Send;
+
+    /// Marks users whose away period has ended as back, clearing their away
+    /// schedule and message.
+    ///
+    /// Returns the number of users updated.
+    fn end_scheduled_away_periods(
+        &mut self,
+        now: DateTime<Utc>,
+    ) -> impl Future<Output = sqlx::Result<u64>> + Send;
+
+    /// Gets the [`CtUserIdentity`] for an account at a login provider.
+    fn get_ct_user_identity(
+        &mut self,
+        provider: &str,
+        subject: &str,
+    ) -> impl Future<Output = sqlx::Result<Option<CtUserIdentity>>> + Send;
+
+    /// Gets all [`CtUserIdentity`]s linked to a user, in the order they were
+    /// linked.
+    fn get_ct_user_identities_by_user_id(
+        &mut self,
+        ct_user_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<CtUserIdentity>> + Send;
+
+    /// Creates one or more new [`CtUserIdentity`]s in the database.
+    ///
+    /// The `id` field of the value is ignored.  It will be populated with the
+    /// real IDs in the returned values.
+    fn create_ct_user_identities<'s, 'v, 'f>(
+        &'s mut self,
+        identities: impl IntoIterator<Item = CtUserIdentityInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<CtUserIdentity>> + Send + 'f
+    where
+        's: 'f,
+        'v: 'f;
+
+    /// Updates an existing [`CtUserIdentity`].
+    ///
+    /// If an existing identity is found, this function will return the new
+    /// record in `Some`, otherwise it will return `None`.
+    fn update_ct_user_identity(
+        &mut self,
+        identity: CtUserIdentity,
+        columns: &[CtUserIdentityIden],
+    ) -> impl Future<Output = sqlx::Result<Option<CtUserIdentity>>> + Send;
+
+    /// Deletes a [`CtUserIdentity`] linked to a user.
+    ///
+    /// Returns the deleted identity, or `None` if no such identity is linked
+    /// to the user.
+    fn delete_ct_user_identity(
+        &mut self,
+        ct_user_id: i32,
+        id: i32,
+    ) -> impl Future<Output = sqlx::Result<Option<CtUserIdentity>>> + Send;
+
+    /// Creates one or more new [`CtUserSession`]s in the database.
+    ///
+    /// The `id` field of the value is ignored.  It will be populated with the
+    /// real IDs in the returned values.
+    fn create_ct_user_sessions<'s, 'v, 'f>(
+        &'s mut self,
+        sessions: impl IntoIterator<Item = CtUserSessionInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<CtUserSession>> + Send + 'f
+    where
+        's: 'f,
+        'v: 'f;
+
+    /// Gets a [`CtUserSession`] by its `jti` field.
+    fn get_ct_user_session_by_jti(
+        &mut self,
+        jti: Uuid,
+    ) -> impl Future<Output = sqlx::Result<Option<CtUserSession>>> + Send;
+
+    /// Gets a [`CtUserSession`] by the hash of its current refresh token.
+    fn get_ct_user_session_by_refresh_token_hash(
+        &mut self,
+        refresh_token_hash: &str,
+    ) -> impl Future<Output = sqlx::Result<Option<CtUserSession>>> + Send;
+
+    /// Gets a user's sessions that are neither revoked nor expired as of
+    /// `now`, most recently used first.
+    fn get_active_ct_user_sessions_by_user_id(
+        &mut self,
+        ct_user_id: i32,
+        now: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<CtUserSession>> + Send;
+
+    /// Updates an existing [`CtUserSession`] if it has not been revoked and its
+    /// refresh token hash is still `refresh_token_hash`.
+    ///
+    /// This prevents the same refresh token from being used twice.  If the
+    /// session is updated, this function will return the new record in
+    /// `Some`, otherwise it will return `None`.
+    fn refresh_ct_user_session(
+        &mut self,
+        session: CtUserSession,
+        refresh_token_hash: &str,
+        columns: &[CtUserSessionIden],
+    ) -> impl Future<Output = sqlx::Result<Option<CtUserSession>>> + Send;
+
+    /// Revokes a user's sessions that haven't been revoked yet.
+    ///
+    /// If `id` is `Some`, only the session with that ID is revoked.  Returns
+    /// the revoked sessions.
+    fn revoke_ct_user_sessions(
+        &mut self,
+        ct_user_id: i32,
+        id: Option<i32>,
+        revoked_at: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<CtUserSession>> + Send;
+
+    /// Creates one or more new [`ApiKey`]s in the database.
+    ///
+    /// The `id` field of the value is ignored.  It will be populated with the
+    /// real IDs in the returned values.
+    fn create_api_keys<'s, 'v, 'f>(
+        &'s mut self,
+        keys: impl IntoIterator<Item = ApiKeyInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<ApiKey>> + Send + 'f
+    where
+        's: 'f,
+        'v: 'f;
+
+    /// Gets an [`ApiKey`] by the hash of its secret.
+    fn get_api_key_by_secret_hash(
+        &mut self,
+        secret_hash: &str,
+    ) -> impl Future<Output = sqlx::Result<Option<ApiKey>>> + Send;
+
+    /// Gets all of a user's [`ApiKey`]s, oldest first.
+    fn get_api_keys_by_user_id(
+        &mut self,
+        ct_user_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<ApiKey>> + Send;
+
+    /// Updates an existing [`ApiKey`].
+    ///
+    /// If an existing key is found, this function will return the new record
+    /// in `Some`, otherwise it will return `None`.
+    fn update_api_key(
+        &mut self,
+        key: ApiKey,
+        columns: &[ApiKeyIden],
+    ) -> impl Future<Output = sqlx::Result<Option<ApiKey>>> + Send;
+
+    /// Deletes one of a user's [`ApiKey`]s.
+    ///
+    /// Returns the deleted key, or `None` if the user has no such key.
+    fn delete_api_key(
+        &mut self,
+        ct_user_id: i32,
+        id: i32,
+    ) -> impl Future<Output = sqlx::Result<Option<ApiKey>>> + Send;
+
+    /// Limits API keys to the given trackers.
+    fn create_api_key_trackers(
+        &mut self,
+        trackers: impl IntoIterator<Item = ApiKeyTracker> + Send,
+    ) -> impl Future<Output = sqlx::Result<()>> + Send;
+
+    /// Gets the trackers an [`ApiKey`] is limited to.
+    fn get_ap_trackers_by_api_key_id(
+        &mut self,
+        api_key_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<ApTracker>> + Send;
+
+    /// Gets a user's claim preferences for a game.
+    fn get_ct_user_game_preference(
+        &mut self,
+        ct_user_id: i32,
+        game: &str,
+    ) -> impl Future<Output = sqlx::Result<Option<CtUserGamePreference>>> + Send;
+
+    /// Gets all of a user's per-game claim preferences.
+    fn get_ct_user_game_preferences_by_user_id(
+        &mut self,
+        ct_user_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<CtUserGamePreference>> + Send;
+
+    /// Sets a user's claim preferences for a game.
+    fn upsert_ct_user_game_preference(
+        &mut self,
+        preference: CtUserGamePreference,
+    ) -> impl Future<Output = sqlx::Result<()>> + Send;
+
+    /// Deletes a user's claim preferences for a game.
+    fn delete_ct_user_game_preference(
+        &mut self,
+        ct_user_id: i32,
+        game: &str,
+    ) -> impl Future<Output = sqlx::Result<Option<CtUserGamePreference>>> + Send;
+
     /// Creates one or more new [`JsError`]s in the database.
     ///
     /// The `id` field of the value is ignored.  It will be populated with the
@@ -274,6 +524,61 @@ pub trait DataAccess {
         user_id: i32,
     ) -> impl Stream<Item = sqlx::Result<ApTrackerDashboard>> + Send;
 
+    /// Gets aggregated counts of a tracker's slots.
+    fn get_tracker_slot_stats(
+        &mut self,
+        tracker_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<TrackerSlotStats>> + Send;
+
+    /// Gets aggregated counts of a tracker's hints.
+    fn get_tracker_hint_stats(
+        &mut self,
+        tracker_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<TrackerHintStats>> + Send;
+
+    /// Gets aggregated counts of the unfound hints that each slot claimed or
+    /// co-claimed by a user needs to find.
+    fn get_user_game_hint_stats(
+        &mut self,
+        ct_user_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<UserGameHintStats>> + Send;
+
+    /// Gets aggregated totals of a tracker's slots for each game.
+    fn get_tracker_game_stats(
+        &mut self,
+        tracker_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<TrackerGameStats>> + Send;
+
+    /// Gets the claimed slots that have crossed one of their tracker's
+    /// inactivity thresholds and are due for an alert.
+    ///
+    /// Slots that were already alerted for the crossed threshold are only
+    /// included if they have had activity since, and the previous alert was
+    /// raised before `alerted_before`.  Trackers that were last updated before
+    /// `updated_after` are skipped.
+    fn get_pending_inactivity_alerts(
+        &mut self,
+        alerted_before: DateTime<Utc>,
+        updated_after: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<PendingInactivityAlert>> + Send;
+
+    /// Records that an inactivity alert was raised, replacing any previous
+    /// record for the same game and threshold.
+    fn upsert_inactivity_alert(
+        &mut self,
+        alert: InactivityAlert,
+    ) -> impl Future<Output = sqlx::Result<()>> + Send;
+
+    /// Gets claimed slots that have had no activity or check-in for longer
+    /// than their tracker's claim expiry period as of `as_of`.
+    ///
+    /// Trackers that were last updated before `updated_after` are skipped.
+    fn get_expired_claims(
+        &mut self,
+        as_of: DateTime<Utc>,
+        updated_after: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<ExpiredClaim>> + Send;
+
     /// Get a dashboard override.
     fn get_ap_tracker_dashboard_override(
         &mut self,
@@ -294,6 +599,203 @@ pub trait DataAccess {
         ap_tracker_id: i32,
     ) -> impl Future<Output = sqlx::Result<Option<ApTrackerDashboardOverride>>> + Send;
 
+    /// Gets the membership of a user in a tracker.
+    fn get_ap_tracker_member(
+        &mut self,
+        ct_user_id: i32,
+        ap_tracker_id: i32,
+    ) -> impl Future<Output = sqlx::Result<Option<ApTrackerMember>>> + Send;
+
+    /// Gets all members of a tracker.
+    fn get_ap_tracker_members_by_tracker_id(
+        &mut self,
+        ap_tracker_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<ApTrackerMember>> + Send;
+
+    /// Adds a member to a tracker, or changes the role of an existing member.
+    fn upsert_ap_tracker_member(
+        &mut self,
+        member: ApTrackerMember,
+    ) -> impl Future<Output = sqlx::Result<()>> + Send;
+
+    /// Removes a member from a tracker.
+    fn delete_ap_tracker_member(
+        &mut self,
+        ct_user_id: i32,
+        ap_tracker_id: i32,
+    ) -> impl Future<Output = sqlx::Result<Option<ApTrackerMember>>> + Send;
+
+    /// Gets the co-claimants of all games in a tracker.
+    fn get_ap_game_claimants_by_tracker_id(
+        &mut self,
+        tracker_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<ApGameClaimant>> + Send;
+
+    /// Gets the co-claimants of a slot.
+    fn get_ap_game_claimants_by_game_id(
+        &mut self,
+        ap_game_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<ApGameClaimant>> + Send;
+
+    /// Adds a co-claimant to a game, or changes the ping preference of an
+    /// existing co-claimant.
+    fn upsert_ap_game_claimant(
+        &mut self,
+        claimant: ApGameClaimant,
+    ) -> impl Future<Output = sqlx::Result<()>> + Send;
+
+    /// Removes all co-claimants from a game.
+    fn delete_ap_game_claimants_by_game_id(
+        &mut self,
+        ap_game_id: i32,
+    ) -> impl Future<Output = sqlx::Result<()>> + Send;
+
+    /// Removes a co-claimant from a game.
+    fn delete_ap_game_claimant(
+        &mut self,
+        ap_game_id: i32,
+        ct_user_id: i32,
+    ) -> impl Future<Output = sqlx::Result<Option<ApGameClaimant>>> + Send;
+
+    /// Gets the pending ownership transfer offer for a tracker.
+    fn get_ap_tracker_ownership_transfer(
+        &mut self,
+        ap_tracker_id: i32,
+    ) -> impl Future<Output = sqlx::Result<Option<ApTrackerOwnershipTransfer>>> + Send;
+
+    /// Creates one or more new [`ApTrackerOwnershipTransfer`]s in the
+    /// database.
+    ///
+    /// The `id` field of the value is ignored.  It will be populated with the
+    /// real IDs in the returned values.
+    fn create_ap_tracker_ownership_transfers<'s, 'v, 'f>(
+        &'s mut self,
+        transfers: impl IntoIterator<Item = ApTrackerOwnershipTransferInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<ApTrackerOwnershipTransfer>> + Send + 'f
+    where
+        's: 'f,
+        'v: 'f;
+
+    /// Deletes the pending ownership transfer offer for a tracker.
+    fn delete_ap_tracker_ownership_transfer(
+        &mut self,
+        ap_tracker_id: i32,
+    ) -> impl Future<Output = sqlx::Result<Option<ApTrackerOwnershipTransfer>>> + Send;
+
+    /// Changes the owner of a tracker from `from_ct_user_id` to
+    /// `to_ct_user_id`.
+    ///
+    /// The tracker is only updated if it is still owned by `from_ct_user_id`.
+    /// Returns the updated tracker, or `None` if it was not updated.
+    fn transfer_ap_tracker_ownership(
+        &mut self,
+        ap_tracker_id: i32,
+        from_ct_user_id: i32,
+        to_ct_user_id: i32,
+    ) -> impl Future<Output = sqlx::Result<Option<ApTracker>>> + Send;
+
+    /// Gets an [`ApGameClaimRequest`] by its ID.
+    fn get_ap_game_claim_request(
+        &mut self,
+        id: i32,
+    ) -> impl Future<Output = sqlx::Result<Option<ApGameClaimRequest>>> + Send;
+
+    /// Gets the pending [`ApGameClaimRequest`]s for all games in a tracker.
+    fn get_pending_ap_game_claim_requests_by_tracker_id(
+        &mut self,
+        tracker_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<ApGameClaimRequest>> + Send;
+
+    /// Creates one or more new [`ApGameClaimRequest`]s in the database.
+    ///
+    /// The `id` field of the value is ignored.  It will be populated with the
+    /// real IDs in the returned values.
+    fn create_ap_game_claim_requests<'s, 'v, 'f>(
+        &'s mut self,
+        requests: impl IntoIterator<Item = ApGameClaimRequestInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<ApGameClaimRequest>> + Send + 'f
+    where
+        's: 'f,
+        'v: 'f;
+
+    /// Updates an existing [`ApGameClaimRequest`], but only if it is still
+    /// pending.
+    ///
+    /// Returns `None` if the request doesn't exist or is no longer pending.
+    fn update_pending_ap_game_claim_request(
+        &mut self,
+        request: ApGameClaimRequest,
+        columns: &[ApGameClaimRequestIden],
+    ) -> impl Future<Output = sqlx::Result<Option<ApGameClaimRequest>>> + Send;
+
+    /// Denies all pending [`ApGameClaimRequest`]s to take over a game's claim.
+    /// Pending requests to join the claim as a co-claimant are left pending.
+    ///
+    /// Returns the requests that were denied.
+    fn deny_pending_ap_game_claim_requests_by_game_id(
+        &mut self,
+        ap_game_id: i32,
+        resolved_by_ct_user_id: i32,
+        resolved_at: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<ApGameClaimRequest>> + Send;
+
+    /// Creates new [`CtUserNotification`]s.
+    fn create_ct_user_notifications<'s, 'v, 'f>(
+        &'s mut self,
+        notifications: impl IntoIterator<Item = CtUserNotificationInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<CtUserNotification>> + Send + 'f
+    where
+        's: 'f,
+        'v: 'f;
+
+    /// Gets a user's most recent [`CtUserNotification`]s, newest first.
+    ///
+    /// If `before_id` is given, only notifications older than that notification
+    /// are returned.
+    fn get_ct_user_notifications(
+        &mut self,
+        ct_user_id: i32,
+        before_id: Option<i32>,
+        limit: u64,
+    ) -> impl Stream<Item = sqlx::Result<CtUserNotification>> + Send;
+
+    /// Counts a user's unread notifications.
+    fn get_ct_user_unread_notification_count(
+        &mut self,
+        ct_user_id: i32,
+    ) -> impl Future<Output = sqlx::Result<i64>> + Send;
+
+    /// Marks a user's unread notifications as read at the given time.
+    ///
+    /// If `ids` is given, only those notifications are marked.  Returns the
+    /// number of notifications marked.
+    fn mark_ct_user_notifications_read(
+        &mut self,
+        ct_user_id: i32,
+        ids: Option<&[i32]>,
+        read_at: DateTime<Utc>,
+    ) -> impl Future<Output = sqlx::Result<u64>> + Send;
+
+    /// Gets the notification mutes of a tracker for any of the given users.
+    fn get_ap_tracker_notification_mutes(
+        &mut self,
+        ap_tracker_id: i32,
+        ct_user_ids: &[i32],
+    ) -> impl Stream<Item = sqlx::Result<ApTrackerNotificationMute>> + Send;
+
+    /// Mutes in-app notifications about a tracker for a user.
+    fn upsert_ap_tracker_notification_mute(
+        &mut self,
+        mute: ApTrackerNotificationMute,
+    ) -> impl Future<Output = sqlx::Result<()>> + Send;
+
+    /// Unmutes in-app notifications about a tracker for a user.
+    fn delete_ap_tracker_notification_mute(
+        &mut self,
+        ct_user_id: i32,
+        ap_tracker_id: i32,
+    ) -> impl Future<Output = sqlx::Result<Option<ApTrackerNotificationMute>>> + Send;
+
     /// Creates one or more new [`Audit`]s in the database.
     ///
     /// The `id` field of the value is ignored.  It will be populated with the
@@ -306,12 +808,51 @@ pub trait DataAccess {
         's: 'f,
         'v: 'f;
 
-    /// Get all [`Audit`]s for a tracker that don't have an actor ID (e.g. were
-    /// performed by the system) and sorted chronologically.
-    fn get_system_audits_for_tracker(
+    /// Creates one or more new [`ChecksHistory`]s in the database.
+    ///
+    /// The `id` field of the value is ignored.  It will be populated with the
+    /// real IDs in the returned values.
+    fn create_checks_history<'s, 'v, 'f>(
+        &'s mut self,
+        entries: impl IntoIterator<Item = ChecksHistoryInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<ChecksHistory>> + Send + 'f
+    where
+        's: 'f,
+        'v: 'f;
+
+    /// Gets the [`ChecksHistory`] entries for a tracker, sorted
+    /// chronologically.
+    ///
+    /// If `ap_game_ids` is `Some`, only entries for those games are returned.
+    /// If `from` and/or `until` are `Some`, only entries that were recorded at
+    /// or after `from` and before `until` are returned.
+    fn get_checks_history_by_tracker_id(
+        &mut self,
+        ap_tracker_id: i32,
+        ap_game_ids: Option<&[i32]>,
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> impl Stream<Item = sqlx::Result<ChecksHistory>> + Send;
+
+    /// Gets the most recent [`ChecksHistory`] entry for each game in a tracker
+    /// that was recorded before the given time.
+    ///
+    /// Games that have no entries before that time are omitted.
+    fn get_last_checks_history_before(
+        &mut self,
+        ap_tracker_id: i32,
+        before: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<ChecksHistory>> + Send;
+
+    /// Gets the earliest [`ChecksHistory`] entry for each game in a tracker
+    /// that was recorded at or after the given time.
+    ///
+    /// Games that have no entries since that time are omitted.
+    fn get_first_checks_history_since(
         &mut self,
         ap_tracker_id: i32,
-    ) -> impl Stream<Item = sqlx::Result<Audit>> + Send;
+        since: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<ChecksHistory>> + Send;
 }
 
 pub fn create_audit_for<V>(
@@ -327,15 +868,66 @@ where
 {
     let diff = old.into_fieldwise_diff(new);
 
-    (!diff.is_empty()).then(|| AuditInsertion {
-        entity: V::table().to_string(),
-        entity_id: *old.primary_key_value(),
+    (!diff.is_empty()).then(|| {
+        create_audit(
+            actor_ipaddr,
+            actor_ct_user,
+            changed_at,
+            V::table().to_string(),
+            *old.primary_key_value(),
+            &diff,
+        )
+    })
+}
+
+/// Creates an audit record for a user joining or leaving a game as a
+/// co-claimant, or changing their ping preference.
+///
+/// Co-claimants have no ID of their own, so the record is attached to the
+/// game.  A ping preference of `None` means that the user is not a
+/// co-claimant.
+pub fn create_co_claimant_audit(
+    actor_ipaddr: Option<IpAddr>,
+    actor_ct_user: Option<&AuthenticatedUser>,
+    changed_at: DateTime<Utc>,
+    ap_game_id: i32,
+    ct_user_id: i32,
+    old_discord_ping: Option<PingPreference>,
+    new_discord_ping: Option<PingPreference>,
+) -> Option<AuditInsertion> {
+    let diff = FieldDiff::new(old_discord_ping, new_discord_ping)?;
+
+    Some(create_audit(
+        actor_ipaddr,
+        actor_ct_user,
+        changed_at,
+        ApGameClaimantIden::Table.to_string(),
+        ap_game_id,
+        &serde_json::json!({
+            "ct_user_id": ct_user_id,
+            "discord_ping": diff,
+        }),
+    ))
+}
+
+fn create_audit(
+    actor_ipaddr: Option<IpAddr>,
+    actor_ct_user: Option<&AuthenticatedUser>,
+    changed_at: DateTime<Utc>,
+    entity: String,
+    entity_id: i32,
+    diff: &impl Serialize,
+) -> AuditInsertion {
+    AuditInsertion {
+        entity,
+        entity_id,
         changed_at,
         actor_ipaddr: actor_ipaddr.map(Into::into),
         actor_ct_user_id: actor_ct_user.map(|i| i.user.id),
         auth_source: actor_ct_user.map(|i| i.source.into()),
-        diff: serde_json::to_string(&diff).unwrap(),
-    })
+        actor_api_key_id: actor_ct_user.and_then(|i| i.api_key.as_ref().map(|k| k.key.id)),
+        diff: serde_json::to_string(diff).unwrap(),
+    }
 }
 
 /// Build values using a closure.
@@ -3,7 +3,7 @@
 use std::{fmt::Debug, hash::Hash};
 
 use cheese_trackers_server_macros::IntoFieldwiseDiff;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use ipnetwork::IpNetwork;
 use sea_query::{Iden, Nullable, Value};
 use serde::Serialize;
@@ -86,7 +86,7 @@ macro_rules! db_enum {
         }
     ) => {
         paste::paste! {
-            #[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, serde::Serialize, serde::Deserialize)]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, sqlx::Type, serde::Serialize, serde::Deserialize)]
             #[sqlx(type_name = $dbn, rename_all = "snake_case")]
             #[serde(rename_all = "snake_case")]
             #[doc = "Model for the database enum `"]
@@ -94,7 +94,7 @@ macro_rules! db_enum {
             #[doc = "`."]
             $nv enum $n {
                 $(
-                    $( #[ $fm:meta ] )*
+                    $( #[ $fm ] )*
                     $variant
                 ),*
             }
@@ -234,6 +234,35 @@ impl From<crate::auth::token::AuthenticationSource> for AuthenticationSource {
     }
 }
 
+db_enum! {
+    pub enum InactivityColor as "inactivity_color" {
+        Green,
+        Yellow,
+        Red,
+    }
+}
+
+db_enum! {
+    pub enum TrackerRole as "tracker_role" {
+        /// The tracker's owner.  Each tracker has at most one owner, recorded
+        /// in [`ApTracker::owner_ct_user_id`].
+        Owner,
+        /// Can manage the tracker's settings and moderate its slots.
+        Organizer,
+        /// Has no additional permissions.
+        Viewer,
+    }
+}
+
+db_enum! {
+    pub enum ClaimRequestStatus as "claim_request_status" {
+        Pending,
+        Approved,
+        Denied,
+        Withdrawn,
+    }
+}
+
 /// Model for database table `ap_tracker`.
 #[sea_query::enum_def]
 #[derive(Debug, Clone, Model, ModelWithAutoPrimaryKey, FromRow, IntoFieldwiseDiff)]
@@ -257,6 +286,11 @@ pub struct ApTracker {
     pub inactivity_threshold_yellow_hours: i32,
     pub inactivity_threshold_red_hours: i32,
     pub require_authentication_to_claim: bool,
+    /// Claims on slots without activity or check-ins for this many days are
+    /// removed automatically.  `None` disables claim expiry.
+    pub claim_expiry_days: Option<i32>,
+    /// Whether to notify claimants when their claim expires.
+    pub claim_expiry_notify: bool,
 }
 
 // This is the result of a database function call.  There is no table backing
@@ -278,6 +312,83 @@ pub struct ApTrackerDashboard {
     pub upstream_url: String,
 }
 
+// The following are the results of database function calls.  There are no
+// tables backing these models.
+
+/// Aggregated counts of a tracker's slots, grouped by their statuses.
+#[derive(Debug, Clone, FromRow)]
+pub struct TrackerSlotStats {
+    pub completion_status: CompletionStatus,
+    pub progression_status: ProgressionStatus,
+    pub availability_status: AvailabilityStatus,
+    pub is_claimed: bool,
+    pub inactivity_color: InactivityColor,
+    pub slots: i64,
+}
+
+/// Aggregated counts of a tracker's hints, grouped by their classification and
+/// found state.
+#[derive(Debug, Clone, FromRow)]
+pub struct TrackerHintStats {
+    pub classification: HintClassification,
+    pub found: bool,
+    pub hints: i64,
+}
+
+/// Aggregated counts of the unfound hints that a user's claimed slots need to
+/// find, grouped by slot and classification.
+#[derive(Debug, Clone, FromRow)]
+pub struct UserGameHintStats {
+    pub ap_game_id: i32,
+    pub classification: HintClassification,
+    pub hints: i64,
+}
+
+/// Aggregated totals of a tracker's slots, grouped by game.
+#[derive(Debug, Clone, FromRow)]
+pub struct TrackerGameStats {
+    pub game: String,
+    pub slots: i64,
+    pub finished_slots: i64,
+    pub checks_done: i64,
+    pub checks_total: i64,
+}
+
+/// A claimed slot that has crossed one of its tracker's inactivity thresholds
+/// and is due for an alert.
+#[derive(Debug, Clone, FromRow)]
+pub struct PendingInactivityAlert {
+    pub tracker_id: Uuid,
+    pub tracker_title: String,
+    pub ap_game_id: i32,
+    pub game_name: String,
+    pub inactivity_color: InactivityColor,
+    pub threshold_hours: i32,
+    pub inactive_since: Option<DateTime<Utc>>,
+    pub claimant_ct_user_id: Option<i32>,
+    pub claimant_discord_username: String,
+    pub claimant_discord_user_id: Option<i64>,
+    pub owner_ct_user_id: Option<i32>,
+    pub owner_discord_username: Option<String>,
+    pub owner_discord_user_id: Option<i64>,
+}
+
+/// A claimed slot that has had no activity or check-in for longer than its
+/// tracker's claim expiry period.
+#[derive(Debug, Clone, FromRow)]
+pub struct ExpiredClaim {
+    pub tracker_id: Uuid,
+    pub tracker_title: String,
+    pub ap_game_id: i32,
+    pub game_name: String,
+    pub claim_expiry_days: i32,
+    pub claim_expiry_notify: bool,
+    pub inactive_since: DateTime<Utc>,
+    pub claimant_ct_user_id: Option<i32>,
+    pub claimant_discord_username: String,
+    pub claimant_discord_user_id: Option<i64>,
+}
+
 /// Model for database view `ap_game`.
 #[sea_query::enum_def]
 #[derive(Debug, Clone, Model, ModelWithAutoPrimaryKey, FromRow, IntoFieldwiseDiff, Serialize)]
@@ -304,14 +415,65 @@ pub struct ApGame {
     pub availability_status: AvailabilityStatus,
     pub completion_status: CompletionStatus,
     pub progression_status: ProgressionStatus,
+    /// Whether the tracker owner has locked the slot against edits by other
+    /// users.
+    pub locked: bool,
 
     // The following columns are computed in the ap_game view and can't be
     // changed.
+    /// The claimant's username, or the earliest co-claimant's username if the
+    /// slot has no claimant.
     #[serde(skip_serializing_if = "Option::is_none")]
     #[diff(skip)]
     pub effective_discord_username: Option<String>,
+    /// Whether all of the slot's claimants and co-claimants are away.
     #[diff(skip)]
     pub user_is_away: bool,
+    /// When the slot's claimants are away, the earliest time one of them is
+    /// expected back.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[diff(skip)]
+    pub user_away_until: Option<DateTime<Utc>>,
+    /// When the slot's claimants are away, the message they left for other
+    /// players.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[diff(skip)]
+    pub user_away_message: Option<String>,
+}
+
+impl ApGame {
+    /// The last time the slot had activity or was checked by its claimant.
+    pub fn last_active_at(&self) -> Option<DateTime<Utc>> {
+        self.last_activity.max(self.last_checked)
+    }
+
+    /// Determines the inactivity color of the slot using its tracker's
+    /// inactivity thresholds.
+    ///
+    /// This matches the `slot_inactivity_color` database function.
+    pub fn inactivity_color(&self, tracker: &ApTracker, now: DateTime<Utc>) -> InactivityColor {
+        if matches!(
+            self.completion_status,
+            CompletionStatus::Done | CompletionStatus::Released
+        ) {
+            return InactivityColor::Green;
+        }
+
+        let Some(last_active_at) = self.last_active_at() else {
+            return InactivityColor::Red;
+        };
+
+        let inactive_for = now - last_active_at;
+
+        if inactive_for >= TimeDelta::hours(tracker.inactivity_threshold_red_hours.into()) {
+            InactivityColor::Red
+        } else if inactive_for >= TimeDelta::hours(tracker.inactivity_threshold_yellow_hours.into())
+        {
+            InactivityColor::Yellow
+        } else {
+            InactivityColor::Green
+        }
+    }
 }
 
 /// Projection of a game used by [`UpdateCompletionStatus`].
@@ -394,6 +556,27 @@ pub struct ApHint {
     pub found: bool,
     pub classification: HintClassification,
     pub item_link_name: String,
+    pub notes: String,
+    /// The user who acknowledged the hint, such as the finder's claimant
+    /// indicating they intend to find the item.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acknowledged_by_ct_user_id: Option<i32>,
+    /// When the hint was first seen on the upstream tracker.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[diff(skip)]
+    pub created_at: Option<DateTime<Utc>>,
+    /// When the hint was first seen as found on the upstream tracker.
+    ///
+    /// This is `None` for hints that are not found, and for hints that were
+    /// already found when they were first seen.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[diff(skip)]
+    pub found_at: Option<DateTime<Utc>>,
+    /// When the hint was last changed, either on the upstream tracker or by a
+    /// user.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[diff(skip)]
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 /// Model for database table `ct_user`.
@@ -403,16 +586,26 @@ pub struct CtUser {
     #[model(primary_key)]
     pub id: i32,
     #[diff(skip)]
-    pub discord_access_token: String,
+    pub discord_access_token: Option<String>,
     #[diff(skip)]
-    pub discord_access_token_expires_at: DateTime<Utc>,
+    pub discord_access_token_expires_at: Option<DateTime<Utc>>,
     #[diff(skip)]
-    pub discord_refresh_token: String,
+    pub discord_refresh_token: Option<String>,
+    /// The user's display name.  Despite the name, this is not necessarily a
+    /// Discord username for users who signed in through another provider.
     pub discord_username: String,
-    pub discord_user_id: i64,
-    #[diff(skip)]
-    pub api_key: Option<Uuid>,
     pub is_away: bool,
+    /// When the user's scheduled away period starts.  Cleared once the user
+    /// has been marked as away.
+    pub away_from: Option<DateTime<Utc>>,
+    /// When the user's away period ends and they are marked as back.
+    pub away_until: Option<DateTime<Utc>>,
+    /// A message to other players while the user is away.
+    pub away_message: String,
+    /// The ping preference applied to slots the user claims.
+    pub default_ping_preference: Option<PingPreference>,
+    /// The availability status applied to slots the user claims.
+    pub default_availability_status: Option<AvailabilityStatus>,
 }
 
 // Manual implementation to omit tokens.
@@ -421,12 +614,112 @@ impl Debug for CtUser {
         f.debug_struct("CtUser")
             .field("id", &self.id)
             .field("discord_username", &self.discord_username)
-            .field("discord_user_id", &self.discord_user_id)
             .field("is_away", &self.is_away)
+            .field("away_from", &self.away_from)
+            .field("away_until", &self.away_until)
             .finish_non_exhaustive()
     }
 }
 
+/// Model for database table `ct_user_identity`.
+///
+/// Each row is an account at an external login provider that can be used to
+/// sign in as a user.
+#[sea_query::enum_def]
+#[derive(Debug, Clone, Model, ModelWithAutoPrimaryKey, FromRow)]
+pub struct CtUserIdentity {
+    #[model(primary_key)]
+    pub id: i32,
+    pub ct_user_id: i32,
+    /// The configured identifier of the login provider.
+    pub provider: String,
+    /// The provider's stable identifier for the account.
+    pub subject: String,
+    /// The account's display name at the provider, as of the last sign-in.
+    pub username: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Model for database table `ct_user_session`.
+///
+/// Each row is a signed-in session of a user, identified in its access tokens
+/// by `jti`.
+#[sea_query::enum_def]
+#[derive(Debug, Clone, Model, ModelWithAutoPrimaryKey, FromRow)]
+pub struct CtUserSession {
+    #[model(primary_key)]
+    pub id: i32,
+    pub ct_user_id: i32,
+    pub jti: Uuid,
+    /// Hash of the current refresh token.
+    pub refresh_token_hash: String,
+    /// When the user signed in.
+    pub created_at: DateTime<Utc>,
+    /// When the session was last refreshed.
+    pub last_used_at: DateTime<Utc>,
+    /// When the refresh token stops being accepted if it is not used.
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// The user agent of the client that last used the session.
+    pub user_agent: Option<String>,
+    /// The IP address of the client that last used the session.
+    pub ipaddr: Option<IpNetwork>,
+}
+
+/// Model for database table `api_key`.
+///
+/// Each row is a named API key of a user.  A key with none of the `can_update_*`
+/// scopes is read-only.
+#[sea_query::enum_def]
+#[derive(Debug, Clone, Model, ModelWithAutoPrimaryKey, FromRow)]
+pub struct ApiKey {
+    #[model(primary_key)]
+    pub id: i32,
+    pub ct_user_id: i32,
+    pub name: String,
+    /// Hash of the key's secret.
+    pub secret_hash: String,
+    /// Whether the key may claim and update slots.
+    pub can_update_games: bool,
+    /// Whether the key may update hints.
+    pub can_update_hints: bool,
+    /// Whether the key may change tracker settings, members and ownership.
+    pub can_update_trackers: bool,
+    /// Whether the key may change the user's settings, preferences and
+    /// notifications.
+    pub can_update_account: bool,
+    /// Whether the key may make changes to all trackers.  If false, the key is
+    /// limited to the trackers in `api_key_tracker`.
+    pub all_trackers: bool,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Model for database table `api_key_tracker`.
+///
+/// Each row is a tracker that an [`ApiKey`] without `all_trackers` may change.
+#[sea_query::enum_def]
+#[derive(Debug, Clone, Copy, Model, sqlx::FromRow)]
+pub struct ApiKeyTracker {
+    pub api_key_id: i32,
+    pub ap_tracker_id: i32,
+}
+
+/// Model for database table `ct_user_game_preference`.
+///
+/// Each row overrides a user's default claim preferences for slots of a game.
+#[sea_query::enum_def]
+#[derive(Debug, Clone, Model, serde::Serialize, sqlx::FromRow)]
+pub struct CtUserGamePreference {
+    pub ct_user_id: i32,
+    pub game: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discord_ping: Option<PingPreference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub availability_status: Option<AvailabilityStatus>,
+}
+
 /// Model for database table `js_error`.
 #[sea_query::enum_def]
 #[derive(Debug, Clone, Model, ModelWithAutoPrimaryKey, FromRow)]
@@ -451,6 +744,24 @@ pub struct Audit {
     pub actor_ct_user_id: Option<i32>,
     pub diff: String,
     pub auth_source: Option<AuthenticationSource>,
+    /// The API key the actor authenticated with, if any.
+    pub actor_api_key_id: Option<i32>,
+}
+
+/// Model for database table `checks_history`.
+///
+/// Each row records a change in the number of checks completed by a slot, as
+/// observed during a tracker update.
+#[sea_query::enum_def]
+#[derive(Debug, Clone, Model, ModelWithAutoPrimaryKey, FromRow)]
+pub struct ChecksHistory {
+    #[model(primary_key)]
+    pub id: i32,
+    pub ap_tracker_id: i32,
+    pub ap_game_id: i32,
+    pub changed_at: DateTime<Utc>,
+    pub previous_checks_done: i32,
+    pub checks_done: i32,
 }
 
 // TODO: Implement composite primary key support on Model.
@@ -463,3 +774,106 @@ pub struct ApTrackerDashboardOverride {
     pub ap_tracker_id: i32,
     pub visibility: bool,
 }
+
+/// Model for database table `ap_tracker_member`.
+///
+/// The tracker owner is not stored in this table.
+#[sea_query::enum_def]
+#[derive(Debug, Clone, Copy, Model, serde::Serialize, sqlx::FromRow)]
+pub struct ApTrackerMember {
+    pub ct_user_id: i32,
+    pub ap_tracker_id: i32,
+    pub role: TrackerRole,
+}
+
+/// Model for database table `ap_game_claimant`.
+///
+/// Each row is a co-claimant of a slot, in addition to the claimant recorded in
+/// the [`ApGame`] itself.
+#[sea_query::enum_def]
+#[derive(Debug, Clone, Copy, Model, serde::Serialize, sqlx::FromRow)]
+pub struct ApGameClaimant {
+    pub ap_game_id: i32,
+    pub ct_user_id: i32,
+    pub discord_ping: PingPreference,
+    pub claimed_at: DateTime<Utc>,
+}
+
+/// Model for database table `ap_tracker_ownership_transfer`.
+///
+/// Each row is a pending offer by the owner of a tracker to transfer ownership
+/// to another user.
+#[sea_query::enum_def]
+#[derive(Debug, Clone, Model, ModelWithAutoPrimaryKey, FromRow)]
+pub struct ApTrackerOwnershipTransfer {
+    #[model(primary_key)]
+    pub id: i32,
+    pub ap_tracker_id: i32,
+    pub from_ct_user_id: i32,
+    pub to_ct_user_id: i32,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Model for database table `ap_game_claim_request`.
+///
+/// Each row is a request by a user to take over the claim on a slot.
+#[sea_query::enum_def]
+#[derive(Debug, Clone, Model, ModelWithAutoPrimaryKey, FromRow, Serialize)]
+pub struct ApGameClaimRequest {
+    #[model(primary_key)]
+    pub id: i32,
+    pub ap_game_id: i32,
+    pub requested_by_ct_user_id: i32,
+    pub message: String,
+    pub status: ClaimRequestStatus,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_by_ct_user_id: Option<i32>,
+    /// Whether the user asks to join the claimant as a co-claimant instead of
+    /// taking over the claim.
+    pub co_claim: bool,
+}
+
+/// Model for database table `ct_user_notification`.
+///
+/// Each row is an in-app notification about an event that concerns a user.
+#[sea_query::enum_def]
+#[derive(Debug, Clone, Model, ModelWithAutoPrimaryKey, FromRow)]
+pub struct CtUserNotification {
+    #[model(primary_key)]
+    pub id: i32,
+    pub ct_user_id: i32,
+    /// The tracker the event happened in, if any.
+    pub ap_tracker_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+    /// The [`Event`](crate::notify::Event) serialized as JSON.
+    pub event: String,
+    /// Human-readable description of the event.
+    pub message: String,
+}
+
+/// Model for database table `ap_tracker_notification_mute`.
+///
+/// Each row is a tracker that a user does not want in-app notifications about.
+#[sea_query::enum_def]
+#[derive(Debug, Clone, Copy, Model, sqlx::FromRow)]
+pub struct ApTrackerNotificationMute {
+    pub ct_user_id: i32,
+    pub ap_tracker_id: i32,
+}
+
+/// Model for database table `inactivity_alert`.
+///
+/// Each row records the last time an alert was raised for a slot crossing one
+/// of its tracker's inactivity thresholds.
+#[sea_query::enum_def]
+#[derive(Debug, Clone, Copy, Model, FromRow)]
+pub struct InactivityAlert {
+    pub ap_game_id: i32,
+    pub inactivity_color: InactivityColor,
+    pub alerted_at: DateTime<Utc>,
+}
@@ -1,6 +1,7 @@
 use std::{collections::HashMap, future::Future, marker::PhantomData};
 
 use async_stream::stream;
+use chrono::{DateTime, Utc};
 use futures::Stream;
 use sea_query::{
     Alias, Asterisk, Expr, Func, Iden, OnConflict, Order, PostgresQueryBuilder, Query, SimpleExpr,
@@ -149,6 +150,27 @@ where
     }
 }
 
+/// Selects all rows returned by a set-returning database function.
+fn pg_select_from_function<'a, T>(
+    executor: &'a mut PgConnection,
+    function: &'static str,
+    args: impl IntoIterator<Item = SimpleExpr>,
+) -> impl Stream<Item = sqlx::Result<T>> + 'a
+where
+    T: for<'b> FromRow<'b, PgRow> + Send + Unpin + 'a,
+{
+    let (sql, values) = Query::select()
+        .column(Asterisk)
+        .from_function(Func::cust(Alias::new(function)).args(args), Alias::new("t"))
+        .build_sqlx(PostgresQueryBuilder);
+
+    stream! {
+        for await row in sqlx::query_as_with(&sql, values).fetch(executor) {
+            yield row;
+        }
+    }
+}
+
 /// Deletes a row from the database by its integer primary key.
 async fn pg_delete<T>(executor: &mut PgConnection, id: i32) -> sqlx::Result<Option<T>>
 where
@@ -183,6 +205,24 @@ async fn pg_update<T>(
     value: T,
     columns: &[T::Iden],
 ) -> sqlx::Result<Option<T>>
+where
+    T: ModelWithAutoPrimaryKey + for<'a> FromRow<'a, PgRow> + Send + Unpin,
+    T::PrimaryKey: Into<sea_query::Value>,
+{
+    pg_update_if(executor, value, columns, None).await
+}
+
+/// Updates a row in the database like [`pg_update`], but only if the existing
+/// row also matches `condition`.
+///
+/// This allows atomically checking that the row hasn't changed since it was
+/// read.  Returns `None` if the row doesn't exist or doesn't match.
+async fn pg_update_if<T>(
+    executor: &mut PgConnection,
+    value: T,
+    columns: &[T::Iden],
+    condition: Option<SimpleExpr>,
+) -> sqlx::Result<Option<T>>
 where
     T: ModelWithAutoPrimaryKey + for<'a> FromRow<'a, PgRow> + Send + Unpin,
     T::PrimaryKey: Into<sea_query::Value>,
@@ -216,6 +256,7 @@ where
             )
         }))
         .and_where(Expr::col(T::primary_key()).eq(key))
+        .and_where_option(condition)
         .returning_all()
         .build_sqlx(PostgresQueryBuilder);
 
@@ -224,6 +265,15 @@ where
         .await
 }
 
+/// The pending [`ClaimRequestStatus`] as an expression that can be compared to
+/// the `claim_request_status` enum.
+///
+/// Enum values are bound as text, and there is no implicit cast from text to
+/// an enum in comparisons.
+fn pending_claim_request_status() -> SimpleExpr {
+    Expr::val(ClaimRequestStatus::Pending).as_enum(Alias::new("claim_request_status"))
+}
+
 impl<T: AsMut<<Postgres as sqlx::Database>::Connection> + Send> DataAccess for PgDataAccess<T> {
     fn get_tracker_by_tracker_id(
         &mut self,
@@ -245,6 +295,16 @@ impl<T: AsMut<<Postgres as sqlx::Database>::Connection> + Send> DataAccess for P
         )
     }
 
+    fn get_trackers_by_ids(
+        &mut self,
+        ids: &[i32],
+    ) -> impl Stream<Item = sqlx::Result<ApTracker>> + Send {
+        pg_select_many(
+            self.0.as_mut(),
+            Expr::col(ApTrackerIden::Id).is_in(ids.iter().copied()),
+        )
+    }
+
     fn create_ap_trackers<'s, 'v, 'f>(
         &'s mut self,
         trackers: impl IntoIterator<Item = ApTrackerInsertion> + Send + 'v,
@@ -274,6 +334,34 @@ impl<T: AsMut<<Postgres as sqlx::Database>::Connection> + Send> DataAccess for P
         )
     }
 
+    fn get_ap_games_by_ids(
+        &mut self,
+        ids: &[i32],
+    ) -> impl Stream<Item = sqlx::Result<ApGame>> + Send {
+        pg_select_many(
+            self.0.as_mut(),
+            Expr::col(ApGameIden::Id).is_in(ids.iter().copied()),
+        )
+    }
+
+    fn get_ap_games_claimed_by_user(
+        &mut self,
+        ct_user_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<ApGame>> + Send {
+        pg_select_many(
+            self.0.as_mut(),
+            Expr::col(ApGameIden::ClaimedByCtUserId)
+                .eq(ct_user_id)
+                .or(
+                    Expr::col(ApGameIden::Id).in_subquery(Query::select().build_with(|q| {
+                        q.column(ApGameClaimantIden::ApGameId)
+                            .from(ApGameClaimantIden::Table)
+                            .and_where(Expr::col(ApGameClaimantIden::CtUserId).eq(ct_user_id));
+                    })),
+                ),
+        )
+    }
+
     fn get_ap_hints_by_tracker_id(
         &mut self,
         tracker_id: i32,
@@ -296,6 +384,56 @@ impl<T: AsMut<<Postgres as sqlx::Database>::Connection> + Send> DataAccess for P
         }
     }
 
+    fn get_ap_hints_by_game_ids(
+        &mut self,
+        game_ids: &[i32],
+        include_found: bool,
+        updated_after: Option<DateTime<Utc>>,
+    ) -> impl Stream<Item = sqlx::Result<ApHint>> + Send {
+        let mut condition = Expr::col(ApHintIden::FinderGameId)
+            .is_in(game_ids.iter().copied())
+            .or(Expr::col(ApHintIden::ReceiverGameId).is_in(game_ids.iter().copied()));
+
+        if !include_found {
+            condition = condition.and(Expr::col(ApHintIden::Found).eq(false));
+        }
+
+        if let Some(updated_after) = updated_after {
+            condition = condition.and(Expr::col(ApHintIden::UpdatedAt).gt(updated_after));
+        }
+
+        pg_select_many(self.0.as_mut(), condition)
+    }
+
+    fn get_ap_game_ids_with_claim_changes(
+        &mut self,
+        game_ids: &[i32],
+        changed_after: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<i32>> + Send {
+        let (sql, values) = Query::select()
+            .distinct()
+            .column(AuditIden::EntityId)
+            .from(AuditIden::Table)
+            .and_where(Expr::col(AuditIden::EntityId).is_in(game_ids.iter().copied()))
+            .and_where(Expr::col(AuditIden::ChangedAt).gt(changed_after))
+            .and_where(
+                Expr::col(AuditIden::Entity)
+                    .eq(ApGameClaimantIden::Table.to_string())
+                    .or(Expr::col(AuditIden::Entity)
+                        .eq(ApGameIden::Table.to_string())
+                        .and(Expr::cust(
+                            "(diff::jsonb -> 'claimed_by_ct_user_id') IS NOT NULL",
+                        ))),
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        stream! {
+            for await row in sqlx::query_scalar_with(&sql, values).fetch(self.0.as_mut()) {
+                yield row;
+            }
+        }
+    }
+
     fn get_ap_hint(
         &mut self,
         hint_id: i32,
@@ -329,6 +467,31 @@ impl<T: AsMut<<Postgres as sqlx::Database>::Connection> + Send> DataAccess for P
         pg_update(self.0.as_mut(), game, columns)
     }
 
+    fn update_ap_game_if_claimed_by(
+        &mut self,
+        game: ApGame,
+        columns: &[ApGameIden],
+        claimed_by_ct_user_id: Option<i32>,
+        discord_username: Option<&str>,
+    ) -> impl Future<Output = sqlx::Result<Option<ApGame>>> + Send {
+        let claimed_by = match claimed_by_ct_user_id {
+            Some(id) => Expr::col(ApGameIden::ClaimedByCtUserId).eq(id),
+            None => Expr::col(ApGameIden::ClaimedByCtUserId).is_null(),
+        };
+
+        let username = match discord_username {
+            Some(name) => Expr::col(ApGameIden::DiscordUsername).eq(name),
+            None => Expr::col(ApGameIden::DiscordUsername).is_null(),
+        };
+
+        pg_update_if(
+            self.0.as_mut(),
+            game,
+            columns,
+            Some(claimed_by.and(username)),
+        )
+    }
+
     fn create_ap_hints<'s, 'v, 'f>(
         &'s mut self,
         hints: impl IntoIterator<Item = ApHintInsertion> + Send + 'v,
@@ -362,23 +525,16 @@ impl<T: AsMut<<Postgres as sqlx::Database>::Connection> + Send> DataAccess for P
         pg_select_one(self.0.as_mut(), Expr::col(CtUserIden::Id).eq(id))
     }
 
-    fn get_ct_user_by_discord_user_id(
+    fn get_ct_users_by_ids(
         &mut self,
-        discord_user_id: i64,
-    ) -> impl Future<Output = sqlx::Result<Option<CtUser>>> + Send {
-        pg_select_one(
+        ids: &[i32],
+    ) -> impl Stream<Item = sqlx::Result<CtUser>> + Send {
+        pg_select_many(
             self.0.as_mut(),
-            Expr::col(CtUserIden::DiscordUserId).eq(discord_user_id),
+            Expr::col(CtUserIden::Id).is_in(ids.iter().copied()),
         )
     }
 
-    fn get_ct_user_by_api_key(
-        &mut self,
-        api_key: uuid::Uuid,
-    ) -> impl Future<Output = sqlx::Result<Option<CtUser>>> + Send {
-        pg_select_one(self.0.as_mut(), Expr::col(CtUserIden::ApiKey).eq(api_key))
-    }
-
     fn create_ct_users<'s, 'v, 'f>(
         &'s mut self,
         users: impl IntoIterator<Item = CtUserInsertion> + Send + 'v,
@@ -398,49 +554,69 @@ impl<T: AsMut<<Postgres as sqlx::Database>::Connection> + Send> DataAccess for P
         pg_update(self.0.as_mut(), user, columns)
     }
 
-    fn create_js_errors<'s, 'v, 'f>(
-        &'s mut self,
-        errors: impl IntoIterator<Item = JsErrorInsertion> + Send + 'v,
-    ) -> impl Stream<Item = sqlx::Result<JsError>> + Send + 'f
-    where
-        's: 'f,
-        'v: 'f,
-    {
-        pg_insert::<_, ViaModelWithPrimaryKey<JsError>>(self.0.as_mut(), errors)
+    fn get_ct_user_identity(
+        &mut self,
+        provider: &str,
+        subject: &str,
+    ) -> impl Future<Output = sqlx::Result<Option<CtUserIdentity>>> + Send {
+        pg_select_one(
+            self.0.as_mut(),
+            Expr::col(CtUserIdentityIden::Provider)
+                .eq(provider)
+                .and(Expr::col(CtUserIdentityIden::Subject).eq(subject)),
+        )
     }
 
-    fn get_dashboard_trackers(
+    fn get_ct_user_identities_by_user_id(
         &mut self,
-        user_id: i32,
-    ) -> impl Stream<Item = sqlx::Result<ApTrackerDashboard>> + Send {
+        ct_user_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<CtUserIdentity>> + Send {
         let (sql, values) = Query::select()
             .column(Asterisk)
-            .from_function(
-                Func::cust(Alias::new("get_dashboard_trackers")).arg(user_id),
-                Alias::new("t"),
-            )
+            .from(CtUserIdentityIden::Table)
+            .and_where(Expr::col(CtUserIdentityIden::CtUserId).eq(ct_user_id))
+            .order_by(CtUserIdentityIden::Id, Order::Asc)
             .build_sqlx(PostgresQueryBuilder);
 
         stream! {
-            for await r in sqlx::query_as_with(&sql, values).fetch(self.0.as_mut()) {
-                yield r;
+            for await row in sqlx::query_as_with(&sql, values).fetch(self.0.as_mut()) {
+                yield row;
             }
         }
     }
 
-    async fn get_ap_tracker_dashboard_override(
+    fn create_ct_user_identities<'s, 'v, 'f>(
+        &'s mut self,
+        identities: impl IntoIterator<Item = CtUserIdentityInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<CtUserIdentity>> + Send + 'f
+    where
+        's: 'f,
+        'v: 'f,
+    {
+        pg_insert::<_, ViaModelWithPrimaryKey<CtUserIdentity>>(self.0.as_mut(), identities)
+    }
+
+    fn update_ct_user_identity(
+        &mut self,
+        identity: CtUserIdentity,
+        columns: &[CtUserIdentityIden],
+    ) -> impl Future<Output = sqlx::Result<Option<CtUserIdentity>>> + Send {
+        pg_update(self.0.as_mut(), identity, columns)
+    }
+
+    async fn delete_ct_user_identity(
         &mut self,
         ct_user_id: i32,
-        ap_tracker_id: i32,
-    ) -> sqlx::Result<Option<ApTrackerDashboardOverride>> {
-        let (sql, values) = Query::select()
-            .column(Asterisk)
-            .from(ApTrackerDashboardOverrideIden::Table)
+        id: i32,
+    ) -> sqlx::Result<Option<CtUserIdentity>> {
+        let (sql, values) = Query::delete()
+            .from_table(CtUserIdentityIden::Table)
             .and_where(
-                Expr::col(ApTrackerDashboardOverrideIden::CtUserId)
-                    .eq(ct_user_id)
-                    .and(Expr::col(ApTrackerDashboardOverrideIden::ApTrackerId).eq(ap_tracker_id)),
+                Expr::col(CtUserIdentityIden::Id)
+                    .eq(id)
+                    .and(Expr::col(CtUserIdentityIden::CtUserId).eq(ct_user_id)),
             )
+            .returning_all()
             .build_sqlx(PostgresQueryBuilder);
 
         sqlx::query_as_with(&sql, values)
@@ -448,93 +624,1089 @@ impl<T: AsMut<<Postgres as sqlx::Database>::Connection> + Send> DataAccess for P
             .await
     }
 
-    async fn upsert_ap_tracker_dashboard_override(
+    fn create_ct_user_sessions<'s, 'v, 'f>(
+        &'s mut self,
+        sessions: impl IntoIterator<Item = CtUserSessionInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<CtUserSession>> + Send + 'f
+    where
+        's: 'f,
+        'v: 'f,
+    {
+        pg_insert::<_, ViaModelWithPrimaryKey<CtUserSession>>(self.0.as_mut(), sessions)
+    }
+
+    fn get_ct_user_session_by_jti(
         &mut self,
-        dashboard_override: ApTrackerDashboardOverride,
-    ) -> sqlx::Result<()> {
-        let (sql, values) = Query::insert()
-            .into_table(ApTrackerDashboardOverrideIden::Table)
-            .columns([
-                ApTrackerDashboardOverrideIden::CtUserId,
-                ApTrackerDashboardOverrideIden::ApTrackerId,
-                ApTrackerDashboardOverrideIden::Visibility,
-            ])
-            .values([
-                dashboard_override.ct_user_id.into(),
-                dashboard_override.ap_tracker_id.into(),
-                dashboard_override.visibility.into(),
-            ])
-            .unwrap()
-            .on_conflict(
-                OnConflict::columns([
-                    ApTrackerDashboardOverrideIden::CtUserId,
-                    ApTrackerDashboardOverrideIden::ApTrackerId,
-                ])
-                .build_with(|c| {
-                    c.update_column(ApTrackerDashboardOverrideIden::Visibility);
-                }),
-            )
+        jti: uuid::Uuid,
+    ) -> impl Future<Output = sqlx::Result<Option<CtUserSession>>> + Send {
+        pg_select_one(self.0.as_mut(), Expr::col(CtUserSessionIden::Jti).eq(jti))
+    }
+
+    fn get_ct_user_session_by_refresh_token_hash(
+        &mut self,
+        refresh_token_hash: &str,
+    ) -> impl Future<Output = sqlx::Result<Option<CtUserSession>>> + Send {
+        pg_select_one(
+            self.0.as_mut(),
+            Expr::col(CtUserSessionIden::RefreshTokenHash).eq(refresh_token_hash),
+        )
+    }
+
+    fn get_active_ct_user_sessions_by_user_id(
+        &mut self,
+        ct_user_id: i32,
+        now: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<CtUserSession>> + Send {
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(CtUserSessionIden::Table)
+            .and_where(Expr::col(CtUserSessionIden::CtUserId).eq(ct_user_id))
+            .and_where(Expr::col(CtUserSessionIden::RevokedAt).is_null())
+            .and_where(Expr::col(CtUserSessionIden::ExpiresAt).gt(now))
+            .order_by(CtUserSessionIden::LastUsedAt, Order::Desc)
             .build_sqlx(PostgresQueryBuilder);
 
-        sqlx::query_with(&sql, values)
-            .execute(self.0.as_mut())
-            .await
-            .map(|_| ())
+        stream! {
+            for await row in sqlx::query_as_with(&sql, values).fetch(self.0.as_mut()) {
+                yield row;
+            }
+        }
     }
 
-    async fn delete_ap_tracker_dashboard_override(
+    fn refresh_ct_user_session(
+        &mut self,
+        session: CtUserSession,
+        refresh_token_hash: &str,
+        columns: &[CtUserSessionIden],
+    ) -> impl Future<Output = sqlx::Result<Option<CtUserSession>>> + Send {
+        pg_update_if(
+            self.0.as_mut(),
+            session,
+            columns,
+            Some(
+                Expr::col(CtUserSessionIden::RefreshTokenHash)
+                    .eq(refresh_token_hash)
+                    .and(Expr::col(CtUserSessionIden::RevokedAt).is_null()),
+            ),
+        )
+    }
+
+    fn revoke_ct_user_sessions(
         &mut self,
         ct_user_id: i32,
-        ap_tracker_id: i32,
-    ) -> sqlx::Result<Option<ApTrackerDashboardOverride>> {
-        let (sql, values) = Query::delete()
-            .from_table(ApTrackerDashboardOverrideIden::Table)
-            .and_where(
-                Expr::col(ApTrackerDashboardOverrideIden::CtUserId)
-                    .eq(ct_user_id)
-                    .and(Expr::col(ApTrackerDashboardOverrideIden::ApTrackerId).eq(ap_tracker_id)),
-            )
+        id: Option<i32>,
+        revoked_at: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<CtUserSession>> + Send {
+        let (sql, values) = Query::update()
+            .table(CtUserSessionIden::Table)
+            .value(CtUserSessionIden::RevokedAt, revoked_at)
+            .and_where(Expr::col(CtUserSessionIden::CtUserId).eq(ct_user_id))
+            .and_where(Expr::col(CtUserSessionIden::RevokedAt).is_null())
+            .and_where_option(id.map(|id| Expr::col(CtUserSessionIden::Id).eq(id)))
             .returning_all()
             .build_sqlx(PostgresQueryBuilder);
 
-        sqlx::query_as_with(&sql, values)
-            .fetch_optional(self.0.as_mut())
-            .await
+        stream! {
+            for await row in sqlx::query_as_with(&sql, values).fetch(self.0.as_mut()) {
+                yield row;
+            }
+        }
     }
 
-    fn create_audits<'s, 'v, 'f>(
+    fn create_api_keys<'s, 'v, 'f>(
         &'s mut self,
-        audits: impl IntoIterator<Item = AuditInsertion> + Send + 'v,
-    ) -> impl Stream<Item = sqlx::Result<Audit>> + Send + 'f
+        keys: impl IntoIterator<Item = ApiKeyInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<ApiKey>> + Send + 'f
     where
         's: 'f,
         'v: 'f,
     {
-        pg_insert::<_, ViaModelWithPrimaryKey<Audit>>(self.0.as_mut(), audits)
+        pg_insert::<_, ViaModelWithPrimaryKey<ApiKey>>(self.0.as_mut(), keys)
     }
 
-    fn get_system_audits_for_tracker(
+    fn get_api_key_by_secret_hash(
         &mut self,
-        ap_tracker_id: i32,
-    ) -> impl Stream<Item = sqlx::Result<Audit>> + Send {
+        secret_hash: &str,
+    ) -> impl Future<Output = sqlx::Result<Option<ApiKey>>> + Send {
+        pg_select_one(
+            self.0.as_mut(),
+            Expr::col(ApiKeyIden::SecretHash).eq(secret_hash),
+        )
+    }
+
+    fn get_api_keys_by_user_id(
+        &mut self,
+        ct_user_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<ApiKey>> + Send {
         let (sql, values) = Query::select()
-            .column((AuditIden::Table, Asterisk))
-            .from(ApGameIden::Table)
-            .inner_join(
-                AuditIden::Table,
-                Expr::col((AuditIden::Table, AuditIden::Entity))
-                    .eq(SimpleExpr::Constant("ap_game".into()))
-                    .and(
-                        Expr::col((AuditIden::Table, AuditIden::EntityId))
-                            .equals((ApGameIden::Table, ApGameIden::Id)),
-                    ),
+            .column(Asterisk)
+            .from(ApiKeyIden::Table)
+            .and_where(Expr::col(ApiKeyIden::CtUserId).eq(ct_user_id))
+            .order_by(ApiKeyIden::Id, Order::Asc)
+            .build_sqlx(PostgresQueryBuilder);
+
+        stream! {
+            for await row in sqlx::query_as_with(&sql, values).fetch(self.0.as_mut()) {
+                yield row;
+            }
+        }
+    }
+
+    fn update_api_key(
+        &mut self,
+        key: ApiKey,
+        columns: &[ApiKeyIden],
+    ) -> impl Future<Output = sqlx::Result<Option<ApiKey>>> + Send {
+        pg_update(self.0.as_mut(), key, columns)
+    }
+
+    async fn delete_api_key(&mut self, ct_user_id: i32, id: i32) -> sqlx::Result<Option<ApiKey>> {
+        let (sql, values) = Query::delete()
+            .from_table(ApiKeyIden::Table)
+            .and_where(
+                Expr::col(ApiKeyIden::Id)
+                    .eq(id)
+                    .and(Expr::col(ApiKeyIden::CtUserId).eq(ct_user_id)),
             )
+            .returning_all()
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_as_with(&sql, values)
+            .fetch_optional(self.0.as_mut())
+            .await
+    }
+
+    async fn create_api_key_trackers(
+        &mut self,
+        trackers: impl IntoIterator<Item = ApiKeyTracker> + Send,
+    ) -> sqlx::Result<()> {
+        let mut query = Query::insert().build_with(|q| {
+            q.into_table(ApiKeyTrackerIden::Table)
+                .columns([ApiKeyTrackerIden::ApiKeyId, ApiKeyTrackerIden::ApTrackerId])
+                .on_conflict(
+                    OnConflict::columns([
+                        ApiKeyTrackerIden::ApiKeyId,
+                        ApiKeyTrackerIden::ApTrackerId,
+                    ])
+                    .build_with(|c| {
+                        c.do_nothing();
+                    }),
+                );
+        });
+
+        let mut any = false;
+        for tracker in trackers {
+            any = true;
+            query.values_panic([tracker.api_key_id.into(), tracker.ap_tracker_id.into()]);
+        }
+
+        if !any {
+            return Ok(());
+        }
+
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_with(&sql, values)
+            .execute(self.0.as_mut())
+            .await
+            .map(|_| ())
+    }
+
+    fn get_ap_trackers_by_api_key_id(
+        &mut self,
+        api_key_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<ApTracker>> + Send {
+        let (sql, values) = Query::select()
+            .column((ApTrackerIden::Table, Asterisk))
+            .from(ApTrackerIden::Table)
+            .inner_join(
+                ApiKeyTrackerIden::Table,
+                Expr::col((ApiKeyTrackerIden::Table, ApiKeyTrackerIden::ApTrackerId))
+                    .equals((ApTrackerIden::Table, ApTrackerIden::Id)),
+            )
+            .and_where(
+                Expr::col((ApiKeyTrackerIden::Table, ApiKeyTrackerIden::ApiKeyId)).eq(api_key_id),
+            )
+            .order_by((ApTrackerIden::Table, ApTrackerIden::Id), Order::Asc)
+            .build_sqlx(PostgresQueryBuilder);
+
+        stream! {
+            for await row in sqlx::query_as_with(&sql, values).fetch(self.0.as_mut()) {
+                yield row;
+            }
+        }
+    }
+
+    fn get_ct_user_game_preference(
+        &mut self,
+        ct_user_id: i32,
+        game: &str,
+    ) -> impl Future<Output = sqlx::Result<Option<CtUserGamePreference>>> + Send {
+        pg_select_one(
+            self.0.as_mut(),
+            Expr::col(CtUserGamePreferenceIden::CtUserId)
+                .eq(ct_user_id)
+                .and(Expr::col(CtUserGamePreferenceIden::Game).eq(game)),
+        )
+    }
+
+    fn get_ct_user_game_preferences_by_user_id(
+        &mut self,
+        ct_user_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<CtUserGamePreference>> + Send {
+        pg_select_many(
+            self.0.as_mut(),
+            Expr::col(CtUserGamePreferenceIden::CtUserId).eq(ct_user_id),
+        )
+    }
+
+    async fn upsert_ct_user_game_preference(
+        &mut self,
+        preference: CtUserGamePreference,
+    ) -> sqlx::Result<()> {
+        let (sql, values) = Query::insert()
+            .into_table(CtUserGamePreferenceIden::Table)
+            .columns([
+                CtUserGamePreferenceIden::CtUserId,
+                CtUserGamePreferenceIden::Game,
+                CtUserGamePreferenceIden::DiscordPing,
+                CtUserGamePreferenceIden::AvailabilityStatus,
+            ])
+            .values([
+                preference.ct_user_id.into(),
+                preference.game.into(),
+                preference.discord_ping.into(),
+                preference.availability_status.into(),
+            ])
+            .unwrap()
+            .on_conflict(
+                OnConflict::columns([
+                    CtUserGamePreferenceIden::CtUserId,
+                    CtUserGamePreferenceIden::Game,
+                ])
+                .build_with(|c| {
+                    c.update_columns([
+                        CtUserGamePreferenceIden::DiscordPing,
+                        CtUserGamePreferenceIden::AvailabilityStatus,
+                    ]);
+                }),
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_with(&sql, values)
+            .execute(self.0.as_mut())
+            .await
+            .map(|_| ())
+    }
+
+    async fn delete_ct_user_game_preference(
+        &mut self,
+        ct_user_id: i32,
+        game: &str,
+    ) -> sqlx::Result<Option<CtUserGamePreference>> {
+        let (sql, values) = Query::delete()
+            .from_table(CtUserGamePreferenceIden::Table)
+            .and_where(
+                Expr::col(CtUserGamePreferenceIden::CtUserId)
+                    .eq(ct_user_id)
+                    .and(Expr::col(CtUserGamePreferenceIden::Game).eq(game)),
+            )
+            .returning_all()
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_as_with(&sql, values)
+            .fetch_optional(self.0.as_mut())
+            .await
+    }
+
+    async fn begin_scheduled_away_periods(&mut self, now: DateTime<Utc>) -> sqlx::Result<u64> {
+        let (sql, values) = Query::update()
+            .table(CtUserIden::Table)
+            .value(CtUserIden::IsAway, true)
+            .value(CtUserIden::AwayFrom, Option::<DateTime<Utc>>::None)
+            .and_where(Expr::col(CtUserIden::AwayFrom).lte(now))
+            .and_where(
+                Expr::col(CtUserIden::AwayUntil)
+                    .is_null()
+                    .or(Expr::col(CtUserIden::AwayUntil).gt(now)),
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_with(&sql, values)
+            .execute(self.0.as_mut())
+            .await
+            .map(|r| r.rows_affected())
+    }
+
+    async fn end_scheduled_away_periods(&mut self, now: DateTime<Utc>) -> sqlx::Result<u64> {
+        let (sql, values) = Query::update()
+            .table(CtUserIden::Table)
+            .value(CtUserIden::IsAway, false)
+            .value(CtUserIden::AwayFrom, Option::<DateTime<Utc>>::None)
+            .value(CtUserIden::AwayUntil, Option::<DateTime<Utc>>::None)
+            .value(CtUserIden::AwayMessage, "")
+            .and_where(Expr::col(CtUserIden::AwayUntil).lte(now))
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_with(&sql, values)
+            .execute(self.0.as_mut())
+            .await
+            .map(|r| r.rows_affected())
+    }
+
+    fn create_js_errors<'s, 'v, 'f>(
+        &'s mut self,
+        errors: impl IntoIterator<Item = JsErrorInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<JsError>> + Send + 'f
+    where
+        's: 'f,
+        'v: 'f,
+    {
+        pg_insert::<_, ViaModelWithPrimaryKey<JsError>>(self.0.as_mut(), errors)
+    }
+
+    fn get_dashboard_trackers(
+        &mut self,
+        user_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<ApTrackerDashboard>> + Send {
+        pg_select_from_function(self.0.as_mut(), "get_dashboard_trackers", [user_id.into()])
+    }
+
+    fn get_tracker_slot_stats(
+        &mut self,
+        tracker_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<TrackerSlotStats>> + Send {
+        pg_select_from_function(
+            self.0.as_mut(),
+            "get_tracker_slot_stats",
+            [tracker_id.into()],
+        )
+    }
+
+    fn get_tracker_hint_stats(
+        &mut self,
+        tracker_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<TrackerHintStats>> + Send {
+        pg_select_from_function(
+            self.0.as_mut(),
+            "get_tracker_hint_stats",
+            [tracker_id.into()],
+        )
+    }
+
+    fn get_user_game_hint_stats(
+        &mut self,
+        ct_user_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<UserGameHintStats>> + Send {
+        pg_select_from_function(
+            self.0.as_mut(),
+            "get_user_game_hint_stats",
+            [ct_user_id.into()],
+        )
+    }
+
+    fn get_tracker_game_stats(
+        &mut self,
+        tracker_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<TrackerGameStats>> + Send {
+        pg_select_from_function(
+            self.0.as_mut(),
+            "get_tracker_game_stats",
+            [tracker_id.into()],
+        )
+    }
+
+    fn get_pending_inactivity_alerts(
+        &mut self,
+        alerted_before: DateTime<Utc>,
+        updated_after: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<PendingInactivityAlert>> + Send {
+        pg_select_from_function(
+            self.0.as_mut(),
+            "get_pending_inactivity_alerts",
+            [alerted_before.into(), updated_after.into()],
+        )
+    }
+
+    fn get_expired_claims(
+        &mut self,
+        as_of: DateTime<Utc>,
+        updated_after: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<ExpiredClaim>> + Send {
+        pg_select_from_function(
+            self.0.as_mut(),
+            "get_expired_claims",
+            [as_of.into(), updated_after.into()],
+        )
+    }
+
+    async fn upsert_inactivity_alert(&mut self, alert: InactivityAlert) -> sqlx::Result<()> {
+        let (sql, values) = Query::insert()
+            .into_table(InactivityAlertIden::Table)
+            .columns([
+                InactivityAlertIden::ApGameId,
+                InactivityAlertIden::InactivityColor,
+                InactivityAlertIden::AlertedAt,
+            ])
+            .values([
+                alert.ap_game_id.into(),
+                alert.inactivity_color.into(),
+                alert.alerted_at.into(),
+            ])
+            .unwrap()
+            .on_conflict(
+                OnConflict::columns([
+                    InactivityAlertIden::ApGameId,
+                    InactivityAlertIden::InactivityColor,
+                ])
+                .build_with(|c| {
+                    c.update_column(InactivityAlertIden::AlertedAt);
+                }),
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_with(&sql, values)
+            .execute(self.0.as_mut())
+            .await
+            .map(|_| ())
+    }
+
+    async fn get_ap_tracker_dashboard_override(
+        &mut self,
+        ct_user_id: i32,
+        ap_tracker_id: i32,
+    ) -> sqlx::Result<Option<ApTrackerDashboardOverride>> {
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(ApTrackerDashboardOverrideIden::Table)
+            .and_where(
+                Expr::col(ApTrackerDashboardOverrideIden::CtUserId)
+                    .eq(ct_user_id)
+                    .and(Expr::col(ApTrackerDashboardOverrideIden::ApTrackerId).eq(ap_tracker_id)),
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_as_with(&sql, values)
+            .fetch_optional(self.0.as_mut())
+            .await
+    }
+
+    async fn upsert_ap_tracker_dashboard_override(
+        &mut self,
+        dashboard_override: ApTrackerDashboardOverride,
+    ) -> sqlx::Result<()> {
+        let (sql, values) = Query::insert()
+            .into_table(ApTrackerDashboardOverrideIden::Table)
+            .columns([
+                ApTrackerDashboardOverrideIden::CtUserId,
+                ApTrackerDashboardOverrideIden::ApTrackerId,
+                ApTrackerDashboardOverrideIden::Visibility,
+            ])
+            .values([
+                dashboard_override.ct_user_id.into(),
+                dashboard_override.ap_tracker_id.into(),
+                dashboard_override.visibility.into(),
+            ])
+            .unwrap()
+            .on_conflict(
+                OnConflict::columns([
+                    ApTrackerDashboardOverrideIden::CtUserId,
+                    ApTrackerDashboardOverrideIden::ApTrackerId,
+                ])
+                .build_with(|c| {
+                    c.update_column(ApTrackerDashboardOverrideIden::Visibility);
+                }),
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_with(&sql, values)
+            .execute(self.0.as_mut())
+            .await
+            .map(|_| ())
+    }
+
+    async fn delete_ap_tracker_dashboard_override(
+        &mut self,
+        ct_user_id: i32,
+        ap_tracker_id: i32,
+    ) -> sqlx::Result<Option<ApTrackerDashboardOverride>> {
+        let (sql, values) = Query::delete()
+            .from_table(ApTrackerDashboardOverrideIden::Table)
+            .and_where(
+                Expr::col(ApTrackerDashboardOverrideIden::CtUserId)
+                    .eq(ct_user_id)
+                    .and(Expr::col(ApTrackerDashboardOverrideIden::ApTrackerId).eq(ap_tracker_id)),
+            )
+            .returning_all()
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_as_with(&sql, values)
+            .fetch_optional(self.0.as_mut())
+            .await
+    }
+
+    fn get_ap_tracker_member(
+        &mut self,
+        ct_user_id: i32,
+        ap_tracker_id: i32,
+    ) -> impl Future<Output = sqlx::Result<Option<ApTrackerMember>>> + Send {
+        pg_select_one(
+            self.0.as_mut(),
+            Expr::col(ApTrackerMemberIden::CtUserId)
+                .eq(ct_user_id)
+                .and(Expr::col(ApTrackerMemberIden::ApTrackerId).eq(ap_tracker_id)),
+        )
+    }
+
+    fn get_ap_tracker_members_by_tracker_id(
+        &mut self,
+        ap_tracker_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<ApTrackerMember>> + Send {
+        pg_select_many(
+            self.0.as_mut(),
+            Expr::col(ApTrackerMemberIden::ApTrackerId).eq(ap_tracker_id),
+        )
+    }
+
+    async fn upsert_ap_tracker_member(&mut self, member: ApTrackerMember) -> sqlx::Result<()> {
+        let (sql, values) = Query::insert()
+            .into_table(ApTrackerMemberIden::Table)
+            .columns([
+                ApTrackerMemberIden::CtUserId,
+                ApTrackerMemberIden::ApTrackerId,
+                ApTrackerMemberIden::Role,
+            ])
+            .values([
+                member.ct_user_id.into(),
+                member.ap_tracker_id.into(),
+                member.role.into(),
+            ])
+            .unwrap()
+            .on_conflict(
+                OnConflict::columns([
+                    ApTrackerMemberIden::CtUserId,
+                    ApTrackerMemberIden::ApTrackerId,
+                ])
+                .build_with(|c| {
+                    c.update_column(ApTrackerMemberIden::Role);
+                }),
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_with(&sql, values)
+            .execute(self.0.as_mut())
+            .await
+            .map(|_| ())
+    }
+
+    async fn delete_ap_tracker_member(
+        &mut self,
+        ct_user_id: i32,
+        ap_tracker_id: i32,
+    ) -> sqlx::Result<Option<ApTrackerMember>> {
+        let (sql, values) = Query::delete()
+            .from_table(ApTrackerMemberIden::Table)
+            .and_where(
+                Expr::col(ApTrackerMemberIden::CtUserId)
+                    .eq(ct_user_id)
+                    .and(Expr::col(ApTrackerMemberIden::ApTrackerId).eq(ap_tracker_id)),
+            )
+            .returning_all()
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_as_with(&sql, values)
+            .fetch_optional(self.0.as_mut())
+            .await
+    }
+
+    fn get_ap_game_claimants_by_tracker_id(
+        &mut self,
+        tracker_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<ApGameClaimant>> + Send {
+        let (sql, values) = Query::select()
+            .column((ApGameClaimantIden::Table, Asterisk))
+            .from(ApGameClaimantIden::Table)
+            .inner_join(
+                ApGameIden::Table,
+                Expr::col((ApGameClaimantIden::Table, ApGameClaimantIden::ApGameId))
+                    .equals((ApGameIden::Table, ApGameIden::Id)),
+            )
+            .and_where(Expr::col((ApGameIden::Table, ApGameIden::TrackerId)).eq(tracker_id))
+            .order_by(
+                (ApGameClaimantIden::Table, ApGameClaimantIden::ClaimedAt),
+                Order::Asc,
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        stream! {
+            for await row in sqlx::query_as_with(&sql, values).fetch(self.0.as_mut()) {
+                yield row;
+            }
+        }
+    }
+
+    fn get_ap_game_claimants_by_game_id(
+        &mut self,
+        ap_game_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<ApGameClaimant>> + Send {
+        pg_select_many(
+            self.0.as_mut(),
+            Expr::col(ApGameClaimantIden::ApGameId).eq(ap_game_id),
+        )
+    }
+
+    async fn upsert_ap_game_claimant(&mut self, claimant: ApGameClaimant) -> sqlx::Result<()> {
+        let (sql, values) = Query::insert()
+            .into_table(ApGameClaimantIden::Table)
+            .columns([
+                ApGameClaimantIden::ApGameId,
+                ApGameClaimantIden::CtUserId,
+                ApGameClaimantIden::DiscordPing,
+                ApGameClaimantIden::ClaimedAt,
+            ])
+            .values([
+                claimant.ap_game_id.into(),
+                claimant.ct_user_id.into(),
+                claimant.discord_ping.into(),
+                claimant.claimed_at.into(),
+            ])
+            .unwrap()
+            .on_conflict(
+                OnConflict::columns([ApGameClaimantIden::ApGameId, ApGameClaimantIden::CtUserId])
+                    .build_with(|c| {
+                        c.update_column(ApGameClaimantIden::DiscordPing);
+                    }),
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_with(&sql, values)
+            .execute(self.0.as_mut())
+            .await
+            .map(|_| ())
+    }
+
+    async fn delete_ap_game_claimants_by_game_id(&mut self, ap_game_id: i32) -> sqlx::Result<()> {
+        let (sql, values) = Query::delete()
+            .from_table(ApGameClaimantIden::Table)
+            .and_where(Expr::col(ApGameClaimantIden::ApGameId).eq(ap_game_id))
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_with(&sql, values)
+            .execute(self.0.as_mut())
+            .await
+            .map(|_| ())
+    }
+
+    async fn delete_ap_game_claimant(
+        &mut self,
+        ap_game_id: i32,
+        ct_user_id: i32,
+    ) -> sqlx::Result<Option<ApGameClaimant>> {
+        let (sql, values) = Query::delete()
+            .from_table(ApGameClaimantIden::Table)
+            .and_where(
+                Expr::col(ApGameClaimantIden::ApGameId)
+                    .eq(ap_game_id)
+                    .and(Expr::col(ApGameClaimantIden::CtUserId).eq(ct_user_id)),
+            )
+            .returning_all()
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_as_with(&sql, values)
+            .fetch_optional(self.0.as_mut())
+            .await
+    }
+
+    fn get_ap_tracker_ownership_transfer(
+        &mut self,
+        ap_tracker_id: i32,
+    ) -> impl Future<Output = sqlx::Result<Option<ApTrackerOwnershipTransfer>>> + Send {
+        pg_select_one(
+            self.0.as_mut(),
+            Expr::col(ApTrackerOwnershipTransferIden::ApTrackerId).eq(ap_tracker_id),
+        )
+    }
+
+    fn create_ap_tracker_ownership_transfers<'s, 'v, 'f>(
+        &'s mut self,
+        transfers: impl IntoIterator<Item = ApTrackerOwnershipTransferInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<ApTrackerOwnershipTransfer>> + Send + 'f
+    where
+        's: 'f,
+        'v: 'f,
+    {
+        pg_insert::<_, ViaModelWithPrimaryKey<ApTrackerOwnershipTransfer>>(
+            self.0.as_mut(),
+            transfers,
+        )
+    }
+
+    async fn delete_ap_tracker_ownership_transfer(
+        &mut self,
+        ap_tracker_id: i32,
+    ) -> sqlx::Result<Option<ApTrackerOwnershipTransfer>> {
+        let (sql, values) = Query::delete()
+            .from_table(ApTrackerOwnershipTransferIden::Table)
+            .and_where(Expr::col(ApTrackerOwnershipTransferIden::ApTrackerId).eq(ap_tracker_id))
+            .returning_all()
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_as_with(&sql, values)
+            .fetch_optional(self.0.as_mut())
+            .await
+    }
+
+    async fn transfer_ap_tracker_ownership(
+        &mut self,
+        ap_tracker_id: i32,
+        from_ct_user_id: i32,
+        to_ct_user_id: i32,
+    ) -> sqlx::Result<Option<ApTracker>> {
+        let (sql, values) = Query::update()
+            .table(ApTrackerIden::Table)
+            .value(ApTrackerIden::OwnerCtUserId, to_ct_user_id)
+            .and_where(
+                Expr::col(ApTrackerIden::Id)
+                    .eq(ap_tracker_id)
+                    .and(Expr::col(ApTrackerIden::OwnerCtUserId).eq(from_ct_user_id)),
+            )
+            .returning_all()
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_as_with(&sql, values)
+            .fetch_optional(self.0.as_mut())
+            .await
+    }
+
+    fn get_ap_game_claim_request(
+        &mut self,
+        id: i32,
+    ) -> impl Future<Output = sqlx::Result<Option<ApGameClaimRequest>>> + Send {
+        pg_select_one(
+            self.0.as_mut(),
+            Expr::col(ApGameClaimRequestIden::Id).eq(id),
+        )
+    }
+
+    fn get_pending_ap_game_claim_requests_by_tracker_id(
+        &mut self,
+        tracker_id: i32,
+    ) -> impl Stream<Item = sqlx::Result<ApGameClaimRequest>> + Send {
+        let (sql, values) = Query::select()
+            .column((ApGameClaimRequestIden::Table, Asterisk))
+            .from(ApGameClaimRequestIden::Table)
+            .inner_join(
+                ApGameIden::Table,
+                Expr::col((
+                    ApGameClaimRequestIden::Table,
+                    ApGameClaimRequestIden::ApGameId,
+                ))
+                .equals((ApGameIden::Table, ApGameIden::Id)),
+            )
+            .and_where(Expr::col((ApGameIden::Table, ApGameIden::TrackerId)).eq(tracker_id))
+            .and_where(
+                Expr::col((
+                    ApGameClaimRequestIden::Table,
+                    ApGameClaimRequestIden::Status,
+                ))
+                .eq(pending_claim_request_status()),
+            )
+            .order_by(
+                (
+                    ApGameClaimRequestIden::Table,
+                    ApGameClaimRequestIden::CreatedAt,
+                ),
+                Order::Asc,
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        stream! {
+            for await row in sqlx::query_as_with(&sql, values).fetch(self.0.as_mut()) {
+                yield row;
+            }
+        }
+    }
+
+    fn create_ap_game_claim_requests<'s, 'v, 'f>(
+        &'s mut self,
+        requests: impl IntoIterator<Item = ApGameClaimRequestInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<ApGameClaimRequest>> + Send + 'f
+    where
+        's: 'f,
+        'v: 'f,
+    {
+        pg_insert::<_, ViaModelWithPrimaryKey<ApGameClaimRequest>>(self.0.as_mut(), requests)
+    }
+
+    fn update_pending_ap_game_claim_request(
+        &mut self,
+        request: ApGameClaimRequest,
+        columns: &[ApGameClaimRequestIden],
+    ) -> impl Future<Output = sqlx::Result<Option<ApGameClaimRequest>>> + Send {
+        pg_update_if(
+            self.0.as_mut(),
+            request,
+            columns,
+            Some(Expr::col(ApGameClaimRequestIden::Status).eq(pending_claim_request_status())),
+        )
+    }
+
+    fn deny_pending_ap_game_claim_requests_by_game_id(
+        &mut self,
+        ap_game_id: i32,
+        resolved_by_ct_user_id: i32,
+        resolved_at: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<ApGameClaimRequest>> + Send {
+        let (sql, values) = Query::update()
+            .table(ApGameClaimRequestIden::Table)
+            .value(ApGameClaimRequestIden::Status, ClaimRequestStatus::Denied)
+            .value(ApGameClaimRequestIden::ResolvedAt, resolved_at)
+            .value(
+                ApGameClaimRequestIden::ResolvedByCtUserId,
+                resolved_by_ct_user_id,
+            )
+            .and_where(Expr::col(ApGameClaimRequestIden::ApGameId).eq(ap_game_id))
+            .and_where(Expr::col(ApGameClaimRequestIden::Status).eq(pending_claim_request_status()))
+            .and_where(Expr::col(ApGameClaimRequestIden::CoClaim).eq(false))
+            .returning_all()
+            .build_sqlx(PostgresQueryBuilder);
+
+        stream! {
+            for await row in sqlx::query_as_with(&sql, values).fetch(self.0.as_mut()) {
+                yield row;
+            }
+        }
+    }
+
+    fn create_ct_user_notifications<'s, 'v, 'f>(
+        &'s mut self,
+        notifications: impl IntoIterator<Item = CtUserNotificationInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<CtUserNotification>> + Send + 'f
+    where
+        's: 'f,
+        'v: 'f,
+    {
+        pg_insert::<_, ViaModelWithPrimaryKey<CtUserNotification>>(self.0.as_mut(), notifications)
+    }
+
+    fn get_ct_user_notifications(
+        &mut self,
+        ct_user_id: i32,
+        before_id: Option<i32>,
+        limit: u64,
+    ) -> impl Stream<Item = sqlx::Result<CtUserNotification>> + Send {
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(CtUserNotificationIden::Table)
+            .and_where(Expr::col(CtUserNotificationIden::CtUserId).eq(ct_user_id))
+            .and_where_option(before_id.map(|id| Expr::col(CtUserNotificationIden::Id).lt(id)))
+            .order_by(CtUserNotificationIden::Id, Order::Desc)
+            .limit(limit)
+            .build_sqlx(PostgresQueryBuilder);
+
+        stream! {
+            for await row in sqlx::query_as_with(&sql, values).fetch(self.0.as_mut()) {
+                yield row;
+            }
+        }
+    }
+
+    async fn get_ct_user_unread_notification_count(
+        &mut self,
+        ct_user_id: i32,
+    ) -> sqlx::Result<i64> {
+        let (sql, values) = Query::select()
+            .expr(Func::count(Expr::col(Asterisk)))
+            .from(CtUserNotificationIden::Table)
+            .and_where(Expr::col(CtUserNotificationIden::CtUserId).eq(ct_user_id))
+            .and_where(Expr::col(CtUserNotificationIden::ReadAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_scalar_with(&sql, values)
+            .fetch_one(self.0.as_mut())
+            .await
+    }
+
+    async fn mark_ct_user_notifications_read(
+        &mut self,
+        ct_user_id: i32,
+        ids: Option<&[i32]>,
+        read_at: DateTime<Utc>,
+    ) -> sqlx::Result<u64> {
+        let (sql, values) = Query::update()
+            .table(CtUserNotificationIden::Table)
+            .value(CtUserNotificationIden::ReadAt, read_at)
+            .and_where(Expr::col(CtUserNotificationIden::CtUserId).eq(ct_user_id))
+            .and_where(Expr::col(CtUserNotificationIden::ReadAt).is_null())
+            .and_where_option(
+                ids.map(|ids| Expr::col(CtUserNotificationIden::Id).is_in(ids.iter().copied())),
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_with(&sql, values)
+            .execute(self.0.as_mut())
+            .await
+            .map(|r| r.rows_affected())
+    }
+
+    fn get_ap_tracker_notification_mutes(
+        &mut self,
+        ap_tracker_id: i32,
+        ct_user_ids: &[i32],
+    ) -> impl Stream<Item = sqlx::Result<ApTrackerNotificationMute>> + Send {
+        pg_select_many(
+            self.0.as_mut(),
+            Expr::col(ApTrackerNotificationMuteIden::ApTrackerId)
+                .eq(ap_tracker_id)
+                .and(
+                    Expr::col(ApTrackerNotificationMuteIden::CtUserId)
+                        .is_in(ct_user_ids.iter().copied()),
+                ),
+        )
+    }
+
+    async fn upsert_ap_tracker_notification_mute(
+        &mut self,
+        mute: ApTrackerNotificationMute,
+    ) -> sqlx::Result<()> {
+        let (sql, values) = Query::insert()
+            .into_table(ApTrackerNotificationMuteIden::Table)
+            .columns([
+                ApTrackerNotificationMuteIden::CtUserId,
+                ApTrackerNotificationMuteIden::ApTrackerId,
+            ])
+            .values([mute.ct_user_id.into(), mute.ap_tracker_id.into()])
+            .unwrap()
+            .on_conflict(
+                OnConflict::columns([
+                    ApTrackerNotificationMuteIden::CtUserId,
+                    ApTrackerNotificationMuteIden::ApTrackerId,
+                ])
+                .build_with(|c| {
+                    c.do_nothing();
+                }),
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_with(&sql, values)
+            .execute(self.0.as_mut())
+            .await
+            .map(|_| ())
+    }
+
+    async fn delete_ap_tracker_notification_mute(
+        &mut self,
+        ct_user_id: i32,
+        ap_tracker_id: i32,
+    ) -> sqlx::Result<Option<ApTrackerNotificationMute>> {
+        let (sql, values) = Query::delete()
+            .from_table(ApTrackerNotificationMuteIden::Table)
+            .and_where(
+                Expr::col(ApTrackerNotificationMuteIden::CtUserId)
+                    .eq(ct_user_id)
+                    .and(Expr::col(ApTrackerNotificationMuteIden::ApTrackerId).eq(ap_tracker_id)),
+            )
+            .returning_all()
+            .build_sqlx(PostgresQueryBuilder);
+
+        sqlx::query_as_with(&sql, values)
+            .fetch_optional(self.0.as_mut())
+            .await
+    }
+
+    fn create_audits<'s, 'v, 'f>(
+        &'s mut self,
+        audits: impl IntoIterator<Item = AuditInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<Audit>> + Send + 'f
+    where
+        's: 'f,
+        'v: 'f,
+    {
+        pg_insert::<_, ViaModelWithPrimaryKey<Audit>>(self.0.as_mut(), audits)
+    }
+
+    fn create_checks_history<'s, 'v, 'f>(
+        &'s mut self,
+        entries: impl IntoIterator<Item = ChecksHistoryInsertion> + Send + 'v,
+    ) -> impl Stream<Item = sqlx::Result<ChecksHistory>> + Send + 'f
+    where
+        's: 'f,
+        'v: 'f,
+    {
+        pg_insert::<_, ViaModelWithPrimaryKey<ChecksHistory>>(self.0.as_mut(), entries)
+    }
+
+    fn get_checks_history_by_tracker_id(
+        &mut self,
+        ap_tracker_id: i32,
+        ap_game_ids: Option<&[i32]>,
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> impl Stream<Item = sqlx::Result<ChecksHistory>> + Send {
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(ChecksHistoryIden::Table)
+            .and_where(Expr::col(ChecksHistoryIden::ApTrackerId).eq(ap_tracker_id))
+            .and_where_option(
+                ap_game_ids
+                    .map(|ids| Expr::col(ChecksHistoryIden::ApGameId).is_in(ids.iter().copied())),
+            )
+            .and_where_option(from.map(|d| Expr::col(ChecksHistoryIden::ChangedAt).gte(d)))
+            .and_where_option(until.map(|d| Expr::col(ChecksHistoryIden::ChangedAt).lt(d)))
+            .order_by(ChecksHistoryIden::ChangedAt, Order::Asc)
+            .order_by(ChecksHistoryIden::Id, Order::Asc)
+            .build_sqlx(PostgresQueryBuilder);
+
+        stream! {
+            for await row in sqlx::query_as_with(&sql, values).fetch(self.0.as_mut()) {
+                yield row;
+            }
+        }
+    }
+
+    fn get_last_checks_history_before(
+        &mut self,
+        ap_tracker_id: i32,
+        before: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<ChecksHistory>> + Send {
+        let (sql, values) = Query::select()
+            .distinct_on([ChecksHistoryIden::ApGameId])
+            .column(Asterisk)
+            .from(ChecksHistoryIden::Table)
+            .and_where(
+                Expr::col(ChecksHistoryIden::ApTrackerId)
+                    .eq(ap_tracker_id)
+                    .and(Expr::col(ChecksHistoryIden::ChangedAt).lt(before)),
+            )
+            .order_by(ChecksHistoryIden::ApGameId, Order::Asc)
+            .order_by(ChecksHistoryIden::ChangedAt, Order::Desc)
+            .order_by(ChecksHistoryIden::Id, Order::Desc)
+            .build_sqlx(PostgresQueryBuilder);
+
+        stream! {
+            for await row in sqlx::query_as_with(&sql, values).fetch(self.0.as_mut()) {
+                yield row;
+            }
+        }
+    }
+
+    fn get_first_checks_history_since(
+        &mut self,
+        ap_tracker_id: i32,
+        since: DateTime<Utc>,
+    ) -> impl Stream<Item = sqlx::Result<ChecksHistory>> + Send {
+        let (sql, values) = Query::select()
+            .distinct_on([ChecksHistoryIden::ApGameId])
+            .column(Asterisk)
+            .from(ChecksHistoryIden::Table)
             .and_where(
-                Expr::col((ApGameIden::Table, ApGameIden::TrackerId))
+                Expr::col(ChecksHistoryIden::ApTrackerId)
                     .eq(ap_tracker_id)
-                    .and(Expr::col((AuditIden::Table, AuditIden::ActorCtUserId)).is_null()),
+                    .and(Expr::col(ChecksHistoryIden::ChangedAt).gte(since)),
             )
-            .order_by((AuditIden::Table, AuditIden::ChangedAt), Order::Asc)
+            .order_by(ChecksHistoryIden::ApGameId, Order::Asc)
+            .order_by(ChecksHistoryIden::ChangedAt, Order::Asc)
+            .order_by(ChecksHistoryIden::Id, Order::Asc)
             .build_sqlx(PostgresQueryBuilder);
 
         stream! {
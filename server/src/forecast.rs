@@ -0,0 +1,421 @@
+//! Completion forecasting.
+//!
+//! Estimates when each slot in a tracker will have sent all of its checks,
+//! based on the rate at which the slot has sent checks recently.  The room's
+//! estimate is the latest estimate of all unfinished slots, since slots
+//! progress in parallel.
+//!
+//! Players rarely play continuously, so the time between two consecutive
+//! changes in a slot's check count is capped at the tracker's yellow inactivity
+//! threshold when computing the rate.  This allows regular breaks (such as
+//! sleeping) to slow the forecast down, while preventing a single long absence
+//! from making the slot look like it will never finish.  Slots that have been
+//! inactive for longer than the tracker's red inactivity threshold are
+//! considered stalled and are not forecast at all.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+
+use crate::db::model::{ApGame, ApTracker, ChecksHistory, CompletionStatus};
+
+/// How far back to look in the checks history when computing check rates.
+pub const FORECAST_WINDOW: TimeDelta = TimeDelta::hours(48);
+
+/// The minimum amount of active time within the window required to produce a
+/// forecast.
+///
+/// This prevents a burst of checks sent over a few minutes from producing a
+/// wildly optimistic estimate.
+const MIN_ACTIVE_TIME: TimeDelta = TimeDelta::minutes(30);
+
+/// The lowest the idle gap cap may be, regardless of the tracker's yellow
+/// inactivity threshold.
+const MIN_IDLE_GAP_CAP: TimeDelta = TimeDelta::hours(1);
+
+/// The state of a slot's forecast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForecastStatus {
+    /// The slot doesn't need to send any more checks, either because it
+    /// already has, or because its completion status indicates that it won't
+    /// (such as after goaling or releasing).
+    Finished,
+    /// The slot is actively sending checks.
+    Active,
+    /// The slot has been inactive for longer than the tracker's yellow
+    /// inactivity threshold.  The forecast is less reliable.
+    Idle,
+    /// The slot has been inactive for longer than the tracker's red inactivity
+    /// threshold.  No forecast is made.
+    Stalled,
+    /// The slot has never had any activity.  No forecast is made.
+    NotStarted,
+    /// The slot has not sent enough checks recently to make a forecast.
+    InsufficientData,
+}
+
+/// Completion forecast for a single slot.
+#[derive(Debug, Clone, Serialize)]
+pub struct SlotForecast {
+    pub status: ForecastStatus,
+    /// The recent rate at which the slot has sent checks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checks_per_hour: Option<f64>,
+    /// The estimated time at which the slot will have sent all of its checks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eta: Option<DateTime<Utc>>,
+}
+
+impl SlotForecast {
+    /// Creates a forecast with the given status and no estimate.
+    fn without_estimate(status: ForecastStatus) -> Self {
+        Self {
+            status,
+            checks_per_hour: None,
+            eta: None,
+        }
+    }
+}
+
+/// Completion forecast for a whole room.
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomForecast {
+    /// The latest estimate of all slots that could be forecast.
+    ///
+    /// If `unforecast_slots` is not zero, the room will likely finish later
+    /// than this.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eta: Option<DateTime<Utc>>,
+    /// The number of slots that are not finished.
+    pub remaining_slots: usize,
+    /// The number of unfinished slots for which no estimate could be made.
+    pub unforecast_slots: usize,
+}
+
+/// Completion forecast for a tracker.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackerForecast {
+    pub room: RoomForecast,
+    /// Forecasts for each slot, keyed by the slot's ID.
+    pub slots: HashMap<i32, SlotForecast>,
+}
+
+/// Forecasts completion of a tracker's slots.
+///
+/// `history` should contain the checks history of the tracker's slots since
+/// [`FORECAST_WINDOW`] before `now`, sorted chronologically.
+pub fn forecast_tracker(
+    tracker: &ApTracker,
+    games: &[ApGame],
+    history: &[ChecksHistory],
+    now: DateTime<Utc>,
+) -> TrackerForecast {
+    let mut history_by_game: HashMap<i32, Vec<&ChecksHistory>> = HashMap::new();
+
+    for entry in history {
+        history_by_game
+            .entry(entry.ap_game_id)
+            .or_default()
+            .push(entry);
+    }
+
+    let slots: HashMap<_, _> = games
+        .iter()
+        .map(|game| {
+            (
+                game.id,
+                forecast_slot(
+                    tracker,
+                    game,
+                    history_by_game
+                        .get(&game.id)
+                        .map(Vec::as_slice)
+                        .unwrap_or_default(),
+                    now,
+                ),
+            )
+        })
+        .collect();
+
+    let unfinished = || {
+        slots
+            .values()
+            .filter(|f| f.status != ForecastStatus::Finished)
+    };
+
+    TrackerForecast {
+        room: RoomForecast {
+            eta: unfinished().filter_map(|f| f.eta).max(),
+            remaining_slots: unfinished().count(),
+            unforecast_slots: unfinished().filter(|f| f.eta.is_none()).count(),
+        },
+        slots,
+    }
+}
+
+/// Forecasts completion of a single slot.
+///
+/// `history` should contain the slot's checks history since
+/// [`FORECAST_WINDOW`] before `now`, sorted chronologically.
+fn forecast_slot(
+    tracker: &ApTracker,
+    game: &ApGame,
+    history: &[&ChecksHistory],
+    now: DateTime<Utc>,
+) -> SlotForecast {
+    if game.completion_status != CompletionStatus::Incomplete
+        || game.checks_done >= game.checks_total
+    {
+        return SlotForecast::without_estimate(ForecastStatus::Finished);
+    }
+
+    let Some(last_activity) = game.last_activity else {
+        return SlotForecast::without_estimate(ForecastStatus::NotStarted);
+    };
+
+    let inactive_for = now - last_activity;
+
+    if inactive_for >= TimeDelta::hours(tracker.inactivity_threshold_red_hours.into()) {
+        return SlotForecast::without_estimate(ForecastStatus::Stalled);
+    }
+
+    let Some(first) = history.first() else {
+        return SlotForecast::without_estimate(ForecastStatus::InsufficientData);
+    };
+
+    let idle_gap_cap =
+        TimeDelta::hours(tracker.inactivity_threshold_yellow_hours.into()).max(MIN_IDLE_GAP_CAP);
+
+    // Sum the time between each change in the check count, and from the last
+    // change until now, capping each gap.
+    let active_time = history
+        .iter()
+        .map(|e| e.changed_at)
+        .chain([now])
+        .scan(first.changed_at, |prev, t| {
+            let gap = (t - *prev).min(idle_gap_cap);
+            *prev = t;
+            Some(gap)
+        })
+        .fold(TimeDelta::zero(), |a, b| a + b);
+
+    let gained = game.checks_done - first.previous_checks_done;
+
+    if gained <= 0 || active_time < MIN_ACTIVE_TIME {
+        return SlotForecast::without_estimate(ForecastStatus::InsufficientData);
+    }
+
+    let checks_per_sec = f64::from(gained) / active_time.num_seconds() as f64;
+    let remaining = f64::from(game.checks_total - game.checks_done);
+
+    let eta = TimeDelta::try_seconds((remaining / checks_per_sec).ceil() as i64)
+        .and_then(|d| now.checked_add_signed(d));
+
+    SlotForecast {
+        status: if inactive_for
+            >= TimeDelta::hours(tracker.inactivity_threshold_yellow_hours.into())
+        {
+            ForecastStatus::Idle
+        } else {
+            ForecastStatus::Active
+        },
+        checks_per_hour: Some(checks_per_sec * 3600.0),
+        eta,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    use crate::db::model::{
+        AvailabilityStatus, PingPreference, ProgressionStatus, TrackerGameStatus,
+    };
+
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap()
+    }
+
+    fn tracker() -> ApTracker {
+        ApTracker {
+            id: 1,
+            tracker_id: Uuid::nil(),
+            updated_at: now(),
+            title: String::new(),
+            description: String::new(),
+            owner_ct_user_id: None,
+            lock_settings: false,
+            upstream_url: String::new(),
+            global_ping_policy: None,
+            room_link: String::new(),
+            last_port: None,
+            next_port_check_at: None,
+            inactivity_threshold_yellow_hours: 2,
+            inactivity_threshold_red_hours: 24,
+            require_authentication_to_claim: false,
+            claim_expiry_days: None,
+            claim_expiry_notify: false,
+        }
+    }
+
+    fn game(id: i32, checks_done: i32, last_activity: Option<DateTime<Utc>>) -> ApGame {
+        ApGame {
+            id,
+            tracker_id: 1,
+            position: id,
+            name: format!("Slot {id}"),
+            game: "Game".to_owned(),
+            tracker_status: TrackerGameStatus::Playing,
+            checks_done,
+            checks_total: 40,
+            last_activity,
+            discord_username: None,
+            discord_ping: PingPreference::Never,
+            last_checked: None,
+            notes: String::new(),
+            claimed_by_ct_user_id: None,
+            availability_status: AvailabilityStatus::Open,
+            completion_status: CompletionStatus::Incomplete,
+            progression_status: ProgressionStatus::Unknown,
+            locked: false,
+            effective_discord_username: None,
+            user_is_away: false,
+            user_away_until: None,
+            user_away_message: None,
+        }
+    }
+
+    fn change(
+        ap_game_id: i32,
+        ago: TimeDelta,
+        previous_checks_done: i32,
+        checks_done: i32,
+    ) -> ChecksHistory {
+        ChecksHistory {
+            id: 0,
+            ap_tracker_id: 1,
+            ap_game_id,
+            changed_at: now() - ago,
+            previous_checks_done,
+            checks_done,
+        }
+    }
+
+    fn forecast(game: &ApGame, history: &[ChecksHistory]) -> SlotForecast {
+        forecast_slot(&tracker(), game, &history.iter().collect::<Vec<_>>(), now())
+    }
+
+    #[test]
+    fn finished_slots_have_no_estimate() {
+        let mut goaled = game(1, 10, Some(now()));
+        goaled.completion_status = CompletionStatus::Goal;
+
+        let all_checks = game(2, 40, Some(now()));
+
+        for g in [goaled, all_checks] {
+            let f = forecast(&g, &[]);
+            assert_eq!(f.status, ForecastStatus::Finished);
+            assert_eq!(f.eta, None);
+        }
+    }
+
+    #[test]
+    fn slots_without_activity_are_not_started() {
+        let f = forecast(&game(1, 0, None), &[]);
+
+        assert_eq!(f.status, ForecastStatus::NotStarted);
+        assert_eq!(f.eta, None);
+    }
+
+    #[test]
+    fn slots_inactive_past_red_threshold_are_stalled() {
+        let g = game(1, 10, Some(now() - TimeDelta::hours(24)));
+        let history = [change(1, TimeDelta::hours(30), 0, 10)];
+
+        let f = forecast(&g, &history);
+
+        assert_eq!(f.status, ForecastStatus::Stalled);
+        assert_eq!(f.eta, None);
+    }
+
+    #[test]
+    fn short_bursts_are_insufficient_data() {
+        let g = game(1, 10, Some(now() - TimeDelta::minutes(5)));
+        let history = [
+            change(1, TimeDelta::minutes(10), 0, 5),
+            change(1, TimeDelta::minutes(5), 5, 10),
+        ];
+
+        assert_eq!(
+            forecast(&g, &history).status,
+            ForecastStatus::InsufficientData
+        );
+        assert_eq!(forecast(&g, &[]).status, ForecastStatus::InsufficientData);
+    }
+
+    #[test]
+    fn idle_gaps_are_capped() {
+        // 9 hours pass between the two changes, which is capped at the 2 hour
+        // yellow threshold, followed by another hour until now.  20 checks in 3
+        // active hours leaves 3 hours for the remaining 20 checks.
+        let g = game(1, 20, Some(now() - TimeDelta::hours(1)));
+        let history = [
+            change(1, TimeDelta::hours(10), 0, 10),
+            change(1, TimeDelta::hours(1), 10, 20),
+        ];
+
+        let f = forecast(&g, &history);
+
+        assert_eq!(f.status, ForecastStatus::Active);
+        assert!((f.checks_per_hour.unwrap() - 20.0 / 3.0).abs() < 1e-9);
+
+        let eta = f.eta.unwrap();
+        assert!(eta >= now() + TimeDelta::hours(3));
+        assert!(eta <= now() + TimeDelta::hours(3) + TimeDelta::seconds(1));
+    }
+
+    #[test]
+    fn slots_inactive_past_yellow_threshold_are_idle() {
+        let g = game(1, 20, Some(now() - TimeDelta::hours(3)));
+        let history = [
+            change(1, TimeDelta::hours(4), 0, 10),
+            change(1, TimeDelta::hours(3), 10, 20),
+        ];
+
+        let f = forecast(&g, &history);
+
+        assert_eq!(f.status, ForecastStatus::Idle);
+        assert!(f.eta.is_some());
+    }
+
+    #[test]
+    fn room_forecast_is_latest_slot_estimate() {
+        let fast = game(1, 20, Some(now()));
+        let slow = game(2, 10, Some(now()));
+        let unknown = game(3, 0, None);
+        let mut finished = game(4, 40, Some(now()));
+        finished.completion_status = CompletionStatus::Done;
+
+        let history = [
+            change(1, TimeDelta::hours(2), 0, 20),
+            change(2, TimeDelta::hours(2), 0, 10),
+        ];
+
+        let f = forecast_tracker(
+            &tracker(),
+            &[fast, slow, unknown, finished],
+            &history,
+            now(),
+        );
+
+        assert_eq!(f.room.remaining_slots, 3);
+        assert_eq!(f.room.unforecast_slots, 1);
+        assert_eq!(f.room.eta, f.slots[&2].eta);
+        assert!(f.slots[&1].eta < f.slots[&2].eta);
+    }
+}
@@ -0,0 +1,427 @@
+//! Hint analysis.
+
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    process::Stdio,
+};
+
+use chrono::{DateTime, Utc};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::db::model::{
+    ApGame, ApHint, ApTracker, CompletionStatus, HintClassification, InactivityColor,
+    ProgressionStatus,
+};
+
+/// An unfound hint that a finder should act on, together with the slot that is
+/// waiting for the item.
+#[derive(Debug, Clone, Copy)]
+pub struct TodoHint<'a> {
+    pub hint: &'a ApHint,
+    /// The slot receiving the item, if it is part of the tracker.
+    pub receiver: Option<&'a ApGame>,
+}
+
+/// Ranks the unfound hints of a finder slot by how urgently the finder should
+/// find them.
+///
+/// Hints are ordered by:
+///
+/// 1. Their classification: critical items first, then progression items, then
+///    items that haven't been classified, then useful and trash items.
+/// 2. The progression status of the receiver: receivers in BK first, then
+///    receivers in soft BK, then everyone else.
+/// 3. The activity of the receiver: the most recently active receivers first.
+/// 4. How long the hint has been outstanding: the oldest hints first.
+///
+/// Hints for receivers that have reached their goal or released don't need to
+/// be found and are omitted.
+pub fn rank_todo_hints<'a>(
+    finder_game_id: i32,
+    games: &'a [ApGame],
+    hints: &'a [ApHint],
+) -> Vec<TodoHint<'a>> {
+    let games_by_id: HashMap<_, _> = games.iter().map(|g| (g.id, g)).collect();
+
+    let mut todo: Vec<_> = hints
+        .iter()
+        .filter(|h| h.finder_game_id == finder_game_id && !h.found)
+        .map(|hint| TodoHint {
+            hint,
+            receiver: hint
+                .receiver_game_id
+                .and_then(|id| games_by_id.get(&id).copied()),
+        })
+        .filter(|t| {
+            t.receiver.is_none_or(|r| {
+                !matches!(
+                    r.completion_status,
+                    CompletionStatus::Goal | CompletionStatus::Done | CompletionStatus::Released
+                )
+            })
+        })
+        .collect();
+
+    todo.sort_by_key(|t| {
+        (
+            classification_rank(t.hint.classification),
+            t.receiver
+                .map_or(2, |r| progression_rank(r.progression_status)),
+            Reverse(t.receiver.and_then(ApGame::last_active_at)),
+            // Hints created before creation times were recorded are the
+            // oldest, and sort first since `None` is less than any `Some`.
+            t.hint.created_at,
+            t.hint.id,
+        )
+    });
+
+    todo
+}
+
+/// Ranks a hint classification by how urgently the item is needed.  Lower
+/// values are more urgent.
+fn classification_rank(classification: HintClassification) -> u8 {
+    match classification {
+        HintClassification::Critical => 0,
+        HintClassification::Progression => 1,
+        HintClassification::Unset | HintClassification::Unknown => 2,
+        HintClassification::Qol => 3,
+        HintClassification::Trash => 4,
+    }
+}
+
+/// Ranks a progression status by how urgently the slot needs items.  Lower
+/// values are more urgent.
+fn progression_rank(status: ProgressionStatus) -> u8 {
+    match status {
+        ProgressionStatus::Bk => 0,
+        ProgressionStatus::SoftBk => 1,
+        ProgressionStatus::Unknown | ProgressionStatus::Unblocked | ProgressionStatus::Go => 2,
+    }
+}
+
+/// A graph of which slots are holding items needed by slots in BK.
+///
+/// Each edge is an unfound hint for a critical or progression item, leading
+/// from a receiver in BK to the finder that holds the item.
+#[derive(Debug, Clone)]
+pub struct BlockerGraph<'a> {
+    /// The slots in the graph: every unfinished slot in BK, and every slot
+    /// holding an item for one of them.
+    pub slots: Vec<BlockerSlot<'a>>,
+    pub edges: Vec<&'a ApHint>,
+}
+
+/// A slot in a [`BlockerGraph`].
+#[derive(Debug, Clone, Copy)]
+pub struct BlockerSlot<'a> {
+    pub game: &'a ApGame,
+    /// Whether this slot is in BK.
+    pub blocked: bool,
+    /// The number of slots in BK that this slot holds items for, excluding
+    /// itself.
+    pub blocking: usize,
+}
+
+/// Computes the [`BlockerGraph`] of a tracker from its games and hints.
+pub fn blocker_graph<'a>(games: &'a [ApGame], hints: &'a [ApHint]) -> BlockerGraph<'a> {
+    let blocked: HashMap<_, _> = games
+        .iter()
+        .filter(|g| {
+            g.progression_status == ProgressionStatus::Bk
+                && matches!(
+                    g.completion_status,
+                    CompletionStatus::Incomplete | CompletionStatus::AllChecks
+                )
+        })
+        .map(|g| (g.id, g))
+        .collect();
+
+    let edges: Vec<_> = hints
+        .iter()
+        .filter(|h| {
+            !h.found
+                && matches!(
+                    h.classification,
+                    HintClassification::Critical | HintClassification::Progression
+                )
+                && h.receiver_game_id
+                    .is_some_and(|id| blocked.contains_key(&id))
+        })
+        .collect();
+
+    let mut blocking: HashMap<i32, HashSet<i32>> = HashMap::new();
+
+    for edge in &edges {
+        if let Some(receiver) = edge.receiver_game_id
+            && receiver != edge.finder_game_id
+        {
+            blocking
+                .entry(edge.finder_game_id)
+                .or_default()
+                .insert(receiver);
+        }
+    }
+
+    let slots = games
+        .iter()
+        .filter(|g| blocked.contains_key(&g.id) || edges.iter().any(|e| e.finder_game_id == g.id))
+        .map(|game| BlockerSlot {
+            game,
+            blocked: blocked.contains_key(&game.id),
+            blocking: blocking.get(&game.id).map_or(0, HashSet::len),
+        })
+        .collect();
+
+    BlockerGraph { slots, edges }
+}
+
+impl BlockerGraph<'_> {
+    /// Renders the graph in the Graphviz DOT language.
+    ///
+    /// Slots in BK are drawn as boxes, and slots are filled according to their
+    /// inactivity color.  Edges for critical items are drawn in bold.
+    pub fn to_dot(&self, tracker: &ApTracker, now: DateTime<Utc>) -> String {
+        let mut dot = String::from("digraph blockers {\n    rankdir=LR;\n");
+
+        for slot in &self.slots {
+            let mut label = slot.game.name.clone();
+
+            if let Some(username) = &slot.game.effective_discord_username {
+                label.push_str(&format!("\n({username})"));
+            }
+
+            let fill = match slot.game.inactivity_color(tracker, now) {
+                InactivityColor::Green => "#d1e7dd",
+                InactivityColor::Yellow => "#fff3cd",
+                InactivityColor::Red => "#f8d7da",
+            };
+
+            writeln!(
+                dot,
+                "    g{} [label=\"{}\", shape={}, style=filled, fillcolor=\"{fill}\"];",
+                slot.game.id,
+                dot_escape(&label),
+                if slot.blocked { "box" } else { "ellipse" },
+            )
+            .unwrap();
+        }
+
+        for edge in &self.edges {
+            let Some(receiver) = edge.receiver_game_id else {
+                continue;
+            };
+
+            writeln!(
+                dot,
+                "    g{receiver} -> g{} [label=\"{}\"{}];",
+                edge.finder_game_id,
+                dot_escape(&edge.item),
+                if edge.classification == HintClassification::Critical {
+                    ", style=bold"
+                } else {
+                    ""
+                },
+            )
+            .unwrap();
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Escapes a string for use in a quoted DOT identifier.
+fn dot_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Renders a DOT graph as SVG using the Graphviz `dot` command.
+pub async fn render_dot_svg(dot: String) -> std::io::Result<Vec<u8>> {
+    let mut child = Command::new("dot")
+        .arg("-Tsvg")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    // Write from a separate task so that a large output can't deadlock the
+    // process by filling the stdout pipe while we're still writing.
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = tokio::spawn(async move { stdin.write_all(dot.as_bytes()).await });
+
+    let output = child.wait_with_output().await?;
+    writer.await.map_err(std::io::Error::other)??;
+
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "dot exited with {}",
+            output.status
+        )));
+    }
+
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, TimeZone};
+
+    use crate::db::model::{AvailabilityStatus, PingPreference, TrackerGameStatus};
+
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap()
+    }
+
+    fn game(id: i32, progression_status: ProgressionStatus) -> ApGame {
+        ApGame {
+            id,
+            tracker_id: 1,
+            position: id,
+            name: format!("Slot {id}"),
+            game: "Game".to_owned(),
+            tracker_status: TrackerGameStatus::Playing,
+            checks_done: 0,
+            checks_total: 10,
+            last_activity: None,
+            discord_username: None,
+            discord_ping: PingPreference::Never,
+            last_checked: None,
+            notes: String::new(),
+            claimed_by_ct_user_id: None,
+            availability_status: AvailabilityStatus::Open,
+            completion_status: CompletionStatus::Incomplete,
+            progression_status,
+            locked: false,
+            effective_discord_username: None,
+            user_is_away: false,
+            user_away_until: None,
+            user_away_message: None,
+        }
+    }
+
+    fn hint(id: i32, receiver_game_id: i32, classification: HintClassification) -> ApHint {
+        ApHint {
+            id,
+            finder_game_id: 1,
+            receiver_game_id: Some(receiver_game_id),
+            item: format!("Item {id}"),
+            location: String::new(),
+            entrance: String::new(),
+            found: false,
+            classification,
+            item_link_name: String::new(),
+            notes: String::new(),
+            acknowledged_by_ct_user_id: None,
+            created_at: None,
+            found_at: None,
+            updated_at: None,
+        }
+    }
+
+    fn ranked_ids(games: &[ApGame], hints: &[ApHint]) -> Vec<i32> {
+        rank_todo_hints(1, games, hints)
+            .iter()
+            .map(|t| t.hint.id)
+            .collect()
+    }
+
+    #[test]
+    fn todo_hints_are_ranked_by_classification_first() {
+        let games = [
+            game(1, ProgressionStatus::Unknown),
+            game(2, ProgressionStatus::Bk),
+        ];
+        let hints = [
+            hint(1, 2, HintClassification::Trash),
+            hint(2, 2, HintClassification::Qol),
+            hint(3, 2, HintClassification::Unset),
+            hint(4, 2, HintClassification::Progression),
+            hint(5, 2, HintClassification::Critical),
+        ];
+
+        assert_eq!(ranked_ids(&games, &hints), [5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn todo_hints_are_ranked_by_receiver_progression_then_activity_then_age() {
+        let mut recent = game(5, ProgressionStatus::Unblocked);
+        recent.last_activity = Some(now() - TimeDelta::minutes(5));
+        let mut stale = game(6, ProgressionStatus::Unblocked);
+        stale.last_activity = Some(now() - TimeDelta::days(1));
+
+        let games = [
+            game(1, ProgressionStatus::Unknown),
+            game(2, ProgressionStatus::Go),
+            game(3, ProgressionStatus::SoftBk),
+            game(4, ProgressionStatus::Bk),
+            recent,
+            stale,
+        ];
+
+        let mut newer = hint(1, 2, HintClassification::Progression);
+        newer.created_at = Some(now());
+        let mut older = hint(2, 2, HintClassification::Progression);
+        older.created_at = Some(now() - TimeDelta::hours(1));
+
+        let hints = [
+            newer,
+            older,
+            hint(3, 3, HintClassification::Progression),
+            hint(4, 4, HintClassification::Progression),
+            hint(5, 6, HintClassification::Progression),
+            hint(6, 5, HintClassification::Progression),
+        ];
+
+        assert_eq!(ranked_ids(&games, &hints), [4, 3, 6, 5, 2, 1]);
+    }
+
+    #[test]
+    fn todo_hints_omit_found_hints_finished_receivers_and_other_finders() {
+        let mut goaled = game(3, ProgressionStatus::Bk);
+        goaled.completion_status = CompletionStatus::Goal;
+
+        let games = [
+            game(1, ProgressionStatus::Unknown),
+            game(2, ProgressionStatus::Bk),
+            goaled,
+        ];
+
+        let mut found = hint(1, 2, HintClassification::Critical);
+        found.found = true;
+        let mut other_finder = hint(2, 2, HintClassification::Critical);
+        other_finder.finder_game_id = 2;
+        let mut outside_receiver = hint(4, 2, HintClassification::Trash);
+        outside_receiver.receiver_game_id = None;
+
+        let hints = [
+            found,
+            other_finder,
+            hint(3, 3, HintClassification::Critical),
+            outside_receiver,
+            hint(5, 2, HintClassification::Critical),
+        ];
+
+        let todo = rank_todo_hints(1, &games, &hints);
+
+        assert_eq!(todo.iter().map(|t| t.hint.id).collect::<Vec<_>>(), [5, 4]);
+        assert_eq!(todo[0].receiver.map(|r| r.id), Some(2));
+        assert!(todo[1].receiver.is_none());
+    }
+}
@@ -0,0 +1,34 @@
+//! Scheduled away periods.
+//!
+//! Marks users whose scheduled away period has started as away, and users
+//! whose away period has ended as back.  Starting an away period clears its
+//! start time, so users can still mark themselves as back early.
+
+use chrono::Utc;
+
+use crate::{
+    db::{DataAccess, DataAccessProvider},
+    logging::log,
+    state::AppState,
+};
+
+/// Starts and ends all due away periods.
+pub async fn run<D>(state: &AppState<D>) -> Result<(), sqlx::Error>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let now = Utc::now();
+
+    let mut db = state.data_provider.create_data_access().await?;
+
+    // A period that both started and ended since the last run is ended without
+    // ever marking the user as away.
+    let ended = db.end_scheduled_away_periods(now).await?;
+    let started = db.begin_scheduled_away_periods(now).await?;
+
+    if ended > 0 || started > 0 {
+        log!("Started {started} and ended {ended} scheduled away periods");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,172 @@
+//! Automatic claim expiry.
+//!
+//! Looks for claimed slots in trackers with a claim expiry period that have had
+//! no activity or check-in (based on the later of `last_activity` and
+//! `last_checked`) for longer than that period.  Slots that have never had
+//! activity or been checked expire based on when they were claimed.  The
+//! claims on these slots, including any co-claims, are removed and the slots
+//! are marked as open.  If the tracker is configured to do so, the former
+//! claimant is notified.
+//!
+//! Locked slots, finished slots and slots claimed by users who are away are
+//! skipped.
+
+use std::future::ready;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::TryStreamExt;
+
+use crate::{
+    db::{
+        DataAccess, DataAccessProvider, Transactable, Transaction, create_audit_for,
+        model::{ApGameIden, AvailabilityStatus, ExpiredClaim, PingPreference},
+    },
+    logging::log,
+    notify::{self, Event, Notification, Recipient, TrackerReference},
+    send_hack::{send_future, send_stream},
+    state::AppState,
+};
+
+/// Removes all expired claims.
+pub async fn run<D>(state: &AppState<D>) -> Result<(), sqlx::Error>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let Some(config) = &state.claim_expiry else {
+        return Ok(());
+    };
+
+    let now = Utc::now();
+
+    let mut db = state.data_provider.create_data_access().await?;
+
+    let expired: Vec<_> = send_stream(db.get_expired_claims(now, now - config.max_tracker_age))
+        .try_collect()
+        .await?;
+
+    for claim in expired {
+        let notification = claim
+            .claim_expiry_notify
+            .then(|| build_notification(state, claim.clone()));
+
+        if !expire_claim(&mut db, &claim, notification.as_ref(), now).await? {
+            continue;
+        }
+
+        let Some(notification) = notification else {
+            continue;
+        };
+
+        // The claim has already been removed, so there is nothing to retry.
+        if let Err(e) = state.notifier.notify(&notification).await {
+            log!(
+                "Failed to deliver claim expiry notification for game {}: {e}",
+                claim.ap_game_id
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the claim on a slot, unless the slot's claim or activity changed
+/// since the expired claim was found.  Returns whether the claim was removed.
+///
+/// If a notification is given, it is recorded in the inbox of its recipients
+/// along with the removal.
+async fn expire_claim(
+    db: &mut (impl Transactable + Send),
+    claim: &ExpiredClaim,
+    notification: Option<&Notification>,
+    now: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    let Some(old_game) = tx.get_ap_game(claim.ap_game_id).await? else {
+        return Ok(false);
+    };
+
+    // Slots without activity have been inactive since they were claimed, which
+    // is what the expired claim records in that case.
+    let expires_at = old_game.last_active_at().unwrap_or(claim.inactive_since)
+        + TimeDelta::days(claim.claim_expiry_days.into());
+
+    if old_game.claimed_by_ct_user_id != claim.claimant_ct_user_id
+        || old_game.locked
+        || expires_at > now
+    {
+        return Ok(false);
+    }
+
+    let mut game = old_game.clone();
+    game.claimed_by_ct_user_id = None;
+    game.discord_username = None;
+    game.discord_ping = PingPreference::Never;
+    game.availability_status = AvailabilityStatus::Open;
+
+    let audit = create_audit_for(None, None, now, &old_game, &game);
+
+    tx.delete_ap_game_claimants_by_game_id(game.id).await?;
+
+    // Only removing the claim if it's still held by the same claimant ensures
+    // that a concurrent claim change isn't clobbered.
+    if tx
+        .update_ap_game_if_claimed_by(
+            game,
+            &[
+                ApGameIden::ClaimedByCtUserId,
+                ApGameIden::DiscordUsername,
+                ApGameIden::DiscordPing,
+                ApGameIden::AvailabilityStatus,
+            ],
+            old_game.claimed_by_ct_user_id,
+            old_game.discord_username.as_deref(),
+        )
+        .await?
+        .is_none()
+    {
+        return Ok(false);
+    }
+
+    send_stream(tx.create_audits(audit))
+        .try_for_each(|_| ready(Ok(())))
+        .await?;
+
+    if let Some(notification) = notification {
+        send_future(notify::record(
+            &mut tx,
+            old_game.tracker_id,
+            notification.recipients.iter().filter_map(|r| r.ct_user_id),
+            &notification.event,
+            now,
+        ))
+        .await?;
+    }
+
+    send_future(tx.commit()).await?;
+
+    Ok(true)
+}
+
+/// Builds the notification for an expired claim, addressed to the slot's former
+/// claimant.
+fn build_notification<D>(state: &AppState<D>, claim: ExpiredClaim) -> Notification {
+    Notification {
+        recipients: vec![Recipient::new(
+            claim.claimant_ct_user_id,
+            claim.claimant_discord_username,
+            claim.claimant_discord_user_id,
+        )],
+        event: Event::ClaimExpired {
+            tracker: TrackerReference::new(
+                &state.public_url,
+                claim.tracker_id.into(),
+                claim.tracker_title,
+            ),
+            game_id: claim.ap_game_id,
+            game_name: claim.game_name,
+            expiry_days: claim.claim_expiry_days,
+            inactive_since: claim.inactive_since,
+        },
+    }
+}
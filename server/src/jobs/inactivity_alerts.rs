@@ -0,0 +1,128 @@
+//! Inactivity threshold alerts.
+//!
+//! Looks for claimed slots that have been inactive (based on the later of
+//! `last_activity` and `last_checked`) for longer than one of their tracker's
+//! inactivity thresholds, and notifies the slot's claimant and the tracker's
+//! owner.
+//!
+//! Each threshold is alerted once per period of inactivity: after a slot has
+//! been alerted for a threshold, it will only be alerted for that threshold
+//! again once it has had activity and then crossed the threshold again.  The
+//! configured cooldown additionally limits how often this can happen for slots
+//! that repeatedly become active for a short time.
+//!
+//! Finished slots, slots that have never had any activity, and slots claimed
+//! by users who are away are skipped.
+//!
+//! Alerts are recorded in the in-app notification inbox of their recipients
+//! and, unless configured otherwise, delivered through the notifier.  An alert
+//! is only recorded once it has been delivered, so alerts that fail to be
+//! delivered are retried on the next run.
+
+use chrono::Utc;
+use futures::TryStreamExt;
+
+use crate::{
+    db::{
+        DataAccess, DataAccessProvider, Transactable, Transaction,
+        model::{InactivityAlert, PendingInactivityAlert},
+    },
+    logging::log,
+    notify::{self, Event, Notification, Recipient, TrackerReference},
+    send_hack::{send_future, send_stream},
+    state::AppState,
+};
+
+/// Raises alerts for all slots that are due for one.
+pub async fn run<D>(state: &AppState<D>) -> Result<(), sqlx::Error>
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    let Some(config) = &state.inactivity_alerts else {
+        return Ok(());
+    };
+
+    let now = Utc::now();
+
+    let mut db = state.data_provider.create_data_access().await?;
+
+    let pending: Vec<_> = send_stream(
+        db.get_pending_inactivity_alerts(now - config.cooldown, now - config.max_tracker_age),
+    )
+    .try_collect()
+    .await?;
+
+    for alert in pending {
+        let ap_game_id = alert.ap_game_id;
+        let inactivity_color = alert.inactivity_color;
+        let notification = build_notification(state, alert);
+
+        if config.deliver
+            && let Err(e) = state.notifier.notify(&notification).await
+        {
+            log!("Failed to deliver inactivity alert for game {ap_game_id}: {e}");
+            continue;
+        }
+
+        let mut tx = db.begin().await?;
+
+        send_future(tx.upsert_inactivity_alert(InactivityAlert {
+            ap_game_id,
+            inactivity_color,
+            alerted_at: now,
+        }))
+        .await?;
+
+        if let Some(game) = send_future(tx.get_ap_game(ap_game_id)).await? {
+            send_future(notify::record(
+                &mut tx,
+                game.tracker_id,
+                notification.recipients.iter().filter_map(|r| r.ct_user_id),
+                &notification.event,
+                now,
+            ))
+            .await?;
+        }
+
+        send_future(tx.commit()).await?;
+    }
+
+    Ok(())
+}
+
+/// Builds the notification for a pending alert, addressed to the slot's
+/// claimant and the tracker's owner.
+fn build_notification<D>(state: &AppState<D>, alert: PendingInactivityAlert) -> Notification {
+    let mut recipients = vec![Recipient::new(
+        alert.claimant_ct_user_id,
+        alert.claimant_discord_username,
+        alert.claimant_discord_user_id,
+    )];
+
+    // Don't notify the owner twice if they claimed the slot themselves.
+    if let (Some(id), Some(username)) = (alert.owner_ct_user_id, alert.owner_discord_username)
+        && alert.claimant_ct_user_id != Some(id)
+    {
+        recipients.push(Recipient::new(
+            Some(id),
+            username,
+            alert.owner_discord_user_id,
+        ));
+    }
+
+    Notification {
+        recipients,
+        event: Event::InactivityThresholdCrossed {
+            tracker: TrackerReference::new(
+                &state.public_url,
+                alert.tracker_id.into(),
+                alert.tracker_title,
+            ),
+            game_id: alert.ap_game_id,
+            game_name: alert.game_name,
+            inactivity_color: alert.inactivity_color,
+            threshold_hours: alert.threshold_hours,
+            inactive_since: alert.inactive_since,
+        },
+    }
+}
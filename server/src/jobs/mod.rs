@@ -0,0 +1,68 @@
+//! Background jobs.
+//!
+//! Jobs run periodically for as long as the service is running.  A job that
+//! fails logs the error and is retried at its next scheduled run.
+
+use std::{fmt::Display, future::Future, sync::Arc};
+
+use tokio::time::MissedTickBehavior;
+
+use crate::{db::DataAccessProvider, logging::log, state::AppState};
+
+pub mod away_schedule;
+pub mod claim_expiry;
+pub mod inactivity_alerts;
+
+/// Starts all background jobs enabled by the service configuration.
+pub fn start<D>(state: &Arc<AppState<D>>)
+where
+    D: DataAccessProvider + Send + Sync + 'static,
+{
+    {
+        let state = state.clone();
+        spawn_periodic("away schedule", state.away_schedule_interval, move || {
+            let state = state.clone();
+            async move { away_schedule::run(&state).await }
+        });
+    }
+
+    if let Some(config) = &state.inactivity_alerts {
+        let state = state.clone();
+        spawn_periodic("inactivity alerts", config.interval, move || {
+            let state = state.clone();
+            async move { inactivity_alerts::run(&state).await }
+        });
+    }
+
+    if let Some(config) = &state.claim_expiry {
+        let state = state.clone();
+        spawn_periodic("claim expiry", config.interval, move || {
+            let state = state.clone();
+            async move { claim_expiry::run(&state).await }
+        });
+    }
+}
+
+/// Spawns a task that runs `job` every `interval`.
+///
+/// The first run happens immediately.  If a run takes longer than `interval`,
+/// the next run is delayed instead of running several times in a row.
+fn spawn_periodic<F, Fut, E>(name: &'static str, interval: chrono::Duration, mut job: F)
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), E>> + Send,
+    E: Display,
+{
+    let mut interval = tokio::time::interval(interval.to_std().unwrap());
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = job().await {
+                log!("Background job {name} failed: {e}");
+            }
+        }
+    });
+}
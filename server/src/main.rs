@@ -24,7 +24,11 @@ mod auth;
 mod conf;
 mod db;
 mod diff;
+mod forecast;
+mod hints;
+mod jobs;
 mod logging;
+mod notify;
 mod send_hack;
 mod signal;
 mod state;
@@ -43,8 +47,9 @@ async fn create_router_from_config(
             let data_provider = sqlx::PgPool::connect(connection_string).await?;
             data_provider.migrate().await?;
             log!("Migrations completed successfully.");
-            api::create_router(Arc::new(AppState::new(config, data_provider)))
-                .layer(client_ip_source.into_extension())
+            let state = Arc::new(AppState::new(config, data_provider));
+            jobs::start(&state);
+            api::create_router(state).layer(client_ip_source.into_extension())
         }
     })
 }
@@ -0,0 +1,386 @@
+//! User notifications.
+//!
+//! Events that users should be made aware of are described by a
+//! [`Notification`] and delivered by a [`Notifier`].  The notifier used is
+//! selected by the service configuration.
+//!
+//! Events can also be recorded in the in-app notification inbox of users with
+//! [`record`], independently of external delivery.
+
+use std::{collections::HashSet, future::ready};
+
+use chrono::{DateTime, Utc};
+use futures::{TryStreamExt, future::BoxFuture};
+use serde::Serialize;
+use url::Url;
+
+use crate::{
+    ap_api::UrlEncodedUuid,
+    conf,
+    db::{
+        DataAccess,
+        model::{CtUserNotificationInsertion, InactivityColor},
+    },
+    logging::log,
+    send_hack::send_stream,
+};
+
+/// Errors that may occur when delivering a notification.
+#[derive(Debug, thiserror::Error)]
+pub enum NotifyError {
+    /// The HTTP request used to deliver the notification failed.
+    #[error("HTTP request failed: {0}")]
+    Http(
+        #[from]
+        #[source]
+        reqwest::Error,
+    ),
+}
+
+/// A user that should receive a notification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Recipient {
+    /// The ID of the user, if the recipient is a registered user.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ct_user_id: Option<i32>,
+    /// The recipient's Discord username.
+    pub discord_username: String,
+    /// The recipient's Discord user ID, if known.
+    ///
+    /// This is serialized as a string since JavaScript cannot represent all
+    /// Discord user IDs as numbers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discord_user_id: Option<String>,
+}
+
+impl Recipient {
+    /// Creates a recipient.  `discord_user_id` is the ID as stored in the
+    /// database.
+    pub fn new(
+        ct_user_id: Option<i32>,
+        discord_username: String,
+        discord_user_id: Option<i64>,
+    ) -> Self {
+        Self {
+            ct_user_id,
+            discord_username,
+            discord_user_id: discord_user_id.map(|id| (id as u64).to_string()),
+        }
+    }
+}
+
+/// A tracker referenced by an [`Event`].
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackerReference {
+    pub tracker_id: UrlEncodedUuid,
+    pub title: String,
+    /// Link to the tracker in the frontend.
+    pub url: Url,
+}
+
+impl TrackerReference {
+    /// Creates a reference to a tracker, linking to it relative to the
+    /// instance's public URL.
+    pub fn new(public_url: &Url, tracker_id: UrlEncodedUuid, title: String) -> Self {
+        Self {
+            url: public_url
+                .join(&format!("tracker/{}", tracker_id.as_str()))
+                .unwrap(),
+            tracker_id,
+            title,
+        }
+    }
+}
+
+/// Something that happened that users should be notified about.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// A claimed slot has been inactive for longer than one of its tracker's
+    /// inactivity thresholds.
+    InactivityThresholdCrossed {
+        tracker: TrackerReference,
+        game_id: i32,
+        game_name: String,
+        /// The color of the threshold that was crossed.
+        inactivity_color: InactivityColor,
+        /// The threshold that was crossed, in hours.
+        threshold_hours: i32,
+        /// When the slot last had activity, or `None` if it never had any.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        inactive_since: Option<DateTime<Utc>>,
+    },
+    /// The claim on a slot was removed because the slot had no activity or
+    /// check-in for longer than its tracker's claim expiry period.
+    ClaimExpired {
+        tracker: TrackerReference,
+        game_id: i32,
+        game_name: String,
+        /// The tracker's claim expiry period, in days.
+        expiry_days: i32,
+        /// When the slot last had activity or was checked.
+        inactive_since: DateTime<Utc>,
+    },
+    /// A hint that one of the recipient's slots has to find was classified as
+    /// critical.
+    HintClassifiedCritical {
+        tracker: TrackerReference,
+        hint_id: i32,
+        item: String,
+        finder_game_name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        receiver_game_name: Option<String>,
+        /// The user who classified the hint.
+        classified_by: String,
+    },
+    /// The recipient's claim on a slot was removed or given to someone else by
+    /// another user.
+    ClaimTakenOver {
+        tracker: TrackerReference,
+        game_id: i32,
+        game_name: String,
+        /// The user who changed the claim.
+        changed_by: String,
+        /// The new claimant, or `None` if the slot is no longer claimed.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        new_claimant: Option<String>,
+    },
+}
+
+impl Event {
+    /// Describes the event in a human-readable form.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::InactivityThresholdCrossed {
+                tracker,
+                game_name,
+                threshold_hours,
+                inactive_since,
+                ..
+            } => match inactive_since {
+                Some(_) => format!(
+                    "{game_name} in {} has had no activity for over {threshold_hours} hours: {}",
+                    tracker.title, tracker.url
+                ),
+                None => format!(
+                    "{game_name} in {} has not had any activity yet: {}",
+                    tracker.title, tracker.url
+                ),
+            },
+            Self::ClaimExpired {
+                tracker,
+                game_name,
+                expiry_days,
+                ..
+            } => format!(
+                "Your claim on {game_name} in {} was released after {expiry_days} days without \
+                activity: {}",
+                tracker.title, tracker.url
+            ),
+            Self::HintClassifiedCritical {
+                tracker,
+                item,
+                finder_game_name,
+                receiver_game_name,
+                classified_by,
+                ..
+            } => match receiver_game_name {
+                Some(receiver) => format!(
+                    "{classified_by} marked {receiver}'s {item} from {finder_game_name} in {} as \
+                    critical: {}",
+                    tracker.title, tracker.url
+                ),
+                None => format!(
+                    "{classified_by} marked {item} from {finder_game_name} in {} as critical: {}",
+                    tracker.title, tracker.url
+                ),
+            },
+            Self::ClaimTakenOver {
+                tracker,
+                game_name,
+                changed_by,
+                new_claimant,
+                ..
+            } => match new_claimant {
+                Some(claimant) => format!(
+                    "{changed_by} gave your claim on {game_name} in {} to {claimant}: {}",
+                    tracker.title, tracker.url
+                ),
+                None => format!(
+                    "{changed_by} removed your claim on {game_name} in {}: {}",
+                    tracker.title, tracker.url
+                ),
+            },
+        }
+    }
+}
+
+/// An event together with the users that should be notified about it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub recipients: Vec<Recipient>,
+    pub event: Event,
+}
+
+/// Records an event in the in-app notification inbox of the given users.
+///
+/// Users who muted the tracker the event happened in are skipped.
+pub async fn record(
+    db: &mut (impl DataAccess + Send),
+    ap_tracker_id: i32,
+    ct_user_ids: impl IntoIterator<Item = i32>,
+    event: &Event,
+    now: DateTime<Utc>,
+) -> sqlx::Result<()> {
+    let mut ct_user_ids: Vec<_> = ct_user_ids.into_iter().collect();
+    ct_user_ids.sort_unstable();
+    ct_user_ids.dedup();
+
+    if ct_user_ids.is_empty() {
+        return Ok(());
+    }
+
+    let muted: HashSet<_> =
+        send_stream(db.get_ap_tracker_notification_mutes(ap_tracker_id, &ct_user_ids))
+            .map_ok(|m| m.ct_user_id)
+            .try_collect()
+            .await?;
+
+    let event_json = serde_json::to_string(event).unwrap();
+    let message = event.describe();
+
+    let notifications = ct_user_ids
+        .into_iter()
+        .filter(|id| !muted.contains(id))
+        .map(|ct_user_id| CtUserNotificationInsertion {
+            ct_user_id,
+            ap_tracker_id: Some(ap_tracker_id),
+            created_at: now,
+            read_at: None,
+            event: event_json.clone(),
+            message: message.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    send_stream(db.create_ct_user_notifications(notifications))
+        .try_for_each(|_| ready(Ok(())))
+        .await
+}
+
+/// Delivers notifications.
+pub trait Notifier: Send + Sync {
+    /// Delivers a notification to its recipients.
+    fn notify<'a>(
+        &'a self,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), NotifyError>>;
+}
+
+/// Creates the notifier selected by the service configuration.
+pub fn from_config(config: conf::Notifier, client: reqwest::Client) -> Box<dyn Notifier> {
+    match config {
+        conf::Notifier::Log => Box::new(LogNotifier),
+        conf::Notifier::Webhook { url } => Box::new(WebhookNotifier { client, url }),
+        conf::Notifier::DiscordWebhook { url } => Box::new(DiscordWebhookNotifier { client, url }),
+    }
+}
+
+/// Writes notifications to the log.
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn notify<'a>(
+        &'a self,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), NotifyError>> {
+        let recipients = notification
+            .recipients
+            .iter()
+            .map(|r| r.discord_username.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        log!(
+            "Notification for {recipients}: {}",
+            notification.event.describe()
+        );
+
+        Box::pin(std::future::ready(Ok(())))
+    }
+}
+
+/// Sends notifications as JSON in a POST request to a URL.
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify<'a>(
+        &'a self,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), NotifyError>> {
+        Box::pin(async move {
+            self.client
+                .post(self.url.clone())
+                .json(notification)
+                .send()
+                .await?
+                .error_for_status()?;
+
+            Ok(())
+        })
+    }
+}
+
+/// Posts notifications to a Discord channel using a webhook.
+///
+/// Recipients with a known Discord user ID are mentioned.  Mentions are
+/// restricted to those users, so event details can't mention anyone else.
+pub struct DiscordWebhookNotifier {
+    client: reqwest::Client,
+    url: Url,
+}
+
+/// Request body for executing a Discord webhook.
+#[derive(Serialize)]
+struct DiscordWebhookMessage<'a> {
+    content: String,
+    allowed_mentions: DiscordAllowedMentions<'a>,
+}
+
+#[derive(Serialize)]
+struct DiscordAllowedMentions<'a> {
+    parse: [&'a str; 0],
+    users: Vec<&'a str>,
+}
+
+impl Notifier for DiscordWebhookNotifier {
+    fn notify<'a>(
+        &'a self,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), NotifyError>> {
+        Box::pin(async move {
+            let users: Vec<_> = notification
+                .recipients
+                .iter()
+                .filter_map(|r| r.discord_user_id.as_deref())
+                .collect();
+
+            let mut content: String = users.iter().map(|id| format!("<@{id}> ")).collect();
+            content.push_str(&notification.event.describe());
+
+            self.client
+                .post(self.url.clone())
+                .json(&DiscordWebhookMessage {
+                    content,
+                    allowed_mentions: DiscordAllowedMentions { parse: [], users },
+                })
+                .send()
+                .await?
+                .error_for_status()?;
+
+            Ok(())
+        })
+    }
+}
@@ -13,7 +13,6 @@ use arrayvec::ArrayVec;
 use axum::http::HeaderValue;
 use chrono::{DateTime, TimeDelta, Utc};
 use futures::TryStreamExt;
-use jsonwebtoken::Header;
 use serde::{Deserialize, Deserializer, de::Visitor};
 use tokio::time::{error::Elapsed, timeout};
 use url::Url;
@@ -22,22 +21,36 @@ use uuid::Uuid;
 use crate::{
     ap_api::UrlEncodedUuid,
     api::UiSettings,
-    auth::{discord::AuthClient, token::TokenProcessor},
-    conf::Config,
+    auth::{
+        api_key::ApiKeyGrant, discord::AuthClient, oidc::OidcAuthClient, token::TokenProcessor,
+    },
+    conf::{ClaimExpiry, Config, InactivityAlerts},
     db::{
         DataAccess, DataAccessProvider, Transactable, Transaction, create_audit_for,
         model::{
             ApGameIden, ApGameInsertion, ApHintIden, ApHintInsertion, ApTrackerIden,
-            ApTrackerInsertion, AvailabilityStatus, CompletionStatus, HintClassification,
-            PingPreference, ProgressionStatus, UpdateCompletionStatus,
+            ApTrackerInsertion, AvailabilityStatus, ChecksHistoryInsertion, CompletionStatus,
+            CtUserSession, HintClassification, PingPreference, ProgressionStatus,
+            UpdateCompletionStatus,
         },
     },
     logging::log,
+    notify::{self, Notifier},
     send_hack::{send_future, send_stream},
     stream::try_into_grouping_map_by,
     tracker::{Checks, Game, Hint, ParseTrackerError, parse_tracker_html},
 };
 
+/// How long to wait for a response from an upstream tracker or room.
+///
+/// Tracker updates keep their transaction open while waiting, so their changes
+/// may only become visible up to this long after the time they are stamped
+/// with.
+pub const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The maximum number of entries in each of the session and API key caches.
+const AUTH_CACHE_CAPACITY: u64 = 10_000;
+
 #[derive(Debug, thiserror::Error)]
 pub enum TrackerUrlParseError {
     #[error("failed to parse URL: {0}")]
@@ -183,8 +196,35 @@ pub struct AppState<D> {
 
     /// Discord authentication client.
     pub auth_client: AuthClient,
+    /// OpenID Connect authentication client, if enabled.
+    pub oidc_auth_client: Option<OidcAuthClient>,
     /// Authentication token processor.
     pub token_processor: TokenProcessor,
+    /// Recently looked-up sessions, keyed by `jti`.
+    ///
+    /// Entries are invalidated when a session is revoked through this server.
+    /// The short time-to-live bounds how long a session revoked elsewhere can
+    /// still be used.
+    pub session_cache: moka::future::Cache<Uuid, CtUserSession>,
+    /// Recently looked-up API keys, keyed by the hash of their secret.
+    ///
+    /// Entries are invalidated when a key is deleted.  A key's `last_used_at`
+    /// is updated whenever it is looked up, so it is accurate to within the
+    /// time-to-live.
+    pub api_key_cache: moka::future::Cache<String, ApiKeyGrant>,
+
+    /// The public URL of the frontend.
+    pub public_url: Url,
+    /// Delivers notifications to users.
+    pub notifier: Box<dyn Notifier>,
+    /// Inactivity alert configuration, if enabled.
+    pub inactivity_alerts: Option<InactivityAlerts>,
+    /// Claim expiry configuration, if enabled.
+    pub claim_expiry: Option<ClaimExpiry>,
+    /// How long an offer to transfer ownership of a tracker remains valid.
+    pub ownership_transfer_expiry: chrono::Duration,
+    /// How often to start and end users' scheduled away periods.
+    pub away_schedule_interval: chrono::Duration,
 
     /// Valid upstream trackers; maps URL prefix to AP hostname.
     upstream_trackers: HashMap<Url, String>,
@@ -209,8 +249,25 @@ impl<D> AppState<D> {
     /// Create the global state from the given service configuration value and
     /// data access provider.
     pub fn new(config: Config, data_provider: D) -> Self {
+        let reqwest_client = reqwest::Client::builder().build().unwrap();
+
+        let oidc_provider = config.oidc.as_ref().map(|oidc| oidc.display_name.clone());
+        let oidc_auth_client = config.oidc.map(|oidc| {
+            OidcAuthClient::new(
+                oidc,
+                &config.public_url,
+                config.discord.token_cipher.clone(),
+                reqwest_client.clone(),
+            )
+        });
+
         Self {
-            reqwest_client: reqwest::Client::builder().build().unwrap(),
+            notifier: notify::from_config(config.notifier, reqwest_client.clone()),
+            inactivity_alerts: config.inactivity_alerts,
+            claim_expiry: config.claim_expiry,
+            ownership_transfer_expiry: config.ownership_transfer_expiry,
+            away_schedule_interval: config.away_schedule_interval,
+            reqwest_client,
             data_provider,
             upstream_trackers: config
                 .upstream_trackers
@@ -226,6 +283,7 @@ impl<D> AppState<D> {
                 banners: config.banners,
                 hoster: config.hoster,
                 build_version: &GIT_COMMIT_ID,
+                oidc_provider,
             })
             .unwrap()
             .parse()
@@ -234,18 +292,24 @@ impl<D> AppState<D> {
                 .time_to_live(config.tracker_update_interval.to_std().unwrap())
                 .build(),
             tracker_update_interval: config.tracker_update_interval,
+            oidc_auth_client,
             auth_client: AuthClient::new(
                 config.discord.client_id,
                 config.discord.client_secret,
                 &config.public_url,
                 config.discord.token_cipher,
             ),
-            token_processor: TokenProcessor::new(
-                Header::new(config.token.algorithm),
-                &config.token.secret,
-                config.token.issuer,
-                config.token.validity_duration,
-            ),
+            token_processor: TokenProcessor::new(config.token)
+                .expect("invalid token configuration"),
+            session_cache: moka::future::Cache::builder()
+                .max_capacity(AUTH_CACHE_CAPACITY)
+                .time_to_live(Duration::from_secs(30))
+                .build(),
+            api_key_cache: moka::future::Cache::builder()
+                .max_capacity(AUTH_CACHE_CAPACITY)
+                .time_to_live(Duration::from_secs(30))
+                .build(),
+            public_url: config.public_url,
         }
     }
 
@@ -371,6 +435,8 @@ impl<D> AppState<D> {
                         inactivity_threshold_yellow_hours: 24,
                         inactivity_threshold_red_hours: 48,
                         require_authentication_to_claim: false,
+                        claim_expiry_days: None,
+                        claim_expiry_notify: true,
                     }]));
 
                     tokio::pin!(trackers);
@@ -411,8 +477,11 @@ impl<D> AppState<D> {
                         last_checked: None,
                         notes: String::new(),
                         claimed_by_ct_user_id: None,
+                        locked: false,
                         effective_discord_username: None,
                         user_is_away: false,
+                        user_away_until: None,
+                        user_away_message: None,
                     };
 
                     game.update_completion_status();
@@ -457,6 +526,13 @@ impl<D> AppState<D> {
                         entrance: hint.entrance,
                         found: hint.found,
                         classification: HintClassification::Unset,
+                        notes: String::new(),
+                        acknowledged_by_ct_user_id: None,
+                        created_at: Some(now),
+                        // We can't know when hints that are already found were
+                        // found.
+                        found_at: None,
+                        updated_at: Some(now),
                     };
 
                     send_stream(db.create_ap_hints([ap_hint]))
@@ -545,11 +621,27 @@ impl<D> AppState<D> {
 
                     let audit = create_audit_for(None, None, now, &old_db_game, &db_game);
 
+                    // Record changes in the check count separately so that the
+                    // checks history can be served without having to scan and
+                    // decode every audit for the tracker.
+                    let checks_history = (db_game.checks_done != old_db_game.checks_done)
+                        .then_some(ChecksHistoryInsertion {
+                            ap_tracker_id: tracker.id,
+                            ap_game_id: db_game.id,
+                            changed_at: now,
+                            previous_checks_done: old_db_game.checks_done,
+                            checks_done: db_game.checks_done,
+                        });
+
                     db.update_ap_game(db_game, &columns).await?;
 
                     send_stream(db.create_audits(audit))
                         .try_for_each(|_| ready(Ok(())))
                         .await?;
+
+                    send_stream(db.create_checks_history(checks_history))
+                        .try_for_each(|_| ready(Ok(())))
+                        .await?;
                 }
 
                 // Reconcile hints.  We need to match up the hints from the
@@ -605,10 +697,20 @@ impl<D> AppState<D> {
                             if h.found != tracker_hint.found {
                                 let old_hint = h.clone();
                                 h.found = tracker_hint.found;
+                                h.found_at = h.found.then_some(now);
+                                h.updated_at = Some(now);
 
                                 let audit = create_audit_for(None, None, now, &old_hint, &h);
 
-                                db.update_ap_hint(h, &[ApHintIden::Found]).await?;
+                                db.update_ap_hint(
+                                    h,
+                                    &[
+                                        ApHintIden::Found,
+                                        ApHintIden::FoundAt,
+                                        ApHintIden::UpdatedAt,
+                                    ],
+                                )
+                                .await?;
 
                                 send_stream(db.create_audits(audit))
                                     .try_for_each(|_| ready(Ok(())))
@@ -626,6 +728,11 @@ impl<D> AppState<D> {
                                 entrance: tracker_hint.entrance,
                                 found: tracker_hint.found,
                                 classification: HintClassification::Unset,
+                                notes: String::new(),
+                                acknowledged_by_ct_user_id: None,
+                                created_at: Some(now),
+                                found_at: None,
+                                updated_at: Some(now),
                             });
                         }
                     }
@@ -740,7 +847,7 @@ impl<D> AppState<D> {
             log!("Requesting AP tracker {url}");
 
             let sync_tracker_fut = async {
-                let html = timeout(Duration::from_secs(30), async {
+                let html = timeout(UPSTREAM_TIMEOUT, async {
                     Ok::<_, TrackerUpdateError>(
                         self.reqwest_client
                             .get(url.clone())
@@ -854,7 +961,7 @@ impl<D> AppState<D> {
         let client =
             crate::ap_api::Client::new_with_client(tracker_url, self.reqwest_client.clone());
 
-        let r = timeout(Duration::from_secs(30), client.get_room_status(room_id)).await??;
+        let r = timeout(UPSTREAM_TIMEOUT, client.get_room_status(room_id)).await??;
 
         // Set the next time to check either when the room times out, or 5
         // minutes from now, whichever is later.